@@ -10,11 +10,26 @@ async fn main() -> color_eyre::eyre::Result<()> {
 
     let config = config_load::with_default_env_var().await?;
     let _keyring_guard = config_bringup::init_keyring_if_needed(&config)?;
-    let mailboxes = config_bringup::for_monitoring(&config).await?;
+    let mailboxes = config_bringup::for_monitoring_when_unlocked(&config).await?;
     drop(config);
 
     let mut join_set = tokio::task::JoinSet::new();
 
+    let resume = Arc::new(tokio::sync::Notify::new());
+
+    // Retry the monitors that need attention on SIGHUP.
+    #[cfg(unix)]
+    {
+        let resume = Arc::clone(&resume);
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                tracing::info!(message = "resuming monitors that need attention");
+                resume.notify_waiters();
+            }
+        });
+    }
+
     monitoring_engine::spawn_monitors::<monitoring_workload_imap::Mailbox, _, _, _, _, _, _>(
         monitoring_engine::SpawnMonitorsParams {
             workload_items: &mailboxes,
@@ -42,6 +57,7 @@ async fn main() -> color_eyre::eyre::Result<()> {
             >| async move {
                 tracing::info!(label = %update.entry, status = ?update.payload, "supervisor event");
             },
+            resume,
         },
     );
 
@@ -0,0 +1,401 @@
+//! CLI utility for managing the stored credentials.
+
+use clap::Parser as _;
+use color_eyre::eyre::{Context as _, bail, eyre};
+use oauth2_token_storage_core::TokenStorage as _;
+
+mod prompt;
+
+/// Manage the stored credentials of the configured servers.
+#[derive(Debug, clap::Parser)]
+struct Cli {
+    /// The command to run.
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Credentials management commands.
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Store the login password of a server in the keyring.
+    ///
+    /// Reads the password from the TTY, or from stdin when it is not a
+    /// terminal.
+    Set {
+        /// The server name.
+        server: String,
+    },
+
+    /// Delete the stored login password or OAuth 2 tokens of a server.
+    Delete {
+        /// The server name.
+        server: String,
+    },
+
+    /// Show where the credentials of a server are stored, without the secrets.
+    ShowMetadata {
+        /// The server name.
+        server: String,
+    },
+
+    /// Connect and authenticate to a server with the stored credentials.
+    Verify {
+        /// The server name.
+        server: String,
+    },
+
+    /// Import OAuth 2 tokens for an `oauth2_session` server.
+    ///
+    /// Reads the tokens from the TTY, or from stdin when it is not a
+    /// terminal.
+    ImportToken {
+        /// The server name.
+        server: String,
+
+        /// Seconds until the imported access token expires.
+        #[arg(long)]
+        expires_in_secs: Option<u64>,
+    },
+
+    /// Revoke the OAuth 2 tokens of an `oauth2_session` server and clear them.
+    Logout {
+        /// The server name.
+        server: String,
+    },
+}
+
+/// Manage the stored credentials.
+#[tokio::main]
+async fn main() -> color_eyre::eyre::Result<()> {
+    color_eyre::install()?;
+    tracing_subscriber::fmt::init();
+
+    let cli = Cli::parse();
+
+    let config = config_load::with_default_env_var().await?;
+    let _keyring_guard = config_bringup::init_keyring_if_needed(&config)?;
+
+    match cli.command {
+        Command::Set { server } => set(find_server(&config, &server)?),
+        Command::Delete { server } => delete(find_server(&config, &server)?).await,
+        Command::ShowMetadata { server } => show_metadata(find_server(&config, &server)?).await,
+        Command::Verify { server } => verify(&config, find_server(&config, &server)?).await,
+        Command::ImportToken {
+            server,
+            expires_in_secs,
+        } => import_token(find_server(&config, &server)?, expires_in_secs).await,
+        Command::Logout { server } => logout(&config, find_server(&config, &server)?).await,
+    }
+}
+
+/// Find the server config by name.
+fn find_server<'a>(
+    config: &'a config_core::Config,
+    server_name: &str,
+) -> color_eyre::eyre::Result<&'a config_core::ServerConfig> {
+    let mut matches = config
+        .servers
+        .iter()
+        .filter(|server| server.name == server_name);
+    let server = matches
+        .next()
+        .ok_or_else(|| eyre!("No server named '{server_name}' in config"))?;
+    if matches.next().is_some() {
+        bail!("Multiple servers named '{server_name}' in config");
+    }
+    Ok(server)
+}
+
+/// The keyring location of the login password, if it is in the keyring.
+fn login_keyring(
+    server: &config_core::ServerConfig,
+) -> Option<config_bringup::keyring::ServiceAccount<'_>> {
+    match &server.auth {
+        config_core::Auth::Login(config_core::LoginCredentials {
+            password: config_core::PasswordSource::Keyring { keyring },
+            username,
+            ..
+        }) => Some(config_bringup::keyring::service_account(
+            keyring,
+            username,
+            config_bringup::keyring::DEFAULT_SERVICE,
+        )),
+        _ => None,
+    }
+}
+
+/// The OAuth 2 session config, if the server uses one.
+fn oauth2_session(server: &config_core::ServerConfig) -> Option<&config_core::OAuth2Session> {
+    match &server.auth {
+        config_core::Auth::OAuth2Session(session) => Some(session),
+        _ => None,
+    }
+}
+
+/// Store the login password.
+fn set(server: &config_core::ServerConfig) -> color_eyre::eyre::Result<()> {
+    let Some(keyring) = login_keyring(server) else {
+        bail!(
+            "Server '{}' does not use keyring credentials in config",
+            server.name
+        );
+    };
+
+    let password = prompt::new_password()?;
+    keyring_password::set(keyring.service, keyring.account, &password)
+        .wrap_err("Failed to store password in keyring")?;
+
+    println!(
+        "Stored password for server '{}' (service '{}', account '{}')",
+        server.name, keyring.service, keyring.account
+    );
+
+    Ok(())
+}
+
+/// Delete the stored login password or OAuth 2 tokens.
+async fn delete(server: &config_core::ServerConfig) -> color_eyre::eyre::Result<()> {
+    if let Some(keyring) = login_keyring(server) {
+        keyring_password::delete(keyring.service, keyring.account)
+            .wrap_err("Failed to delete password from keyring")?;
+        println!(
+            "Deleted password for server '{}' (service '{}', account '{}')",
+            server.name, keyring.service, keyring.account
+        );
+        return Ok(());
+    }
+
+    if let Some(session) = oauth2_session(server) {
+        let storage =
+            config_bringup::token_storage::init(&session.token_storage, &session.user).await?;
+        storage
+            .clear()
+            .await
+            .wrap_err("Failed to clear OAuth 2 token storage")?;
+        println!("Deleted OAuth 2 tokens for server '{}'", server.name);
+        return Ok(());
+    }
+
+    bail!(
+        "Server '{}' has no stored credentials to delete",
+        server.name
+    );
+}
+
+/// Print where the credentials are stored.
+async fn show_metadata(server: &config_core::ServerConfig) -> color_eyre::eyre::Result<()> {
+    println!("Server '{}':", server.name);
+
+    match &server.auth {
+        config_core::Auth::Login(credentials) => {
+            println!("  auth: login as '{}'", credentials.username);
+            match login_keyring(server) {
+                Some(keyring) => {
+                    println!(
+                        "  password: keyring (service '{}', account '{}')",
+                        keyring.service, keyring.account
+                    );
+                    print_keyring_entry(keyring.service, keyring.account);
+                }
+                None => println!("  password: in config"),
+            }
+        }
+        config_core::Auth::OAuth2Credentials(credentials) => {
+            println!("  auth: OAuth 2 as '{}'", credentials.user);
+            println!("  access token: in config");
+        }
+        config_core::Auth::OAuth2Session(session) => {
+            println!(
+                "  auth: OAuth 2 session as '{}' with client '{}'",
+                session.user, session.oauth2_client
+            );
+            match &session.token_storage {
+                config_core::OAuth2TokenStorage::Keyring(keyring) => {
+                    let keyring = config_bringup::keyring::service_account(
+                        keyring,
+                        &session.user,
+                        config_bringup::keyring::DEFAULT_SERVICE,
+                    );
+                    println!(
+                        "  tokens: keyring (service '{}', account '{}')",
+                        keyring.service, keyring.account
+                    );
+                }
+                config_core::OAuth2TokenStorage::File(file) => {
+                    println!("  tokens: encrypted file {}", file.path.display());
+                }
+            }
+
+            let storage =
+                config_bringup::token_storage::init(&session.token_storage, &session.user).await?;
+            match storage.load().await {
+                Ok(data) => {
+                    println!("  refresh token: stored");
+                    println!("  access token: {}", describe_expiry(data.expires_at));
+                }
+                Err(oauth2_token_storage_core::LoadError::NoData(_)) => {
+                    println!("  refresh token: not stored");
+                }
+                Err(oauth2_token_storage_core::LoadError::Internal(err)) => {
+                    println!("  refresh token: unreadable ({err})");
+                }
+            }
+        }
+        config_core::Auth::External(external) => {
+            println!(
+                "  auth: SASL EXTERNAL as '{}'",
+                external.authzid.as_deref().unwrap_or("<derived by server>")
+            );
+        }
+    }
+
+    match &server.tls.client_certificate {
+        Some(config_core::ClientCertificateSource::Files {
+            certificate_file,
+            key_file,
+        }) => println!(
+            "  client certificate: {} (key {})",
+            certificate_file.display(),
+            key_file.display()
+        ),
+        Some(config_core::ClientCertificateSource::Keyring { keyring }) => {
+            let keyring = config_bringup::keyring::service_account(
+                keyring,
+                &server.host,
+                config_bringup::keyring::DEFAULT_SERVICE,
+            );
+            println!(
+                "  client certificate: keyring (service '{}', account '{}')",
+                keyring.service, keyring.account
+            );
+            print_keyring_entry(keyring.service, keyring.account);
+        }
+        None => {}
+    }
+
+    Ok(())
+}
+
+/// Print whether the keyring entry exists, and its attributes.
+fn print_keyring_entry(service: &str, account: &str) {
+    let attributes = keyring_core::Entry::new(service, account)
+        .and_then(|entry| entry.get_credential().map(|_| entry))
+        .and_then(|entry| entry.get_attributes());
+    match attributes {
+        Ok(attributes) => {
+            println!("    stored: yes");
+            let mut attributes: Vec<_> = attributes.into_iter().collect();
+            attributes.sort();
+            for (key, value) in attributes {
+                println!("    {key}: {value}");
+            }
+        }
+        Err(keyring_core::Error::NoEntry) => println!("    stored: no"),
+        Err(err) => println!("    stored: unknown ({err})"),
+    }
+}
+
+/// Describe when the access token expires.
+fn describe_expiry(expires_at: Option<std::time::SystemTime>) -> String {
+    let Some(expires_at) = expires_at else {
+        return "does not expire".to_string();
+    };
+    match expires_at.duration_since(std::time::SystemTime::now()) {
+        Ok(remaining) => format!("expires in {}s", remaining.as_secs()),
+        Err(err) => format!("expired {}s ago", err.duration().as_secs()),
+    }
+}
+
+/// Connect and authenticate with the stored credentials.
+async fn verify(
+    config: &config_core::Config,
+    server: &config_core::ServerConfig,
+) -> color_eyre::eyre::Result<()> {
+    let config = config_core::Config {
+        servers: vec![server.clone()],
+        oauth2_clients: config.oauth2_clients.clone(),
+        keyring: config.keyring.clone(),
+        proxy: config.proxy.clone(),
+    };
+    let servers = config_bringup::servers_only(&config).await?;
+
+    for server in &servers {
+        let (mut session, _) = imap_service::connect_to_server(server)
+            .await
+            .wrap_err_with(|| format!("Failed to authenticate to '{}'", server.server_name))?;
+        session.logout().await?;
+
+        println!("Authenticated to server '{}'", server.server_name);
+    }
+
+    Ok(())
+}
+
+/// Import the OAuth 2 tokens into the session storage.
+async fn import_token(
+    server: &config_core::ServerConfig,
+    expires_in_secs: Option<u64>,
+) -> color_eyre::eyre::Result<()> {
+    let Some(session) = oauth2_session(server) else {
+        bail!("Server '{}' does not use an OAuth 2 session", server.name);
+    };
+
+    let (refresh_token, access_token) = prompt::tokens()?;
+
+    // Without an access token, mark it as expired so the first use refreshes it.
+    let (access_token, expires_at) = match access_token {
+        Some(access_token) => (
+            access_token,
+            expires_in_secs
+                .map(|secs| std::time::SystemTime::now() + std::time::Duration::from_secs(secs)),
+        ),
+        None => (String::new(), Some(std::time::SystemTime::UNIX_EPOCH)),
+    };
+
+    let mut storage =
+        config_bringup::token_storage::init(&session.token_storage, &session.user).await?;
+    oauth2_session::manage(
+        &mut storage,
+        oauth2_token_storage_core::DataRef {
+            access_token: &access_token,
+            expires_at,
+            refresh_token: &refresh_token,
+        },
+    )
+    .await
+    .wrap_err("Failed to store OAuth 2 tokens")?;
+
+    println!("Imported OAuth 2 tokens for server '{}'", server.name);
+
+    Ok(())
+}
+
+/// Revoke and clear the OAuth 2 tokens.
+async fn logout(
+    config: &config_core::Config,
+    server: &config_core::ServerConfig,
+) -> color_eyre::eyre::Result<()> {
+    let Some(session) = oauth2_session(server) else {
+        bail!("Server '{}' does not use an OAuth 2 session", server.name);
+    };
+
+    let manager = config_bringup::oauth2_session(session, &config.oauth2_clients).await?;
+    let revoked = manager
+        .logout()
+        .await
+        .wrap_err("Failed to log out of the OAuth 2 session")?;
+
+    if revoked {
+        println!(
+            "Revoked and cleared OAuth 2 tokens for server '{}'",
+            server.name
+        );
+    } else {
+        println!(
+            "Cleared OAuth 2 tokens for server '{}' (not revoked: no stored token or no revocation URL)",
+            server.name
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Secret input helpers.
+
+use std::io::{IsTerminal as _, Read as _};
+
+use color_eyre::eyre::{Context as _, bail};
+
+/// Read a new password, from the TTY without echo with a confirmation, or
+/// from stdin when it is not a terminal.
+pub fn new_password() -> color_eyre::eyre::Result<String> {
+    if !std::io::stdin().is_terminal() {
+        let password = read_stdin()?;
+        return non_empty(password.lines().next().unwrap_or_default().to_string());
+    }
+
+    let password =
+        rpassword::prompt_password("Password: ").wrap_err("Failed to read password from TTY")?;
+    let password = non_empty(password)?;
+
+    let confirmation = rpassword::prompt_password("Confirm password: ")
+        .wrap_err("Failed to read password confirmation from TTY")?;
+    if password != confirmation {
+        bail!("Passwords do not match");
+    }
+
+    Ok(password)
+}
+
+/// Read OAuth 2 tokens, from the TTY without echo, or from stdin when it is
+/// not a terminal.
+///
+/// On stdin the refresh token goes on the first line and the optional
+/// access token on the second one.
+pub fn tokens() -> color_eyre::eyre::Result<(String, Option<String>)> {
+    let (refresh_token, access_token) = if std::io::stdin().is_terminal() {
+        let refresh_token = rpassword::prompt_password("Refresh token: ")
+            .wrap_err("Failed to read refresh token from TTY")?;
+        let access_token =
+            rpassword::prompt_password("Access token (leave empty to refresh on first use): ")
+                .wrap_err("Failed to read access token from TTY")?;
+        (refresh_token, access_token)
+    } else {
+        let input = read_stdin()?;
+        let mut lines = input.lines();
+        let refresh_token = lines.next().unwrap_or_default().to_string();
+        let access_token = lines.next().unwrap_or_default().to_string();
+        (refresh_token, access_token)
+    };
+
+    let refresh_token = non_empty(refresh_token.trim().to_string())?;
+    let access_token = Some(access_token.trim().to_string()).filter(|token| !token.is_empty());
+
+    Ok((refresh_token, access_token))
+}
+
+/// Read all of stdin.
+fn read_stdin() -> color_eyre::eyre::Result<String> {
+    let mut input = String::new();
+    std::io::stdin()
+        .read_to_string(&mut input)
+        .wrap_err("Failed to read from stdin")?;
+    Ok(input)
+}
+
+/// Reject an empty secret.
+fn non_empty(secret: String) -> color_eyre::eyre::Result<String> {
+    if secret.is_empty() {
+        bail!("No secret provided");
+    }
+    Ok(secret)
+}
@@ -0,0 +1,60 @@
+//! The server certificate details shown in the report.
+
+/// The certificate details shown in the report.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Info {
+    /// The subject distinguished name.
+    pub subject: String,
+
+    /// The issuer distinguished name.
+    pub issuer: String,
+
+    /// The end of the validity period, in RFC 3339 format.
+    pub not_after: String,
+
+    /// Whole days until the certificate expires, negative once it expired.
+    pub expires_in_days: i64,
+}
+
+/// Read the details of the certificate.
+///
+/// `now` is the current time in seconds since the Unix epoch.
+pub fn inspect(certificate: &rustls_pki_types::CertificateDer<'_>, now: i64) -> Option<Info> {
+    let info = imap_tls_core::CertificateInfo::read(certificate)?;
+    let not_after = i64::try_from(info.not_after.as_secs()).ok()?;
+
+    Some(Info {
+        subject: info.subject,
+        issuer: info.issuer,
+        not_after: rfc3339(not_after),
+        expires_in_days: (not_after - now).div_euclid(86400),
+    })
+}
+
+/// Render the time in seconds since the Unix epoch, e.g.
+/// `2036-01-02T03:04:05Z`.
+fn rfc3339(time: i64) -> String {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let days = time.div_euclid(86400) + 719468;
+    let seconds = time.rem_euclid(86400);
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,515 @@
+//! The connectivity and credential checks.
+
+use crate::report::{Check, ServerReport, Status};
+
+/// Resolving the credentials and settings from the config.
+const CREDENTIALS: &str = "credentials";
+
+/// Resolving the server host name.
+const DNS: &str = "dns";
+
+/// Connecting over TCP.
+const TCP: &str = "tcp";
+
+/// The TLS handshake, including STARTTLS.
+const TLS: &str = "tls";
+
+/// Reading the server greeting.
+const GREETING: &str = "greeting";
+
+/// Querying the server capabilities.
+const CAPABILITY: &str = "capability";
+
+/// Checking the offered authentication mechanisms.
+const MECHANISMS: &str = "mechanisms";
+
+/// Establishing an authenticated session.
+const LOGIN: &str = "login";
+
+/// The checks that run before the mailbox checks, in order.
+const CHECKS: [&str; 8] = [
+    CREDENTIALS,
+    DNS,
+    TCP,
+    TLS,
+    GREETING,
+    CAPABILITY,
+    MECHANISMS,
+    LOGIN,
+];
+
+/// What the unauthenticated connection found out about the server.
+struct Probe {
+    /// The capabilities advertised after securing the connection.
+    capabilities: imap_capabilities::Capabilities,
+
+    /// Whether the connection provides TLS channel binding.
+    channel_binding: bool,
+
+    /// Whether the server preauthenticated the connection.
+    preauth: bool,
+}
+
+/// Run all the checks for the server.
+pub async fn run(
+    core_server: &config_core::ServerConfig,
+    config: &config_core::Config,
+) -> ServerReport {
+    let mut report = ServerReport::new(core_server.name.clone());
+
+    if run_checks(core_server, config, &mut report).await.is_none() {
+        let mailboxes = core_server.mailboxes.iter().map(status_check_name);
+        report.skip_remaining(CHECKS.map(str::to_owned).into_iter().chain(mailboxes));
+    }
+
+    report
+}
+
+/// Run the checks in order, stopping at the first failure.
+async fn run_checks(
+    core_server: &config_core::ServerConfig,
+    config: &config_core::Config,
+    report: &mut ServerReport,
+) -> Option<()> {
+    let server = config_bringup::server(core_server, &config.oauth2_clients, config.proxy.as_ref())
+        .await
+        .map(|mut server| {
+            let auth = describe_auth(server.auth.get_mut());
+            (server, auth)
+        });
+    let (server, auth) = report.record(CREDENTIALS, server, |(_, auth)| auth.clone())?;
+
+    let probe = probe(&server, report).await?;
+    check_mechanisms(&*server.auth.read().await, &probe, report)?;
+
+    let mut session = match imap_service::connect_to_server(&server).await {
+        Ok((session, server_info)) => {
+            let mut detail = format!("authenticated with {auth}");
+            if let Some(server_info) = &server_info {
+                detail.push_str(&format!(", server is {server_info}"));
+            }
+            report.checks.push(Check {
+                name: LOGIN.to_owned(),
+                status: Status::Pass,
+                detail,
+                certificate: None,
+                server_info: server_info.map(|server_info| server_info.fields),
+            });
+            session
+        }
+        Err(error) => {
+            // Point out the failures that retrying does not fix.
+            let hint = if error.needs_attention() {
+                " (fix the credentials or settings)"
+            } else {
+                ""
+            };
+            report.fail(LOGIN, format!("{error}{hint}"));
+            return None;
+        }
+    };
+
+    for mailbox in &core_server.mailboxes {
+        let name = imap_utf7::ImapUtf7String::from_utf8(&mailbox.name);
+        let counts = imap_checker::fetch_counts(
+            &mut session,
+            name.as_imap_utf7_str(),
+            server.timeouts.command,
+        )
+        .await;
+        report.record(&status_check_name(mailbox), counts, |counts| {
+            format!("{} messages, {} unread", counts.total, counts.unread)
+        });
+    }
+
+    let _ = session.logout().await;
+    Some(())
+}
+
+/// The name of the `STATUS` check of the mailbox.
+fn status_check_name(mailbox: &config_core::MailboxConfig) -> String {
+    format!("status {}", mailbox.name)
+}
+
+/// Describe how the server authenticates.
+fn describe_auth(auth: &config_bringup::ServerAuth) -> String {
+    match auth {
+        config_bringup::ServerAuth::Login { username, .. } => {
+            format!("password as '{username}'")
+        }
+        config_bringup::ServerAuth::OAuth2Credentials { user, .. } => {
+            format!("OAuth 2 access token as '{user}'")
+        }
+        config_bringup::ServerAuth::OAuth2Session { user, .. } => {
+            format!("OAuth 2 session as '{user}'")
+        }
+        config_bringup::ServerAuth::External { authzid } => match authzid {
+            Some(authzid) => format!("TLS client certificate as '{authzid}'"),
+            None => "TLS client certificate".to_owned(),
+        },
+    }
+}
+
+/// How the TLS check turns out once connected, unless an earlier step
+/// fails.
+enum Secured {
+    /// TLS does not apply, for the reason.
+    Skipped(&'static str),
+
+    /// The connection is plaintext, allowed for the reason.
+    Plaintext(String),
+
+    /// The connection is secured with TLS.
+    Tls,
+}
+
+/// Connect without authenticating through the steps the monitoring
+/// connects with, recording the outcome of each.
+async fn probe(server: &config_bringup::Server, report: &mut ServerReport) -> Option<Probe> {
+    let params = imap_service::connect_params(server);
+
+    let (connected, secured) = match (params.tunnel, params.socket) {
+        (Some(command), _) => {
+            report.push(
+                DNS,
+                Status::Skipped,
+                "the tunnel command reaches the server",
+            );
+            let connected = imap_connect::connect(params.clone()).await;
+            if !failed_at(&connected, TCP) {
+                report.pass(TCP, format!("spawned the tunnel command '{command}'"));
+            }
+            let secured = Secured::Skipped("the tunnel command secures the connection");
+            (connected, secured)
+        }
+        (None, Some(path)) => {
+            let path = path.display();
+            report.push(DNS, Status::Skipped, format!("{path} is a Unix socket"));
+            let connected = imap_connect::connect(params.clone()).await;
+            if !failed_at(&connected, TCP) {
+                report.pass(TCP, format!("connected to the Unix socket {path}"));
+            }
+            (connected, Secured::Skipped("the Unix socket is local"))
+        }
+        (None, None) => {
+            let tcp_stream = connect_tcp(server, report).await?;
+            let secured = match server.tls_mode {
+                imap_tls::TlsMode::None => {
+                    Secured::Plaintext(plaintext_reason(server, &tcp_stream))
+                }
+                imap_tls::TlsMode::Implicit | imap_tls::TlsMode::StartTls => Secured::Tls,
+            };
+            (imap_connect::start(tcp_stream, &params).await, secured)
+        }
+    };
+
+    let (mut client, greeting) = match connected {
+        Ok(connected) => connected,
+        Err(error) => {
+            let failed = failed_check(&error);
+            if failed == GREETING || failed == CAPABILITY {
+                record_secured(server, secured, None, report);
+            }
+            if failed == CAPABILITY {
+                report.pass(GREETING, "OK");
+            }
+            report.fail(failed, error.to_string());
+            return None;
+        }
+    };
+
+    record_secured(server, secured, Some(client.get_ref()), report);
+    let status = if greeting.preauth { "PREAUTH" } else { "OK" };
+    report.pass(GREETING, status);
+
+    let preauth = greeting.preauth;
+    let channel_binding = imap_connect::channel_binding(client.get_ref()).is_some();
+    Some(Probe {
+        capabilities: capabilities(server, &mut client, greeting, report).await?,
+        channel_binding,
+        preauth,
+    })
+}
+
+/// Whether connecting failed at the check.
+fn failed_at<T>(connected: &Result<T, imap_connect::Error>, check: &str) -> bool {
+    connected
+        .as_ref()
+        .is_err_and(|error| failed_check(error) == check)
+}
+
+/// The check of the step that failed to connect.
+fn failed_check(error: &imap_connect::Error) -> &'static str {
+    use imap_tls::{ConnectError, Step};
+
+    match error {
+        imap_connect::Error::Resolve(_) | imap_connect::Error::NoAddress(_) => DNS,
+        imap_connect::Error::TcpConnect(_)
+        | imap_connect::Error::ConnectTimeout(_)
+        | imap_connect::Error::Proxy(_)
+        | imap_connect::Error::Tunnel(_)
+        | imap_connect::Error::UnixConnect(_) => TCP,
+        imap_connect::Error::ImapTlsConnector(_)
+        | imap_connect::Error::RemotePlaintext(_)
+        | imap_connect::Error::ProxiedPlaintext => TLS,
+        imap_connect::Error::ImapTlsConnect(error) => match error {
+            ConnectError::Tls(_)
+            | ConnectError::StartTlsUnsupported
+            | ConnectError::PreAuthBeforeStartTls
+            | ConnectError::Timeout {
+                step: Step::TlsHandshake | Step::Command("STARTTLS"),
+                ..
+            } => TLS,
+            ConnectError::Timeout {
+                step: Step::Command(_),
+                ..
+            } => CAPABILITY,
+            // The greeting is where the server first has its say, so an
+            // exchange failing is blamed on it.
+            ConnectError::Io(_)
+            | ConnectError::Imap(_)
+            | ConnectError::MissingGreeting
+            | ConnectError::Greeting(_)
+            | ConnectError::Timeout {
+                step: Step::Greeting,
+                ..
+            } => GREETING,
+        },
+    }
+}
+
+/// Why the plaintext connection is allowed, once it is.
+fn plaintext_reason(server: &config_bringup::Server, tcp_stream: &tokio::net::TcpStream) -> String {
+    if let Some(proxy) = &server.proxy {
+        return format!("plaintext through the {proxy}, allowed by the config");
+    }
+    let reason = match tcp_stream.peer_addr() {
+        Ok(peer_addr) if peer_addr.ip().to_canonical().is_loopback() => "loopback address",
+        _ => "remote address, allowed by the config",
+    };
+    format!("plaintext to a {reason}")
+}
+
+/// Record the TLS check, along with the negotiated protocol and the server
+/// certificate when the connection is at hand.
+fn record_secured(
+    server: &config_bringup::Server,
+    secured: Secured,
+    stream: Option<&imap_connect::Stream>,
+    report: &mut ServerReport,
+) {
+    match secured {
+        Secured::Skipped(reason) => report.push(TLS, Status::Skipped, reason),
+        Secured::Plaintext(reason) => report.pass(TLS, reason),
+        Secured::Tls => {
+            let mode = match server.tls_mode {
+                imap_tls::TlsMode::Implicit => "implicit TLS",
+                imap_tls::TlsMode::StartTls => "STARTTLS",
+                imap_tls::TlsMode::None => "plaintext",
+            };
+            let negotiated = match stream.and_then(imap_connect::negotiated) {
+                Some(negotiated) => format!(" with {negotiated}"),
+                None => String::new(),
+            };
+
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| {
+                    elapsed.as_secs().try_into().unwrap_or(i64::MAX)
+                });
+            let certificate = stream
+                .and_then(imap_connect::peer_certificate)
+                .and_then(|certificate| crate::certificate::inspect(&certificate, now));
+
+            report.checks.push(Check {
+                name: TLS.to_owned(),
+                status: Status::Pass,
+                detail: format!("{mode}{negotiated}, {}", describe_trust(server)),
+                certificate,
+                server_info: None,
+            });
+        }
+    }
+}
+
+/// Open the TCP connection, directly or through the proxy.
+async fn connect_tcp(
+    server: &config_bringup::Server,
+    report: &mut ServerReport,
+) -> Option<tokio::net::TcpStream> {
+    let host = server.host.as_str();
+    let port = server.port;
+
+    if let Some(proxy) = &server.proxy {
+        report.push(
+            DNS,
+            Status::Skipped,
+            format!("{host} is resolved through the {proxy}"),
+        );
+        let tcp_stream = limit(
+            server.connect_timeout,
+            imap_proxy::connect(proxy, host, port),
+        );
+        return report.record(TCP, tcp_stream.await, |_| {
+            format!("connected to {host}:{port} through the {proxy}")
+        });
+    }
+
+    let addrs = report.record(
+        DNS,
+        limit(
+            server.connect_timeout,
+            imap_connect::resolve(host, port, server.ip_family),
+        )
+        .await,
+        |addrs| {
+            let ips = addrs.iter().map(|addr| addr.ip().to_string());
+            let family = match server.ip_family {
+                Some(ip_family) => format!(" {ip_family}"),
+                None => String::new(),
+            };
+            format!(
+                "{host} resolved to{family} {}",
+                ips.collect::<Vec<_>>().join(", ")
+            )
+        },
+    )?;
+
+    report.record(
+        TCP,
+        limit(server.connect_timeout, imap_connect::race(addrs)).await,
+        |tcp_stream| match tcp_stream.peer_addr() {
+            Ok(addr) => format!("connected to {addr}"),
+            Err(_) => format!("connected to port {port}"),
+        },
+    )
+}
+
+/// Record the capabilities listed in the greeting, or query them, then log
+/// out.
+async fn capabilities(
+    server: &config_bringup::Server,
+    client: &mut imap_connect::Client,
+    greeting: imap_tls::Greeting,
+    report: &mut ServerReport,
+) -> Option<imap_capabilities::Capabilities> {
+    let (capabilities, source) = match greeting.capabilities {
+        Some(capabilities) => (Ok(capabilities), " (sent while connecting)"),
+        None => (
+            limit(server.timeouts.command, imap_capabilities::fetch(client)).await,
+            "",
+        ),
+    };
+    let capabilities = report.record(CAPABILITY, capabilities, |capabilities| {
+        let names = capabilities.iter().collect::<Vec<_>>().join(" ");
+        format!("{names}{source}")
+    })?;
+
+    let logout = client.run_command_and_check_ok("LOGOUT", None);
+    let _ = limit(server.timeouts.command, logout).await;
+    Some(capabilities)
+}
+
+/// Run the step within the time limit, turning the expiry into an error.
+async fn limit<T, E>(
+    after: std::time::Duration,
+    step: impl Future<Output = Result<T, E>>,
+) -> Result<T, String>
+where
+    E: std::fmt::Display,
+{
+    match tokio::time::timeout(after, step).await {
+        Ok(result) => result.map_err(|error| error.to_string()),
+        Err(_) => Err(format!("timed out after {after:?}")),
+    }
+}
+
+/// Describe how the server certificate was trusted.
+fn describe_trust(server: &config_bringup::Server) -> String {
+    let trust = &server.tls_trust;
+    let pinned = !trust.pins.is_empty();
+    if !trust.native_roots && trust.extra_roots.is_empty() {
+        return "certificate matches a pin".to_owned();
+    }
+
+    let name = trust
+        .verify_name
+        .as_deref()
+        .unwrap_or(&server.tls_server_name);
+    let mut description = format!("verified for '{name}'");
+    if !trust.native_roots {
+        description.push_str(" by the configured CAs");
+    }
+    if pinned {
+        description.push_str(", matches a pin");
+    }
+    description
+}
+
+/// Check that the server offers a mechanism the configured authentication
+/// can use.
+fn check_mechanisms(
+    auth: &config_bringup::ServerAuth,
+    probe: &Probe,
+    report: &mut ServerReport,
+) -> Option<()> {
+    if probe.preauth {
+        report.pass(
+            MECHANISMS,
+            "none needed, the server preauthenticated the connection",
+        );
+        return Some(());
+    }
+
+    let capabilities = &probe.capabilities;
+    let offered = capabilities.auth_mechanisms().collect::<Vec<_>>();
+    let offered = if offered.is_empty() {
+        "none offered".to_owned()
+    } else {
+        format!("offered {}", offered.join(", "))
+    };
+
+    let mechanism = match auth {
+        config_bringup::ServerAuth::Login { mechanism, .. } => {
+            let usable = |mechanism: imap_auth::PasswordMechanism| match mechanism {
+                imap_auth::PasswordMechanism::Login => !capabilities.has("LOGINDISABLED"),
+                mechanism => {
+                    capabilities.has_auth(mechanism.name())
+                        && (probe.channel_binding || !mechanism.requires_channel_binding())
+                }
+            };
+            match mechanism {
+                Some(mechanism) => usable(*mechanism).then_some(mechanism.name()),
+                None => {
+                    imap_auth::PasswordMechanism::negotiate(capabilities, probe.channel_binding)
+                        .map(imap_auth::PasswordMechanism::name)
+                }
+            }
+        }
+        config_bringup::ServerAuth::OAuth2Credentials { mechanism, .. }
+        | config_bringup::ServerAuth::OAuth2Session { mechanism, .. } => {
+            let mechanism =
+                mechanism.unwrap_or_else(|| imap_auth::OAuth2Mechanism::negotiate(capabilities));
+            capabilities
+                .has_auth(mechanism.name())
+                .then_some(mechanism.name())
+        }
+        config_bringup::ServerAuth::External { .. } => {
+            capabilities.has_auth("EXTERNAL").then_some("EXTERNAL")
+        }
+    };
+
+    match mechanism {
+        Some(mechanism) => {
+            report.pass(MECHANISMS, format!("{offered}, using {mechanism}"));
+            Some(())
+        }
+        None => {
+            report.fail(
+                MECHANISMS,
+                format!("{offered}, none usable with the configured authentication"),
+            );
+            None
+        }
+    }
+}
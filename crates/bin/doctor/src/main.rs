@@ -0,0 +1,66 @@
+//! CLI utility diagnosing the connectivity and credentials of the configured
+//! servers.
+
+use clap::Parser as _;
+use color_eyre::eyre::bail;
+
+mod certificate;
+mod checks;
+mod report;
+
+/// Check the connectivity and credentials of the configured servers.
+#[derive(Debug, clap::Parser)]
+struct Cli {
+    /// Print the report as JSON.
+    #[arg(long)]
+    json: bool,
+
+    /// The names of the servers to check, all of them when none is given.
+    servers: Vec<String>,
+}
+
+/// Run the checks and print the report.
+///
+/// Exits with a failure status when any check fails.
+#[tokio::main]
+async fn main() -> color_eyre::eyre::Result<std::process::ExitCode> {
+    color_eyre::install()?;
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+
+    let config = config_load::with_default_env_var().await?;
+    let _keyring_guard = config_bringup::init_keyring_if_needed(&config)?;
+
+    for name in &cli.servers {
+        if !config.servers.iter().any(|server| &server.name == name) {
+            bail!("No server named '{name}' in config");
+        }
+    }
+
+    let mut reports = Vec::new();
+    for server in &config.servers {
+        if cli.servers.is_empty() || cli.servers.contains(&server.name) {
+            reports.push(checks::run(server, &config).await);
+        }
+    }
+
+    if cli.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in &reports {
+            print!("{report}");
+        }
+    }
+
+    Ok(if reports.iter().any(report::ServerReport::failed) {
+        std::process::ExitCode::FAILURE
+    } else {
+        std::process::ExitCode::SUCCESS
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,151 @@
+//! Check results and their human-readable rendering.
+
+/// The results of the checks of a server.
+#[derive(Debug, serde::Serialize)]
+pub struct ServerReport {
+    /// The server name.
+    pub server: String,
+
+    /// The check results, in the order they ran.
+    pub checks: Vec<Check>,
+}
+
+/// The result of a single check.
+#[derive(Debug, serde::Serialize)]
+pub struct Check {
+    /// The check name.
+    pub name: String,
+
+    /// The outcome.
+    pub status: Status,
+
+    /// What the check found, or why it failed.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+
+    /// The server certificate, for the TLS handshake check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<crate::certificate::Info>,
+
+    /// What the server told about itself, for the login check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_info: Option<std::collections::BTreeMap<String, String>>,
+}
+
+/// The outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// The check passed.
+    Pass,
+
+    /// The check failed.
+    Fail,
+
+    /// The check did not run, because an earlier one failed.
+    Skipped,
+}
+
+impl ServerReport {
+    /// Start an empty report for the server.
+    pub fn new(server: String) -> Self {
+        Self {
+            server,
+            checks: Vec::new(),
+        }
+    }
+
+    /// Record a check result.
+    pub fn push(&mut self, name: impl Into<String>, status: Status, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+            certificate: None,
+            server_info: None,
+        });
+    }
+
+    /// Record a passed check.
+    pub fn pass(&mut self, name: impl Into<String>, detail: impl Into<String>) {
+        self.push(name, Status::Pass, detail);
+    }
+
+    /// Record a failed check.
+    pub fn fail(&mut self, name: impl Into<String>, detail: impl Into<String>) {
+        self.push(name, Status::Fail, detail);
+    }
+
+    /// Record the outcome of a check, returning the value when it passed.
+    pub fn record<T, E: std::fmt::Display>(
+        &mut self,
+        name: &str,
+        result: Result<T, E>,
+        detail: impl FnOnce(&T) -> String,
+    ) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.pass(name, detail(&value));
+                Some(value)
+            }
+            Err(error) => {
+                self.fail(name, error.to_string());
+                None
+            }
+        }
+    }
+
+    /// Record the checks that did not run as skipped.
+    pub fn skip_remaining(&mut self, names: impl IntoIterator<Item = String>) {
+        for name in names {
+            if !self.checks.iter().any(|check| check.name == name) {
+                self.push(name, Status::Skipped, String::new());
+            }
+        }
+    }
+
+    /// Whether any check failed.
+    pub fn failed(&self) -> bool {
+        self.checks.iter().any(|check| check.status == Status::Fail)
+    }
+}
+
+impl std::fmt::Display for ServerReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:", self.server)?;
+
+        let width = self
+            .checks
+            .iter()
+            .map(|check| check.name.len())
+            .max()
+            .unwrap_or_default();
+        let indent = width + 10;
+
+        for check in &self.checks {
+            let status = match check.status {
+                Status::Pass => "PASS",
+                Status::Fail => "FAIL",
+                Status::Skipped => "SKIP",
+            };
+            let line = format!("  {status}  {:width$}  {}", check.name, check.detail);
+            writeln!(f, "{}", line.trim_end())?;
+
+            if let Some(certificate) = &check.certificate {
+                writeln!(f, "{:indent$}subject: {}", "", certificate.subject)?;
+                writeln!(f, "{:indent$}issuer: {}", "", certificate.issuer)?;
+                writeln!(
+                    f,
+                    "{:indent$}expires: {} (in {} days)",
+                    "", certificate.not_after, certificate.expires_in_days
+                )?;
+            }
+
+            for (name, value) in check.server_info.iter().flatten() {
+                writeln!(f, "{:indent$}{name}: {value}", "")?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use imap_test_server::Step;
+
+use crate::certificate;
+use crate::report::Status;
+
+/// A leaf certificate for `imap.example.com`, issued by a test CA and valid
+/// until 2036-01-02T03:04:05Z.
+const CERTIFICATE: &[u8] = include_bytes!("fixtures/certificate.der");
+
+/// 2026-01-01T00:00:00Z.
+const NOW: i64 = 1_767_225_600;
+
+#[test]
+fn inspect_certificate() {
+    let certificate = rustls_pki_types::CertificateDer::from(CERTIFICATE);
+    let info = certificate::inspect(&certificate, NOW).unwrap();
+
+    assert_eq!(
+        info,
+        certificate::Info {
+            subject: "CN=imap.example.com".to_owned(),
+            issuer: "C=US, O=Example Org, CN=Example CA".to_owned(),
+            not_after: "2036-01-02T03:04:05Z".to_owned(),
+            expires_in_days: 3653,
+        }
+    );
+}
+
+#[test]
+fn inspect_truncated_certificate() {
+    let certificate = rustls_pki_types::CertificateDer::from(&CERTIFICATE[..CERTIFICATE.len() / 2]);
+
+    assert_eq!(certificate::inspect(&certificate, NOW), None);
+}
+
+/// The greeting of the server stand-in.
+const GREETING: Step = ("", "* OK [CAPABILITY IMAP4rev1] ready\r\n");
+
+/// The config of a plaintext server on the local port.
+fn config(port: u16) -> config_core::Config {
+    config_yaml::parse_yaml(&format!(
+        r#"
+servers:
+  - name: "local"
+    host: "127.0.0.1"
+    port: {port}
+    tls:
+      mode: none
+    login:
+      username: "user"
+      password: "secret"
+    id:
+      enabled: false
+    mailboxes:
+      - name: "INBOX"
+"#
+    ))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn all_checks_pass() {
+    static PROBE: [Step; 2] = [GREETING, ("A0001 LOGOUT", "* BYE\r\nA0001 OK bye\r\n")];
+    static SESSION: [Step; 4] = [
+        GREETING,
+        ("A0001 LOGIN \"user\" \"secret\"", "A0001 OK logged in\r\n"),
+        (
+            "A0002 STATUS \"INBOX\" (MESSAGES UNSEEN)",
+            "* STATUS INBOX (MESSAGES 2 UNSEEN 1)\r\nA0002 OK done\r\n",
+        ),
+        ("A0003 LOGOUT", "* BYE\r\nA0003 OK bye\r\n"),
+    ];
+    static SCRIPTS: [&[Step]; 2] = [&PROBE, &SESSION];
+    let (port, server) = imap_test_server::serve_each(&SCRIPTS).await;
+    let config = config(port);
+
+    let report = crate::checks::run(&config.servers[0], &config).await;
+
+    let checks = report
+        .checks
+        .iter()
+        .map(|check| (check.name.as_str(), check.status, check.detail.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        checks,
+        [
+            ("credentials", Status::Pass, "password as 'user'"),
+            ("dns", Status::Pass, "127.0.0.1 resolved to 127.0.0.1"),
+            (
+                "tcp",
+                Status::Pass,
+                &*format!("connected to 127.0.0.1:{port}")
+            ),
+            ("tls", Status::Pass, "plaintext to a loopback address"),
+            ("greeting", Status::Pass, "OK"),
+            (
+                "capability",
+                Status::Pass,
+                "IMAP4REV1 (sent while connecting)"
+            ),
+            ("mechanisms", Status::Pass, "none offered, using LOGIN"),
+            (
+                "login",
+                Status::Pass,
+                "authenticated with password as 'user'"
+            ),
+            ("status INBOX", Status::Pass, "2 messages, 1 unread"),
+        ]
+    );
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn refused_connection_skips_the_rest() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+    let config = config(port);
+
+    let report = crate::checks::run(&config.servers[0], &config).await;
+
+    let statuses = report
+        .checks
+        .iter()
+        .map(|check| (check.name.as_str(), check.status))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        [
+            ("credentials", Status::Pass),
+            ("dns", Status::Pass),
+            ("tcp", Status::Fail),
+            ("tls", Status::Skipped),
+            ("greeting", Status::Skipped),
+            ("capability", Status::Skipped),
+            ("mechanisms", Status::Skipped),
+            ("login", Status::Skipped),
+            ("status INBOX", Status::Skipped),
+        ]
+    );
+    assert!(report.failed());
+}
@@ -22,10 +22,13 @@ async fn main() -> color_eyre::eyre::Result<()> {
             "listing IMAP mailboxes"
         );
 
-        let mut session = imap_service::connect_to_server(server).await?;
+        let (mut session, server_info) = imap_service::connect_to_server(server).await?;
 
         let mut list_stream = session.list(None, Some("*")).await?;
-        println!("{}:", server.server_name);
+        match server_info {
+            Some(server_info) => println!("{} ({server_info}):", server.server_name),
+            None => println!("{}:", server.server_name),
+        }
         while let Some(name) = list_stream.try_next().await? {
             let name = imap_utf7::ImapUtf7Str::new(name.name())?;
             println!("  {name}");
@@ -17,7 +17,7 @@ async fn main() -> color_eyre::eyre::Result<core::convert::Infallible> {
 
     let config = config_load::with_default_env_var().await?;
     let _keyring_guard = config_bringup::init_keyring_if_needed(&config)?;
-    let mailboxes = config_bringup::for_monitoring(&config).await?;
+    let mailboxes = config_bringup::for_monitoring_when_unlocked(&config).await?;
     drop(config);
 
     let mut join_set = tokio::task::JoinSet::new();
@@ -30,6 +30,7 @@ async fn main() -> color_eyre::eyre::Result<core::convert::Infallible> {
             name: label,
             active: false,
             unread: 0,
+            needs_attention: false,
         })
     };
 
@@ -46,6 +47,8 @@ async fn main() -> color_eyre::eyre::Result<core::convert::Infallible> {
         event_loop
     };
 
+    let resume = Arc::new(tokio::sync::Notify::new());
+
     monitoring_engine::spawn_monitors::<monitoring_workload_imap::Mailbox, _, _, _, _, _, _>(
         monitoring_engine::SpawnMonitorsParams {
             workload_items: &mailboxes,
@@ -77,6 +80,7 @@ async fn main() -> color_eyre::eyre::Result<core::convert::Infallible> {
                     }
                 }
             },
+            resume: Arc::clone(&resume),
         },
     );
 
@@ -141,6 +145,10 @@ async fn main() -> color_eyre::eyre::Result<core::convert::Infallible> {
                     if let Some(entry) = entries.get_mut(update.entry) {
                         entry.active =
                             matches!(update.payload, supervisor::SupervisorEvent::Started);
+                        entry.needs_attention = matches!(
+                            update.payload,
+                            supervisor::SupervisorEvent::NeedsAttention { .. }
+                        );
                     }
                     update_tray_menu(&mut tray_icon, &entries);
                     update_total(&entries, &mut total_cache, new_icon_text_sender.clone());
@@ -156,6 +164,9 @@ async fn main() -> color_eyre::eyre::Result<core::convert::Infallible> {
                         && let Some(entry) = entries.get(key)
                     {
                         tracing::info!("Menu item clicked: {}", entry.name);
+                        if entry.needs_attention {
+                            resume.notify_waiters();
+                        }
                     }
                 }
                 tao::event::Event::WindowEvent {
@@ -14,13 +14,18 @@ pub struct EntryState {
 
     /// Number of unread emails.
     pub unread: u32,
+
+    /// Whether the mailbox monitor is stopped until the user acts.
+    pub needs_attention: bool,
 }
 
 /// Build the tray menu from the current entries.
 pub fn build_menu(entries: &SlotMap<crate::Key, EntryState>) -> Menu {
     let menu = Menu::new();
     for (key, entry) in entries.iter() {
-        let text = if entry.active {
+        let text = if entry.needs_attention {
+            format!("{}: needs attention, click to retry", entry.name)
+        } else if entry.active {
             format!("{}: {} unread", entry.name, entry.unread)
         } else {
             format!("{}: inactive", entry.name)
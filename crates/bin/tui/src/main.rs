@@ -9,7 +9,7 @@ async fn main() -> color_eyre::eyre::Result<()> {
 
     let config = config_load::with_default_env_var().await?;
     let _keyring_guard = config_bringup::init_keyring_if_needed(&config)?;
-    let mailboxes = config_bringup::for_monitoring(&config).await?;
+    let mailboxes = config_bringup::for_monitoring_when_unlocked(&config).await?;
     drop(config);
 
     let mut join_set = tokio::task::JoinSet::new();
@@ -26,9 +26,12 @@ async fn main() -> color_eyre::eyre::Result<()> {
             name: label,
             active: false,
             unread: 0,
+            needs_attention: false,
         })
     };
 
+    let resume = Arc::new(tokio::sync::Notify::new());
+
     monitoring_engine::spawn_monitors::<monitoring_workload_imap::Mailbox, _, _, _, _, _, _>(
         monitoring_engine::SpawnMonitorsParams {
             workload_items: &mailboxes,
@@ -46,6 +49,7 @@ async fn main() -> color_eyre::eyre::Result<()> {
                     let _ = supervisor_sender.send(update).await;
                 }
             },
+            resume: Arc::clone(&resume),
         },
     );
 
@@ -75,6 +79,10 @@ async fn main() -> color_eyre::eyre::Result<()> {
                         if matches!(key.code, crossterm::event::KeyCode::Char('q') | crossterm::event::KeyCode::Esc) => {
                         break;
                     }
+                    crossterm::event::Event::Key(key)
+                        if matches!(key.code, crossterm::event::KeyCode::Char('r')) => {
+                        resume.notify_waiters();
+                    }
                     crossterm::event::Event::Resize(_, _) => {
                         tui_view::render(&mut terminal, entries.values())?;
                     }
@@ -91,6 +99,10 @@ async fn main() -> color_eyre::eyre::Result<()> {
             Some(update) = supervisor_receiver.recv() => {
                 if let Some(entry) = entries.get_mut(update.entry) {
                     entry.active = matches!(update.payload, supervisor::SupervisorEvent::Started);
+                    entry.needs_attention = matches!(
+                        update.payload,
+                        supervisor::SupervisorEvent::NeedsAttention { .. }
+                    );
                 }
 
                 tui_view::render(&mut terminal, entries.values())?;
@@ -3,6 +3,7 @@
 use std::sync::Arc;
 
 pub mod keyring;
+pub mod token_storage;
 mod types;
 
 pub use types::*;
@@ -10,6 +11,25 @@ pub use types::*;
 /// Default IDLE timeout (seconds) when not specified in config.
 const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
 
+/// Default poll interval (seconds) when not specified in config.
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 120;
+
+/// Default TCP connect timeout (seconds) when not specified in config.
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 30;
+
+/// Default TLS handshake timeout (seconds) when not specified in config.
+const DEFAULT_TLS_HANDSHAKE_TIMEOUT_SECS: u64 = 30;
+
+/// Default greeting timeout (seconds) when not specified in config.
+const DEFAULT_GREETING_TIMEOUT_SECS: u64 = 30;
+
+/// Default command timeout (seconds) when not specified in config.
+const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 60;
+
+/// Default OAuth 2 token expiration imminence tolerance (seconds) when not
+/// specified in config.
+const DEFAULT_EXPIRATION_IMMINENCE_TOLERANCE_SECS: u64 = 60;
+
 /// Initialize the default keyring store when the config references keyring credentials.
 pub fn init_keyring_if_needed(
     config: &config_core::Config,
@@ -21,28 +41,89 @@ pub fn init_keyring_if_needed(
                 password: config_core::PasswordSource::Keyring { .. },
                 ..
             })
-        )
+        ) || matches!(
+            server.auth,
+            config_core::Auth::OAuth2Session(config_core::OAuth2Session {
+                token_storage: config_core::OAuth2TokenStorage::Keyring(_),
+                ..
+            })
+        ) || matches!(
+            server.tls.client_certificate,
+            Some(config_core::ClientCertificateSource::Keyring { .. })
+        ) || server
+            .proxy
+            .as_ref()
+            .or(config.proxy.as_ref())
+            .is_some_and(proxy_needs_keyring)
     });
 
     if !needs_keyring {
         return Ok(None);
     }
 
-    keyring_bridge::KeyringGuard::init_default().map(Some)
+    keyring_bridge::KeyringGuard::init(&keyring_options(&config.keyring)).map(Some)
+}
+
+/// Whether the proxy password is in the keyring.
+fn proxy_needs_keyring(proxy: &config_core::ProxyConfig) -> bool {
+    let login = match proxy {
+        config_core::ProxyConfig::Direct => None,
+        config_core::ProxyConfig::Socks5(socks5) => socks5.login.as_ref(),
+        config_core::ProxyConfig::Http(http) => http.login.as_ref(),
+    };
+    login.is_some_and(|login| matches!(login.password, config_core::PasswordSource::Keyring { .. }))
+}
+
+/// Convert the keyring config into the keyring store options.
+fn keyring_options(keyring: &config_core::KeyringConfig) -> keyring_bridge::Options {
+    let backend = match keyring.backend {
+        config_core::KeyringBackend::Auto => keyring_bridge::Backend::Auto,
+        config_core::KeyringBackend::Platform => keyring_bridge::Backend::Platform,
+        config_core::KeyringBackend::File => keyring_bridge::Backend::File,
+    };
+
+    keyring_bridge::Options {
+        backend,
+        file: keyring_bridge::FileStoreOptions {
+            path: keyring.file.path.clone(),
+            key_source: keyring.file.key.as_ref().map(file_key_source),
+        },
+        secret_service: keyring_bridge::SecretServiceOptions {
+            collection: keyring.secret_service.collection.clone(),
+            label: keyring.secret_service.label.clone(),
+        },
+    }
+}
+
+/// Convert the file key source config.
+pub(crate) fn file_key_source(key: &config_core::FileKeySource) -> encrypted_file::KeySource {
+    match key {
+        config_core::FileKeySource::Passphrase { passphrase } => {
+            encrypted_file::KeySource::Passphrase(passphrase.clone())
+        }
+        config_core::FileKeySource::KeyFile { key_file } => {
+            encrypted_file::KeySource::KeyFile(key_file.clone())
+        }
+    }
 }
 
 /// Bringup the server config.
-async fn server(
+///
+/// The default proxy applies unless the server has its own proxy settings.
+pub async fn server(
     server: &config_core::ServerConfig,
+    oauth2_clients: &std::collections::HashMap<String, config_core::OAuth2ClientConfig>,
+    default_proxy: Option<&config_core::ProxyConfig>,
 ) -> Result<types::Server, ResolveCredentialsError> {
     let tls_mode = match server.tls.mode {
         config_core::TlsMode::Implicit => imap_tls::TlsMode::Implicit,
         config_core::TlsMode::StartTls => imap_tls::TlsMode::StartTls,
+        config_core::TlsMode::None => imap_tls::TlsMode::None,
     };
 
     let port = server.port.unwrap_or(match tls_mode {
         imap_tls::TlsMode::Implicit => 993,
-        imap_tls::TlsMode::StartTls => 143,
+        imap_tls::TlsMode::StartTls | imap_tls::TlsMode::None => 143,
     });
 
     let tls_server_name = server
@@ -51,7 +132,19 @@ async fn server(
         .clone()
         .unwrap_or_else(|| server.host.clone());
 
-    let auth = server_auth(&server.auth).await?;
+    let tls_client_identity = match &server.tls.client_certificate {
+        Some(source) => Some(resolve_client_identity(source, &server.host).await?),
+        None => None,
+    };
+
+    let tls_trust = resolve_trust(&server.tls).await?;
+
+    let proxy = match server.proxy.as_ref().or(default_proxy) {
+        Some(proxy) => resolve_proxy(proxy).await?,
+        None => None,
+    };
+
+    let auth = server_auth(&server.auth, oauth2_clients).await?;
 
     Ok(types::Server {
         server_name: server.name.clone(),
@@ -59,47 +152,291 @@ async fn server(
         port,
         tls_mode,
         tls_server_name,
+        tls_client_identity,
+        tls_trust,
+        tls_policy: imap_tls_core::TlsPolicy {
+            min_version: server.tls.min_version.map(tls_version),
+            alpn: server.tls.alpn.clone(),
+        },
+        allow_remote_plaintext: server.tls.allow_remote_plaintext,
+        ip_family: server.ip_family.map(ip_family),
+        proxy,
+        tunnel: server.tunnel.clone(),
+        socket: server.socket.clone(),
+        connect_timeout: secs(
+            server,
+            "connect_secs",
+            server.timeouts.connect_secs,
+            DEFAULT_CONNECT_TIMEOUT_SECS,
+        )?,
+        timeouts: timeouts(server)?,
+        client_id: client_id(&server.id),
+        compress: server.compress,
+        trace: trace(&server.name, &server.trace),
+        auth: tokio::sync::RwLock::new(auth),
+        auth_source: types::AuthSource {
+            auth: server.auth.clone(),
+            oauth2_clients: oauth2_clients.clone(),
+        },
+    })
+}
+
+/// Resolve the server auth again, as the user may have fixed the
+/// credentials since the server rejected them.
+pub async fn refresh_auth(server: &types::Server) -> Result<(), ResolveCredentialsError> {
+    let types::AuthSource {
         auth,
+        oauth2_clients,
+    } = &server.auth_source;
+    let auth = server_auth(auth, oauth2_clients).await?;
+    *server.auth.write().await = auth;
+    Ok(())
+}
+
+/// Bringup the trace of the exchange, unless disabled.
+fn trace(server_name: &str, trace: &config_core::TraceConfig) -> Option<imap_trace::Config> {
+    if !trace.enabled && trace.file.is_none() {
+        return None;
+    }
+
+    Some(imap_trace::Config {
+        server: server_name.to_owned(),
+        file: trace.file.clone(),
+    })
+}
+
+/// Bringup the client identification, unless disabled.
+fn client_id(id: &config_core::IdConfig) -> Option<imap_session::ClientId> {
+    if id.enabled == Some(false) {
+        return None;
+    }
+
+    let default = imap_session::ClientId::default();
+    Some(imap_session::ClientId {
+        name: id.name.clone().unwrap_or(default.name),
+        version: id.version.clone().unwrap_or(default.version),
     })
 }
 
+/// Convert the configured TLS version.
+fn tls_version(version: config_core::TlsVersion) -> imap_tls_core::TlsVersion {
+    match version {
+        config_core::TlsVersion::V1_2 => imap_tls_core::TlsVersion::V1_2,
+        config_core::TlsVersion::V1_3 => imap_tls_core::TlsVersion::V1_3,
+    }
+}
+
+/// Convert the configured IP version.
+fn ip_family(ip_family: config_core::IpFamily) -> imap_connect::IpFamily {
+    match ip_family {
+        config_core::IpFamily::V4 => imap_connect::IpFamily::V4,
+        config_core::IpFamily::V6 => imap_connect::IpFamily::V6,
+    }
+}
+
+/// Bringup the time limits.
+fn timeouts(
+    server: &config_core::ServerConfig,
+) -> Result<imap_tls::Timeouts, ResolveCredentialsError> {
+    let timeouts = &server.timeouts;
+    Ok(imap_tls::Timeouts {
+        tls_handshake: secs(
+            server,
+            "tls_handshake_secs",
+            timeouts.tls_handshake_secs,
+            DEFAULT_TLS_HANDSHAKE_TIMEOUT_SECS,
+        )?,
+        greeting: secs(
+            server,
+            "greeting_secs",
+            timeouts.greeting_secs,
+            DEFAULT_GREETING_TIMEOUT_SECS,
+        )?,
+        command: secs(
+            server,
+            "command_secs",
+            timeouts.command_secs,
+            DEFAULT_COMMAND_TIMEOUT_SECS,
+        )?,
+    })
+}
+
+/// The duration in seconds, or the default.
+///
+/// A zero limit would fail every connection, so it is rejected.
+fn secs(
+    server: &config_core::ServerConfig,
+    setting: &'static str,
+    secs: Option<u64>,
+    default: u64,
+) -> Result<std::time::Duration, ResolveCredentialsError> {
+    match secs.unwrap_or(default) {
+        0 => Err(ResolveCredentialsError::ZeroInterval {
+            setting,
+            server: server.name.clone(),
+            mailbox: None,
+        }),
+        secs => Ok(std::time::Duration::from_secs(secs)),
+    }
+}
+
 /// Bringup the server auth config.
 async fn server_auth(
     auth: &config_core::Auth,
+    oauth2_clients: &std::collections::HashMap<String, config_core::OAuth2ClientConfig>,
 ) -> Result<types::ServerAuth, ResolveCredentialsError> {
     Ok(match auth {
         config_core::Auth::Login(credentials) => {
-            let password = resolve_password(credentials).await?;
+            let password = resolve_password(&credentials.password, &credentials.username).await?;
 
             types::ServerAuth::Login {
                 username: credentials.username.clone(),
                 password,
+                mechanism: credentials.auth_mechanism.map(password_mechanism),
             }
         }
         config_core::Auth::OAuth2Credentials(oauth2) => types::ServerAuth::OAuth2Credentials {
             user: oauth2.user.clone(),
             access_token: oauth2.access_token.clone(),
+            mechanism: oauth2.auth_mechanism.map(oauth2_mechanism),
+        },
+        config_core::Auth::OAuth2Session(session) => types::ServerAuth::OAuth2Session {
+            user: session.user.clone(),
+            mechanism: session.auth_mechanism.map(oauth2_mechanism),
+            session: Box::new(tokio::sync::Mutex::new(
+                oauth2_session(session, oauth2_clients).await?,
+            )),
+        },
+        config_core::Auth::External(external) => types::ServerAuth::External {
+            authzid: external.authzid.clone(),
         },
-        config_core::Auth::OAuth2Session(_) => todo!(),
     })
 }
 
+/// Build the OAuth 2 session manager.
+pub async fn oauth2_session(
+    session: &config_core::OAuth2Session,
+    oauth2_clients: &std::collections::HashMap<String, config_core::OAuth2ClientConfig>,
+) -> Result<types::OAuth2SessionManager, ResolveCredentialsError> {
+    let client = oauth2_clients.get(&session.oauth2_client).ok_or_else(|| {
+        ResolveCredentialsError::UnknownOAuth2Client(session.oauth2_client.clone())
+    })?;
+
+    let token_url = oauth2::TokenUrl::new(client.token_url.clone())
+        .map_err(ResolveCredentialsError::OAuth2Url)?;
+    let revocation_url = client
+        .revocation_url
+        .clone()
+        .map(oauth2::RevocationUrl::new)
+        .transpose()
+        .map_err(ResolveCredentialsError::OAuth2Url)?;
+    let oauth2_client =
+        oauth2::basic::BasicClient::new(oauth2::ClientId::new(client.client_id.clone()))
+            .set_client_secret(oauth2::ClientSecret::new(client.client_secret.clone()))
+            .set_token_uri(token_url)
+            .set_revocation_url_option(revocation_url);
+
+    // Following redirects would expose the client secret to other hosts.
+    let http_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(ResolveCredentialsError::HttpClient)?;
+
+    let storage = token_storage::init(&session.token_storage, &session.user)
+        .await
+        .map_err(ResolveCredentialsError::TokenStorage)?;
+
+    let expiration_immenance_tolerance = std::time::Duration::from_secs(
+        session
+            .expiration_immenance_tolerance_secs
+            .unwrap_or(DEFAULT_EXPIRATION_IMMINENCE_TOLERANCE_SECS),
+    );
+
+    Ok(oauth2_session::Manager {
+        oauth2_client,
+        http_client,
+        storage,
+        expiration_immenance_tolerance,
+    })
+}
+
+/// Convert the configured password authentication mechanism.
+fn password_mechanism(mechanism: config_core::PasswordMechanism) -> imap_auth::PasswordMechanism {
+    match mechanism {
+        config_core::PasswordMechanism::Login => imap_auth::PasswordMechanism::Login,
+        config_core::PasswordMechanism::Plain => imap_auth::PasswordMechanism::Plain,
+        config_core::PasswordMechanism::ScramSha1 => imap_auth::PasswordMechanism::ScramSha1,
+        config_core::PasswordMechanism::ScramSha1Plus => {
+            imap_auth::PasswordMechanism::ScramSha1Plus
+        }
+        config_core::PasswordMechanism::ScramSha256 => imap_auth::PasswordMechanism::ScramSha256,
+        config_core::PasswordMechanism::ScramSha256Plus => {
+            imap_auth::PasswordMechanism::ScramSha256Plus
+        }
+    }
+}
+
+/// Convert the configured OAuth 2 SASL mechanism.
+fn oauth2_mechanism(mechanism: config_core::OAuth2Mechanism) -> imap_auth::OAuth2Mechanism {
+    match mechanism {
+        config_core::OAuth2Mechanism::XOAuth2 => imap_auth::OAuth2Mechanism::XOAuth2,
+        config_core::OAuth2Mechanism::OAuthBearer => imap_auth::OAuth2Mechanism::OAuthBearer,
+    }
+}
+
 /// Build an IMAP mailbox config.
 fn mailbox(
     bringup_server: Arc<types::Server>,
     core_server: &config_core::ServerConfig,
     core_mailbox: &config_core::MailboxConfig,
-) -> types::Mailbox {
-    let idle_timeout_secs = core_mailbox
-        .idle_timeout_secs
-        .or(core_server.idle_timeout_secs)
-        .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS);
+) -> Result<types::Mailbox, ResolveCredentialsError> {
+    let interval = |setting, secs: u64| {
+        if secs == 0 {
+            return Err(ResolveCredentialsError::ZeroInterval {
+                setting,
+                server: core_server.name.clone(),
+                mailbox: Some(core_mailbox.name.clone()),
+            });
+        }
+        Ok(std::time::Duration::from_secs(secs))
+    };
+
+    let idle_timeout = interval(
+        "idle_timeout_secs",
+        core_mailbox
+            .idle_timeout_secs
+            .or(core_server.idle_timeout_secs)
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS),
+    )?;
 
-    types::Mailbox {
+    let heartbeat = core_mailbox
+        .heartbeat_secs
+        .or(core_server.heartbeat_secs)
+        .map(|secs| interval("heartbeat_secs", secs))
+        .transpose()?;
+
+    let poll_interval = interval(
+        "poll_interval_secs",
+        core_mailbox
+            .poll_interval_secs
+            .or(core_server.poll_interval_secs)
+            .unwrap_or(DEFAULT_POLL_INTERVAL_SECS),
+    )?;
+    let watch = match core_mailbox.watch.or(core_server.watch) {
+        None => imap_checker::WatchMode::Auto { poll_interval },
+        Some(config_core::WatchMode::Idle) => imap_checker::WatchMode::Idle,
+        Some(config_core::WatchMode::Poll) => imap_checker::WatchMode::Poll {
+            interval: poll_interval,
+        },
+    };
+
+    Ok(types::Mailbox {
         server: bringup_server,
         mailbox: imap_utf7::ImapUtf7String::from_utf8(&core_mailbox.name),
-        idle_timeout: std::time::Duration::from_secs(idle_timeout_secs),
-    }
+        idle_timeout,
+        heartbeat,
+        watch,
+    })
 }
 
 /// Bringup the full config for monitoring purposes.
@@ -109,12 +446,17 @@ pub async fn for_monitoring(
     let mut list = Vec::new();
 
     for core_server in &core_config.servers {
-        let bringup_server = server(core_server).await?;
+        let bringup_server = server(
+            core_server,
+            &core_config.oauth2_clients,
+            core_config.proxy.as_ref(),
+        )
+        .await?;
         let bringup_server = Arc::new(bringup_server);
 
         for core_mailbox in &core_server.mailboxes {
             let bringup_server = Arc::clone(&bringup_server);
-            let bringup_mailbox = mailbox(bringup_server, core_server, core_mailbox);
+            let bringup_mailbox = mailbox(bringup_server, core_server, core_mailbox)?;
             let bringup_mailbox = Arc::new(bringup_mailbox);
             list.push(bringup_mailbox);
         }
@@ -123,6 +465,32 @@ pub async fn for_monitoring(
     Ok(list)
 }
 
+/// Bringup the full config for monitoring purposes, waiting for the keyring
+/// to be unlocked.
+///
+/// Retries with a backoff while the keyring is locked, so starting at login
+/// before the keyring is unlocked does not fail.
+pub async fn for_monitoring_when_unlocked(
+    core_config: &config_core::Config,
+) -> Result<Vec<Arc<types::Mailbox>>, ResolveCredentialsError> {
+    let mut backoff = exp_backoff::State {
+        factor: 2,
+        max: std::time::Duration::from_secs(30),
+        value: std::time::Duration::from_secs(1),
+    };
+
+    loop {
+        match for_monitoring(core_config).await {
+            Err(error) if error.is_keyring_locked() => {
+                let delay = backoff.advance();
+                tracing::warn!(message = "keyring is locked, retrying", %error, ?delay);
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
 /// Bringup the partial config for server operations.
 pub async fn servers_only(
     core_config: &config_core::Config,
@@ -130,7 +498,12 @@ pub async fn servers_only(
     let mut list = Vec::new();
 
     for core_server in &core_config.servers {
-        let bringup_server = server(core_server).await?;
+        let bringup_server = server(
+            core_server,
+            &core_config.oauth2_clients,
+            core_config.proxy.as_ref(),
+        )
+        .await?;
 
         list.push(bringup_server);
     }
@@ -139,14 +512,16 @@ pub async fn servers_only(
 }
 
 /// Resolve the password from config, including keyring lookups.
+///
+/// The keyring account defaults to the username.
 async fn resolve_password(
-    credentials: &config_core::LoginCredentials,
+    password: &config_core::PasswordSource,
+    username: &str,
 ) -> Result<String, ResolveCredentialsError> {
-    match &credentials.password {
+    match password {
         config_core::PasswordSource::Plain(password) => Ok(password.clone()),
         config_core::PasswordSource::Keyring { keyring } => {
-            let keyring =
-                keyring::service_account(keyring, &credentials.username, keyring::DEFAULT_SERVICE);
+            let keyring = keyring::service_account(keyring, username, keyring::DEFAULT_SERVICE);
             let service = keyring.service.to_owned();
             let account = keyring.account.to_owned();
 
@@ -159,10 +534,205 @@ async fn resolve_password(
     }
 }
 
+/// Resolve the proxy settings, including keyring lookups.
+async fn resolve_proxy(
+    proxy: &config_core::ProxyConfig,
+) -> Result<Option<imap_proxy::Proxy>, ResolveCredentialsError> {
+    let proxy = match proxy {
+        config_core::ProxyConfig::Direct => return Ok(None),
+        config_core::ProxyConfig::Socks5(socks5) => imap_proxy::Proxy::Socks5 {
+            host: socks5.host.clone(),
+            port: socks5.port,
+            remote_dns: socks5.remote_dns.unwrap_or(true),
+            login: resolve_proxy_login(socks5.login.as_ref()).await?,
+        },
+        config_core::ProxyConfig::Http(http) => imap_proxy::Proxy::Http {
+            host: http.host.clone(),
+            port: http.port,
+            login: resolve_proxy_login(http.login.as_ref()).await?,
+        },
+    };
+    Ok(Some(proxy))
+}
+
+/// Resolve the proxy credentials.
+async fn resolve_proxy_login(
+    login: Option<&config_core::ProxyLogin>,
+) -> Result<Option<imap_proxy::Login>, ResolveCredentialsError> {
+    let Some(login) = login else {
+        return Ok(None);
+    };
+    let password = resolve_password(&login.password, &login.username).await?;
+    Ok(Some(imap_proxy::Login {
+        username: login.username.clone(),
+        password,
+    }))
+}
+
+/// Resolve the TLS client identity from config, including keyring lookups.
+async fn resolve_client_identity(
+    source: &config_core::ClientCertificateSource,
+    host: &str,
+) -> Result<imap_tls_core::ClientIdentity, ResolveCredentialsError> {
+    let identity = match source {
+        config_core::ClientCertificateSource::Files {
+            certificate_file,
+            key_file,
+        } => {
+            let certificate_chain = read_file(certificate_file).await?;
+            let private_key = read_file(key_file).await?;
+            imap_tls_core::ClientIdentity::from_pem(&certificate_chain, &private_key)?
+        }
+        config_core::ClientCertificateSource::Keyring { keyring } => {
+            let keyring = keyring::service_account(keyring, host, keyring::DEFAULT_SERVICE);
+            let service = keyring.service.to_owned();
+            let account = keyring.account.to_owned();
+
+            let bundle =
+                tokio::task::spawn_blocking(move || keyring_password::get(&service, &account))
+                    .await
+                    .unwrap()?;
+            imap_tls_core::ClientIdentity::from_pem(bundle.as_bytes(), bundle.as_bytes())?
+        }
+    };
+    Ok(identity)
+}
+
+/// Resolve the server certificate trust settings, reading the CA files.
+async fn resolve_trust(
+    tls: &config_core::TlsConfig,
+) -> Result<imap_tls_core::ServerTrust, ResolveCredentialsError> {
+    let mut trust = imap_tls_core::ServerTrust {
+        native_roots: tls.native_roots.unwrap_or(true),
+        verify_name: tls.verify_name.clone(),
+        ..Default::default()
+    };
+
+    for path in &tls.ca_files {
+        let pem = read_file(path).await?;
+        trust
+            .add_roots_pem(&pem)
+            .map_err(|source| ResolveCredentialsError::CaFile {
+                path: path.clone(),
+                source,
+            })?;
+    }
+
+    for pin in &tls.pins {
+        let pin = match pin {
+            config_core::CertificatePin::SpkiSha256(fingerprint) => {
+                imap_tls_core::Pin::parse_fingerprint(fingerprint)
+                    .map(imap_tls_core::Pin::SpkiSha256)
+            }
+            config_core::CertificatePin::CertificateSha256(fingerprint) => {
+                imap_tls_core::Pin::parse_fingerprint(fingerprint)
+                    .map(imap_tls_core::Pin::CertificateSha256)
+            }
+        };
+        trust
+            .pins
+            .push(pin.map_err(ResolveCredentialsError::CertificatePin)?);
+    }
+
+    Ok(trust)
+}
+
+/// Read a credentials file.
+async fn read_file(path: &std::path::Path) -> Result<Vec<u8>, ResolveCredentialsError> {
+    tokio::fs::read(path)
+        .await
+        .map_err(|source| ResolveCredentialsError::ReadFile {
+            path: path.to_owned(),
+            source,
+        })
+}
+
 /// Errors returned while resolving credentials.
 #[derive(Debug, thiserror::Error)]
 pub enum ResolveCredentialsError {
     /// Failed to read the password from the keyring.
     #[error(transparent)]
     Keyring(#[from] keyring_password::GetError),
+
+    /// Failed to read a credentials file.
+    #[error("failed to read {}: {source}", path.display())]
+    ReadFile {
+        /// The file path.
+        path: std::path::PathBuf,
+
+        /// Underlying I/O error.
+        source: std::io::Error,
+    },
+
+    /// The OAuth 2 session references an OAuth 2 client missing in the config.
+    #[error("unknown OAuth 2 client '{0}'")]
+    UnknownOAuth2Client(String),
+
+    /// The OAuth 2 client has an invalid URL.
+    #[error("invalid OAuth 2 URL: {0}")]
+    OAuth2Url(#[source] oauth2::url::ParseError),
+
+    /// Failed to build the HTTP client for OAuth 2.
+    #[error("failed to build the OAuth 2 HTTP client: {0}")]
+    HttpClient(#[source] reqwest::Error),
+
+    /// Failed to initialize the OAuth 2 token storage.
+    #[error("failed to initialize the OAuth 2 token storage: {0}")]
+    TokenStorage(#[source] keyring_core::Error),
+
+    /// The CA file has no valid certificates.
+    #[error("invalid CA file {}: {source}", path.display())]
+    CaFile {
+        /// The file path.
+        path: std::path::PathBuf,
+
+        /// Underlying parsing error.
+        source: imap_tls_core::TrustError,
+    },
+
+    /// The certificate pin is invalid.
+    #[error("invalid TLS certificate pin: {0}")]
+    CertificatePin(#[source] imap_tls_core::TrustError),
+
+    /// The TLS client certificate or key is invalid.
+    #[error("invalid TLS client certificate: {0}")]
+    ClientIdentity(#[from] imap_tls_core::ClientIdentityError),
+
+    /// An interval of a mailbox is zero, which would flood the server with
+    /// commands, or a time limit of a server is zero, which would fail every
+    /// connection.
+    #[error("{setting} for {} must be at least 1", interval_scope(.server, .mailbox.as_deref()))]
+    ZeroInterval {
+        /// The setting name.
+        setting: &'static str,
+
+        /// The server name.
+        server: String,
+
+        /// The mailbox name, none for a setting of the server.
+        mailbox: Option<String>,
+    },
 }
+
+/// Describe what a rejected interval belongs to.
+fn interval_scope(server: &str, mailbox: Option<&str>) -> String {
+    match mailbox {
+        Some(mailbox) => format!("mailbox '{mailbox}' on server '{server}'"),
+        None => format!("server '{server}'"),
+    }
+}
+
+impl ResolveCredentialsError {
+    /// Whether the keyring was locked, so resolving may succeed once the
+    /// user unlocks it.
+    pub fn is_keyring_locked(&self) -> bool {
+        match self {
+            Self::Keyring(keyring_password::GetError::Resolve { source, .. })
+            | Self::TokenStorage(source) => keyring_bridge::is_locked(source),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,298 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use keyring_core::api::{CredentialApi, CredentialStoreApi};
+use keyring_core::{Credential, CredentialStore, Entry};
+
+use super::*;
+
+/// Serialize the keyring tests, as they replace the global default store.
+static KEYRING_TEST_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// The keyring answers, in turn.
+type Answers = Arc<Mutex<VecDeque<keyring_core::Result<String>>>>;
+
+/// Keyring store answering every password lookup with the next answer.
+#[derive(Debug)]
+struct ScriptedStore {
+    /// The answers still to give.
+    answers: Answers,
+}
+
+/// A credential of the [`ScriptedStore`].
+#[derive(Debug, Clone)]
+struct ScriptedCredential {
+    /// The answers still to give.
+    answers: Answers,
+}
+
+/// The default keyring store for a test, restored on drop.
+struct ScriptedKeyring {
+    /// The previous default store.
+    previous: Option<Arc<CredentialStore>>,
+
+    /// The held test lock.
+    _lock: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl ScriptedKeyring {
+    /// Make a store with the answers the default one.
+    async fn install(answers: impl IntoIterator<Item = keyring_core::Result<String>>) -> Self {
+        let lock = KEYRING_TEST_LOCK.lock().await;
+        let store: Arc<CredentialStore> = Arc::new(ScriptedStore {
+            answers: Arc::new(Mutex::new(answers.into_iter().collect())),
+        });
+        let previous = keyring_core::get_default_store();
+        keyring_core::set_default_store(store);
+        Self {
+            previous,
+            _lock: lock,
+        }
+    }
+}
+
+impl Drop for ScriptedKeyring {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some(previous) => keyring_core::set_default_store(previous),
+            None => {
+                keyring_core::unset_default_store();
+            }
+        }
+    }
+}
+
+impl CredentialStoreApi for ScriptedStore {
+    fn vendor(&self) -> String {
+        "scripted".to_owned()
+    }
+
+    fn id(&self) -> String {
+        "scripted-store".to_owned()
+    }
+
+    fn build(
+        &self,
+        _service: &str,
+        _user: &str,
+        _modifiers: Option<&HashMap<&str, &str>>,
+    ) -> keyring_core::Result<Entry> {
+        Ok(Entry::new_with_credential(Arc::new(ScriptedCredential {
+            answers: Arc::clone(&self.answers),
+        })))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl CredentialApi for ScriptedCredential {
+    fn set_secret(&self, _secret: &[u8]) -> keyring_core::Result<()> {
+        unimplemented!("the tests only look passwords up")
+    }
+
+    fn get_secret(&self) -> keyring_core::Result<Vec<u8>> {
+        let answer = self.answers.lock().unwrap().pop_front();
+        answer
+            .unwrap_or(Err(keyring_core::Error::NoEntry))
+            .map(String::into_bytes)
+    }
+
+    fn delete_credential(&self) -> keyring_core::Result<()> {
+        unimplemented!("the tests only look passwords up")
+    }
+
+    fn get_credential(&self) -> keyring_core::Result<Option<Arc<Credential>>> {
+        Ok(Some(Arc::new(self.clone())))
+    }
+
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        None
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// A server with the password in the keyring.
+fn keyring_config() -> config_core::Config {
+    config_yaml::parse_yaml(
+        r#"
+servers:
+  - name: "test server"
+    host: "imap.example.com"
+    tls:
+      mode: implicit
+    login:
+      username: "user@example.com"
+      password:
+        keyring: {}
+    mailboxes:
+      - name: "INBOX"
+"#,
+    )
+    .unwrap()
+}
+
+/// The password the server authenticates with.
+async fn password(server: &types::Server) -> String {
+    match &*server.auth.read().await {
+        types::ServerAuth::Login { password, .. } => password.clone(),
+        auth => panic!("unexpected auth {auth:?}"),
+    }
+}
+
+/// A server with the mailbox settings.
+fn config(mailbox_settings: &str) -> config_core::Config {
+    let yaml = format!(
+        r#"
+servers:
+  - name: "test server"
+    host: "imap.example.com"
+    tls:
+      mode: implicit
+    login:
+      username: "user@example.com"
+      password: "secret"
+    mailboxes:
+      - name: "INBOX"
+        {mailbox_settings}
+"#
+    );
+    config_yaml::parse_yaml(&yaml).unwrap()
+}
+
+#[tokio::test]
+async fn zero_intervals_are_rejected() {
+    for setting in ["poll_interval_secs", "heartbeat_secs", "idle_timeout_secs"] {
+        let error = for_monitoring(&config(&format!("{setting}: 0")))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                &error,
+                ResolveCredentialsError::ZeroInterval { setting: rejected, .. }
+                    if *rejected == setting
+            ),
+            "{error}"
+        );
+    }
+
+    for setting in [
+        "connect_secs",
+        "tls_handshake_secs",
+        "greeting_secs",
+        "command_secs",
+    ] {
+        let mut config = config("");
+        let timeouts = &mut config.servers[0].timeouts;
+        *match setting {
+            "connect_secs" => &mut timeouts.connect_secs,
+            "tls_handshake_secs" => &mut timeouts.tls_handshake_secs,
+            "greeting_secs" => &mut timeouts.greeting_secs,
+            _ => &mut timeouts.command_secs,
+        } = Some(0);
+        let error = for_monitoring(&config).await.unwrap_err();
+
+        assert!(
+            matches!(
+                &error,
+                ResolveCredentialsError::ZeroInterval { setting: rejected, mailbox: None, .. }
+                    if *rejected == setting
+            ),
+            "{error}"
+        );
+        assert_eq!(
+            error.to_string(),
+            format!("{setting} for server 'test server' must be at least 1")
+        );
+    }
+}
+
+#[tokio::test]
+async fn intervals_are_brought_up() {
+    let mailboxes = for_monitoring(&config("poll_interval_secs: 30"))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        mailboxes[0].watch,
+        imap_checker::WatchMode::Auto {
+            poll_interval: std::time::Duration::from_secs(30)
+        }
+    );
+    assert_eq!(mailboxes[0].heartbeat, None);
+}
+
+#[tokio::test]
+async fn refreshed_auth_has_the_fixed_password() {
+    let _keyring =
+        ScriptedKeyring::install([Ok("rejected".to_owned()), Ok("fixed".to_owned())]).await;
+    let config = keyring_config();
+
+    let server = server(&config.servers[0], &config.oauth2_clients, None)
+        .await
+        .unwrap();
+    assert_eq!(password(&server).await, "rejected");
+
+    refresh_auth(&server).await.unwrap();
+    assert_eq!(password(&server).await, "fixed");
+}
+
+#[tokio::test]
+async fn failed_refresh_keeps_the_auth() {
+    let _keyring = ScriptedKeyring::install([Ok("rejected".to_owned())]).await;
+    let config = keyring_config();
+
+    let server = server(&config.servers[0], &config.oauth2_clients, None)
+        .await
+        .unwrap();
+    let error = refresh_auth(&server).await.unwrap_err();
+
+    assert!(
+        matches!(error, ResolveCredentialsError::Keyring(_)),
+        "{error}"
+    );
+    assert_eq!(password(&server).await, "rejected");
+}
+
+/// The error of a keyring that stays locked.
+fn keyring_locked() -> keyring_core::Error {
+    keyring_core::Error::NoStorageAccess(Box::new(keyring_bridge::LockedError { source: None }))
+}
+
+#[tokio::test(start_paused = true)]
+async fn monitoring_waits_for_the_keyring_to_unlock() {
+    let _keyring = ScriptedKeyring::install([
+        Err(keyring_locked()),
+        Err(keyring_locked()),
+        Ok("secret".to_owned()),
+    ])
+    .await;
+
+    let mailboxes = for_monitoring_when_unlocked(&keyring_config())
+        .await
+        .unwrap();
+
+    assert_eq!(password(&mailboxes[0].server).await, "secret");
+}
+
+#[tokio::test(start_paused = true)]
+async fn monitoring_fails_on_other_keyring_errors() {
+    let _keyring =
+        ScriptedKeyring::install([Err(keyring_core::Error::NoEntry), Ok("secret".to_owned())])
+            .await;
+
+    let error = for_monitoring_when_unlocked(&keyring_config())
+        .await
+        .unwrap_err();
+
+    assert!(!error.is_keyring_locked(), "{error}");
+    assert!(
+        matches!(error, ResolveCredentialsError::Keyring(_)),
+        "{error}"
+    );
+}
@@ -0,0 +1,125 @@
+//! OAuth 2 token storage bringup.
+
+use oauth2_token_storage_core::{Data, DataRef, LoadError, UpdateError};
+
+/// The token storage selected in the config.
+#[derive(Debug)]
+pub enum TokenStorage {
+    /// Tokens in the keyring.
+    Keyring(oauth2_token_storage_keyring::KeyringTokenStorage),
+
+    /// Tokens in an encrypted file.
+    File(oauth2_token_storage_file::FileTokenStorage),
+}
+
+/// Errors from the selected token storage.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Keyring storage error.
+    #[error(transparent)]
+    Keyring(oauth2_token_storage_keyring::Error),
+
+    /// File storage error.
+    #[error(transparent)]
+    File(oauth2_token_storage_file::Error),
+}
+
+/// Initialize the token storage for the given config.
+///
+/// The keyring account defaults to the session user.
+pub async fn init(
+    config: &config_core::OAuth2TokenStorage,
+    user: &str,
+) -> Result<TokenStorage, keyring_core::Error> {
+    Ok(match config {
+        config_core::OAuth2TokenStorage::Keyring(keyring) => {
+            let keyring =
+                crate::keyring::service_account(keyring, user, crate::keyring::DEFAULT_SERVICE);
+            let storage = oauth2_token_storage_keyring::KeyringTokenStorage::init(
+                keyring.service.to_owned(),
+                keyring.account.to_owned(),
+            )
+            .await?;
+            TokenStorage::Keyring(storage)
+        }
+        config_core::OAuth2TokenStorage::File(file) => {
+            TokenStorage::File(oauth2_token_storage_file::FileTokenStorage::new(
+                &file.path,
+                crate::file_key_source(&file.key),
+            ))
+        }
+    })
+}
+
+/// Map the load error of the underlying storage.
+fn map_load_error<E>(error: LoadError<E>, f: impl FnOnce(E) -> Error) -> LoadError<Error> {
+    match error {
+        LoadError::NoData(error) => LoadError::NoData(f(error)),
+        LoadError::Internal(error) => LoadError::Internal(f(error)),
+    }
+}
+
+/// Map the update error of the underlying storage.
+fn map_update_error<E, U>(
+    error: UpdateError<E, E, U>,
+    f: impl FnOnce(E) -> Error,
+) -> UpdateError<Error, Error, U> {
+    match error {
+        UpdateError::Load(error) => UpdateError::Load(map_load_error(error, f)),
+        UpdateError::Update(error) => UpdateError::Update(error),
+        UpdateError::Store(error) => UpdateError::Store(f(error)),
+    }
+}
+
+impl oauth2_token_storage_core::TokenStorage for TokenStorage {
+    type StoreError = Error;
+    type LoadError = Error;
+    type ClearError = Error;
+
+    async fn store<'a>(&'a self, data: DataRef<'a>) -> Result<(), Self::StoreError> {
+        match self {
+            Self::Keyring(storage) => storage.store(data).await.map_err(Error::Keyring),
+            Self::File(storage) => storage.store(data).await.map_err(Error::File),
+        }
+    }
+
+    async fn load(&self) -> Result<Data, LoadError<Self::LoadError>> {
+        match self {
+            Self::Keyring(storage) => storage
+                .load()
+                .await
+                .map_err(|error| map_load_error(error, Error::Keyring)),
+            Self::File(storage) => storage
+                .load()
+                .await
+                .map_err(|error| map_load_error(error, Error::File)),
+        }
+    }
+
+    async fn update<'a, E, Fut>(
+        &'a self,
+        update: impl FnOnce(Data) -> Fut + Send + 'a,
+    ) -> Result<Data, UpdateError<Self::LoadError, Self::StoreError, E>>
+    where
+        E: 'a,
+        Fut: std::future::Future<Output = Result<Option<Data>, E>> + Send + 'a,
+    {
+        match self {
+            Self::Keyring(storage) => storage
+                .update(update)
+                .await
+                .map_err(|error| map_update_error(error, Error::Keyring)),
+            Self::File(storage) => storage
+                .update(update)
+                .await
+                .map_err(|error| map_update_error(error, Error::File)),
+        }
+    }
+
+    async fn clear(&self) -> Result<(), Self::ClearError> {
+        match self {
+            Self::Keyring(storage) => storage.clear().await.map_err(Error::Keyring),
+            Self::File(storage) => storage.clear().await.map_err(Error::File),
+        }
+    }
+}
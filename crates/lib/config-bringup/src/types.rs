@@ -20,8 +20,61 @@ pub struct Server {
     /// TLS server name (SNI).
     pub tls_server_name: String,
 
-    /// IMAP authentication.
-    pub auth: ServerAuth,
+    /// TLS client identity for mutual TLS.
+    pub tls_client_identity: Option<imap_tls_core::ClientIdentity>,
+
+    /// How to verify the server certificate.
+    pub tls_trust: imap_tls_core::ServerTrust,
+
+    /// Which TLS versions and application protocol to negotiate.
+    pub tls_policy: imap_tls_core::TlsPolicy,
+
+    /// Allow plaintext connections to servers that are not on a loopback
+    /// address.
+    pub allow_remote_plaintext: bool,
+
+    /// IP version to connect over, both when unset.
+    pub ip_family: Option<imap_connect::IpFamily>,
+
+    /// Proxy to connect through.
+    pub proxy: Option<imap_proxy::Proxy>,
+
+    /// Command to talk to the server through.
+    pub tunnel: Option<String>,
+
+    /// Unix socket to talk to the server over.
+    pub socket: Option<std::path::PathBuf>,
+
+    /// Time limit for opening the TCP connection.
+    pub connect_timeout: std::time::Duration,
+
+    /// Time limits for establishing the connection and for each command.
+    pub timeouts: imap_tls::Timeouts,
+
+    /// Client identification to send after auth, none when disabled.
+    pub client_id: Option<imap_session::ClientId>,
+
+    /// Whether to compress the connection when the server supports it.
+    pub compress: bool,
+
+    /// Where to trace the exchange with the server, not traced when unset.
+    pub trace: Option<imap_trace::Config>,
+
+    /// IMAP authentication, resolved again by [`crate::refresh_auth`].
+    pub auth: tokio::sync::RwLock<ServerAuth>,
+
+    /// What the authentication is resolved from.
+    pub auth_source: AuthSource,
+}
+
+/// What the server authentication is resolved from, to resolve it again.
+#[derive(Debug)]
+pub struct AuthSource {
+    /// The authentication config.
+    pub(crate) auth: config_core::Auth,
+
+    /// The OAuth 2 clients the authentication may refer to.
+    pub(crate) oauth2_clients: std::collections::HashMap<String, config_core::OAuth2ClientConfig>,
 }
 
 /// Fully-resolved IMAP authentication config.
@@ -34,6 +87,9 @@ pub enum ServerAuth {
 
         /// Password for IMAP authentication.
         password: String,
+
+        /// Pinned authentication mechanism, negotiated when unset.
+        mechanism: Option<imap_auth::PasswordMechanism>,
     },
 
     /// Authenticate with the static OAuth 2 credentials.
@@ -43,9 +99,39 @@ pub enum ServerAuth {
 
         /// Access token for OAuth2 IMAP authentication.
         access_token: String,
+
+        /// Pinned SASL mechanism, negotiated when unset.
+        mechanism: Option<imap_auth::OAuth2Mechanism>,
+    },
+
+    /// Authenticate with an access token from the managed OAuth 2 session.
+    OAuth2Session {
+        /// Username for OAuth2 IMAP authentication.
+        user: String,
+
+        /// Pinned SASL mechanism, negotiated when unset.
+        mechanism: Option<imap_auth::OAuth2Mechanism>,
+
+        /// The session manager issuing the access tokens.
+        session: Box<tokio::sync::Mutex<OAuth2SessionManager>>,
+    },
+
+    /// Authenticate with SASL `EXTERNAL`.
+    External {
+        /// Authorization identity, derived by the server when unset.
+        authzid: Option<String>,
     },
 }
 
+/// The OAuth 2 session manager for the configured token storage.
+pub type OAuth2SessionManager = oauth2_session::Manager<
+    crate::token_storage::TokenStorage,
+    oauth2::EndpointNotSet,
+    oauth2::EndpointNotSet,
+    oauth2::EndpointNotSet,
+    oauth2::EndpointMaybeSet,
+>;
+
 /// Fully resolved bringup configuration shared across mailboxes.
 #[derive(Debug)]
 pub struct Mailbox {
@@ -57,4 +143,10 @@ pub struct Mailbox {
 
     /// Idle timeout.
     pub idle_timeout: std::time::Duration,
+
+    /// Interval for checking the connection while idle, if enabled.
+    pub heartbeat: Option<std::time::Duration>,
+
+    /// How to wait for changes to the mailbox.
+    pub watch: imap_checker::WatchMode,
 }
@@ -11,6 +11,78 @@ pub struct Config {
     /// OAuth 2 client configurations.
     #[cfg_attr(feature = "serde", serde(default))]
     pub oauth2_clients: std::collections::HashMap<String, OAuth2ClientConfig>,
+
+    /// Keyring settings.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub keyring: KeyringConfig,
+
+    /// Proxy for the servers without their own proxy settings.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub proxy: Option<ProxyConfig>,
+}
+
+/// Keyring settings.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyringConfig {
+    /// Which credential store to keep the keyring secrets in.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub backend: KeyringBackend,
+
+    /// Encrypted file store settings.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub file: FileKeyringConfig,
+
+    /// Secret Service settings, used on Linux and FreeBSD.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub secret_service: SecretServiceConfig,
+}
+
+/// Keyring credential stores.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum KeyringBackend {
+    /// The platform keyring, or the encrypted file when it is unavailable.
+    #[default]
+    Auto,
+
+    /// Only the platform keyring.
+    Platform,
+
+    /// Only the encrypted file.
+    File,
+}
+
+/// Secret Service keyring store settings.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SecretServiceConfig {
+    /// Label of the collection to keep the items in, the default collection
+    /// if unset.
+    pub collection: Option<String>,
+
+    /// Label of newly created items.
+    ///
+    /// `{service}` and `{account}` are replaced by the keyring service and
+    /// account of the item.
+    pub label: Option<String>,
+}
+
+/// Encrypted file keyring store settings.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileKeyringConfig {
+    /// Path of the encrypted file, in the local data directory by default.
+    pub path: Option<std::path::PathBuf>,
+
+    /// The source of the encryption key, a generated key file next to the
+    /// encrypted file by default.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub key: Option<FileKeySource>,
 }
 
 /// A monitored IMAP server.
@@ -27,6 +99,9 @@ pub struct ServerConfig {
     /// Optional port override.
     pub port: Option<u16>,
 
+    /// IP version to connect over, both when unset.
+    pub ip_family: Option<IpFamily>,
+
     /// TLS settings.
     pub tls: TlsConfig,
 
@@ -39,6 +114,186 @@ pub struct ServerConfig {
 
     /// Idle timeout override for this server (seconds).
     pub idle_timeout_secs: Option<u64>,
+
+    /// Interval for checking the connection with `NOOP` while idle
+    /// (seconds), disabled when unset.
+    pub heartbeat_secs: Option<u64>,
+
+    /// How to wait for changes on this server, IDLE when supported and
+    /// polling otherwise when unset.
+    pub watch: Option<WatchMode>,
+
+    /// Interval for polling the counts when not idle (seconds).
+    pub poll_interval_secs: Option<u64>,
+
+    /// Proxy override for this server.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub proxy: Option<ProxyConfig>,
+
+    /// Command to talk to the server through instead of connecting to it,
+    /// e.g. `ssh mailhost /usr/lib/dovecot/imap`.
+    ///
+    /// The command is trusted to secure the connection, so the TLS and
+    /// proxy settings are ignored. A server that greets with `PREAUTH` is
+    /// not logged in to.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tunnel: Option<String>,
+
+    /// Unix socket to talk to the server over instead of connecting to it,
+    /// e.g. `/run/dovecot/imap`.
+    ///
+    /// The socket is local to the machine, so the connection is plaintext
+    /// and the TLS and proxy settings are ignored.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub socket: Option<std::path::PathBuf>,
+
+    /// Time limits for talking to this server.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub timeouts: TimeoutsConfig,
+
+    /// Client identification sent with `ID` (RFC 2971).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub id: IdConfig,
+
+    /// Compress the connection with `COMPRESS=DEFLATE` (RFC 4978) when the
+    /// server supports it, e.g. over metered connections.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compress: bool,
+
+    /// Trace the exchange with the server, for debugging it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub trace: TraceConfig,
+}
+
+/// Client identification sent after login to servers that support `ID`.
+///
+/// Some servers refuse to select a mailbox until the client identifies
+/// itself.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdConfig {
+    /// Whether to identify the client, enabled when unset.
+    pub enabled: Option<bool>,
+
+    /// Client name override, `mail-notifier` when unset.
+    pub name: Option<String>,
+
+    /// Client version override, the running version when unset.
+    pub version: Option<String>,
+}
+
+/// Tracing of the exchange with a server.
+///
+/// The credentials and the message data are redacted from the trace.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceConfig {
+    /// Whether to log the exchange, with the `imap_wire` target.
+    ///
+    /// The target is shared by all the servers; the lines are in a span with
+    /// a `server_name` field to filter on, e.g.
+    /// `imap_wire[{server_name=work}]=info`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub enabled: bool,
+
+    /// File to append the exchange to, which also enables the trace.
+    pub file: Option<std::path::PathBuf>,
+}
+
+/// Time limits for talking to a server, in seconds.
+///
+/// A limit that expires fails the connection, so it is retried.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeoutsConfig {
+    /// Limit for opening the TCP connection, including the proxy handshake.
+    pub connect_secs: Option<u64>,
+
+    /// Limit for the TLS handshake.
+    pub tls_handshake_secs: Option<u64>,
+
+    /// Limit for receiving the server greeting.
+    pub greeting_secs: Option<u64>,
+
+    /// Limit for the server to answer each command.
+    pub command_secs: Option<u64>,
+}
+
+/// IP version.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    /// IPv4 only.
+    V4,
+
+    /// IPv6 only.
+    V6,
+}
+
+/// Proxy settings.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProxyConfig {
+    /// Connect directly, overriding the global proxy.
+    Direct,
+
+    /// Connect through a SOCKS5 proxy, e.g. Tor.
+    Socks5(Socks5ProxyConfig),
+
+    /// Connect through an HTTP proxy with `CONNECT`.
+    Http(HttpProxyConfig),
+}
+
+/// SOCKS5 proxy settings.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Socks5ProxyConfig {
+    /// Hostname or IP address of the proxy.
+    pub host: String,
+
+    /// Port of the proxy.
+    pub port: u16,
+
+    /// Whether the proxy resolves the server host name, enabled by default.
+    pub remote_dns: Option<bool>,
+
+    /// Proxy credentials.
+    pub login: Option<ProxyLogin>,
+}
+
+/// HTTP proxy settings.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpProxyConfig {
+    /// Hostname or IP address of the proxy.
+    pub host: String,
+
+    /// Port of the proxy.
+    pub port: u16,
+
+    /// Proxy credentials.
+    pub login: Option<ProxyLogin>,
+}
+
+/// Proxy credentials.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyLogin {
+    /// Username for the proxy.
+    pub username: String,
+
+    /// Password for the proxy.
+    ///
+    /// The keyring account defaults to the username.
+    pub password: PasswordSource,
 }
 
 /// TLS configuration for a server.
@@ -51,6 +306,91 @@ pub struct TlsConfig {
 
     /// Optional override for the TLS server name (SNI).
     pub server_name: Option<String>,
+
+    /// Optional name to verify the server certificate for, instead of the
+    /// TLS server name.
+    pub verify_name: Option<String>,
+
+    /// The oldest TLS version to accept, the backend default when unset.
+    pub min_version: Option<TlsVersion>,
+
+    /// The application protocol to offer through ALPN, usually `imap`.
+    pub alpn: Option<String>,
+
+    /// Client certificate to present for mutual TLS.
+    pub client_certificate: Option<ClientCertificateSource>,
+
+    /// PEM files with extra CA certificates to trust.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ca_files: Vec<std::path::PathBuf>,
+
+    /// Whether to trust the system root certificates, enabled when unset.
+    pub native_roots: Option<bool>,
+
+    /// Fingerprints the server certificate must match.
+    ///
+    /// Without any trusted roots, a matching pin is all it takes to trust
+    /// the certificate, e.g. a self-signed one.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pins: Vec<CertificatePin>,
+
+    /// Allow the `none` mode for servers that are not on a loopback address.
+    ///
+    /// The credentials and mail are then readable by anyone on the network
+    /// path.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allow_remote_plaintext: bool,
+}
+
+/// A TLS protocol version.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TlsVersion {
+    /// TLS 1.2.
+    #[cfg_attr(feature = "serde", serde(rename = "1.2"))]
+    V1_2,
+
+    /// TLS 1.3.
+    #[cfg_attr(feature = "serde", serde(rename = "1.3"))]
+    V1_3,
+}
+
+/// A SHA-256 fingerprint pinning the server certificate.
+///
+/// The fingerprint is hex, optionally colon separated, or base64.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertificatePin {
+    /// Fingerprint of the DER encoded subject public key info.
+    SpkiSha256(String),
+
+    /// Fingerprint of the DER encoded certificate.
+    CertificateSha256(String),
+}
+
+/// Source for a TLS client certificate and its private key.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientCertificateSource {
+    /// PEM files on disk.
+    Files {
+        /// Path to the PEM-encoded certificate chain.
+        certificate_file: std::path::PathBuf,
+
+        /// Path to the PEM-encoded private key.
+        key_file: std::path::PathBuf,
+    },
+
+    /// A keyring entry holding both the PEM certificate chain and the private
+    /// key, nested under a `keyring` field.
+    Keyring {
+        /// Keyring reference for resolving the PEM bundle.
+        ///
+        /// The account defaults to the server host.
+        keyring: KeyringRef,
+    },
 }
 
 /// Supported TLS modes.
@@ -64,6 +404,12 @@ pub enum TlsMode {
     /// STARTTLS upgrade (usually port 143).
     #[cfg_attr(feature = "serde", serde(rename = "starttls", alias = "start_tls"))]
     StartTls,
+
+    /// No TLS (usually port 143), for local bridges.
+    ///
+    /// Only allowed for loopback addresses, unless
+    /// [`TlsConfig::allow_remote_plaintext`] is set.
+    None,
 }
 
 /// IMAP authentication settings.
@@ -81,6 +427,10 @@ pub enum Auth {
     /// Authenticate via a managed OAuth 2 session.
     #[cfg_attr(feature = "serde", serde(rename = "oauth2_session"))]
     OAuth2Session(OAuth2Session),
+
+    /// Authenticate via SASL `EXTERNAL`, typically with a TLS client
+    /// certificate.
+    External(ExternalCredentials),
 }
 
 /// Login credentials for IMAP authentication.
@@ -93,6 +443,48 @@ pub struct LoginCredentials {
 
     /// Password for IMAP authentication.
     pub password: PasswordSource,
+
+    /// Pin the authentication mechanism instead of negotiating it.
+    pub auth_mechanism: Option<PasswordMechanism>,
+}
+
+/// Credentials for SASL `EXTERNAL` authentication.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExternalCredentials {
+    /// Authorization identity to act as.
+    ///
+    /// Derived by the server from the external credentials when unset.
+    pub authzid: Option<String>,
+}
+
+/// Password authentication mechanisms.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PasswordMechanism {
+    /// The IMAP `LOGIN` command.
+    Login,
+
+    /// The `PLAIN` SASL mechanism.
+    Plain,
+
+    /// The `SCRAM-SHA-1` SASL mechanism.
+    #[cfg_attr(feature = "serde", serde(alias = "scram-sha-1"))]
+    ScramSha1,
+
+    /// The `SCRAM-SHA-1-PLUS` SASL mechanism.
+    #[cfg_attr(feature = "serde", serde(alias = "scram-sha-1-plus"))]
+    ScramSha1Plus,
+
+    /// The `SCRAM-SHA-256` SASL mechanism.
+    #[cfg_attr(feature = "serde", serde(alias = "scram-sha-256"))]
+    ScramSha256,
+
+    /// The `SCRAM-SHA-256-PLUS` SASL mechanism.
+    #[cfg_attr(feature = "serde", serde(alias = "scram-sha-256-plus"))]
+    ScramSha256Plus,
 }
 
 /// OAuth 2 credentials for IMAP authentication.
@@ -105,6 +497,9 @@ pub struct OAuth2Credentials {
 
     /// Access token for OAuth 2 IMAP authentication.
     pub access_token: String,
+
+    /// Pin the SASL mechanism instead of negotiating it.
+    pub auth_mechanism: Option<OAuth2Mechanism>,
 }
 
 /// Managed OAuth 2 session for IMAP authentication.
@@ -118,11 +513,72 @@ pub struct OAuth2Session {
     /// OAuth 2 client to use for IMAP authentication.
     pub oauth2_client: String,
 
-    /// The keyring to use for the OAuth 2 credentials.
-    pub keyring: KeyringRef,
+    /// Where to store the OAuth 2 tokens.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub token_storage: OAuth2TokenStorage,
 
     /// If the token expires in less than this duration - refresh it (secs).
     pub expiration_immenance_tolerance_secs: Option<u64>,
+
+    /// Pin the SASL mechanism instead of negotiating it.
+    pub auth_mechanism: Option<OAuth2Mechanism>,
+}
+
+/// Storage for the OAuth 2 session tokens.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum OAuth2TokenStorage {
+    /// Store the tokens in the keyring.
+    Keyring(KeyringRef),
+
+    /// Store the tokens in an encrypted file.
+    File(OAuth2TokenFile),
+}
+
+/// Encrypted file storage for the OAuth 2 session tokens.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuth2TokenFile {
+    /// Path to the token file.
+    pub path: std::path::PathBuf,
+
+    /// The source of the encryption key.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub key: FileKeySource,
+}
+
+/// Source of the key to encrypt a file with.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileKeySource {
+    /// Derive the key from a passphrase.
+    Passphrase {
+        /// The passphrase.
+        passphrase: String,
+    },
+
+    /// Derive the key from the contents of a key file.
+    KeyFile {
+        /// Path to the key file.
+        key_file: std::path::PathBuf,
+    },
+}
+
+/// OAuth 2 SASL mechanisms.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OAuth2Mechanism {
+    /// The non-standard `XOAUTH2` mechanism.
+    #[cfg_attr(feature = "serde", serde(rename = "xoauth2"))]
+    XOAuth2,
+
+    /// The `OAUTHBEARER` mechanism (RFC 7628).
+    #[cfg_attr(feature = "serde", serde(rename = "oauthbearer"))]
+    OAuthBearer,
 }
 
 /// OAuth 2 client configuration.
@@ -144,6 +600,9 @@ pub struct OAuth2ClientConfig {
 
     /// OAuth 2 device authorization URL.
     pub device_authorization_url: Option<String>,
+
+    /// OAuth 2 token revocation URL (RFC 7009).
+    pub revocation_url: Option<String>,
 }
 
 /// Source for a password value.
@@ -183,4 +642,25 @@ pub struct MailboxConfig {
 
     /// Idle timeout override for this mailbox (seconds).
     pub idle_timeout_secs: Option<u64>,
+
+    /// Heartbeat interval override for this mailbox (seconds).
+    pub heartbeat_secs: Option<u64>,
+
+    /// Watch mode override for this mailbox.
+    pub watch: Option<WatchMode>,
+
+    /// Poll interval override for this mailbox (seconds).
+    pub poll_interval_secs: Option<u64>,
+}
+
+/// How to wait for changes to a mailbox.
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    /// IDLE, failing when the server does not support it.
+    Idle,
+
+    /// Poll the counts with `STATUS`, for servers whose IDLE misbehaves.
+    Poll,
 }
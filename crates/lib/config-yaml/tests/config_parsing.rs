@@ -11,19 +11,42 @@ fn base_server() -> ServerConfig {
         name: "test server".to_string(),
         host: "imap.example.com".to_string(),
         port: None,
+        ip_family: None,
         tls: TlsConfig {
             mode: TlsMode::Implicit,
             server_name: None,
+            verify_name: None,
+            min_version: None,
+            alpn: None,
+            client_certificate: None,
+            ca_files: Vec::new(),
+            native_roots: None,
+            pins: Vec::new(),
+            allow_remote_plaintext: false,
         },
         auth: Auth::Login(LoginCredentials {
             username: "user@example.com".to_string(),
             password: PasswordSource::Plain("secret".to_string()),
+            auth_mechanism: None,
         }),
         mailboxes: vec![MailboxConfig {
             name: "INBOX".to_string(),
             idle_timeout_secs: None,
+            heartbeat_secs: None,
+            watch: None,
+            poll_interval_secs: None,
         }],
         idle_timeout_secs: None,
+        heartbeat_secs: None,
+        watch: None,
+        poll_interval_secs: None,
+        proxy: None,
+        tunnel: None,
+        socket: None,
+        timeouts: TimeoutsConfig::default(),
+        id: IdConfig::default(),
+        compress: false,
+        trace: TraceConfig::default(),
     }
 }
 
@@ -35,6 +58,8 @@ fn test_basic_config_parsing() {
     let expected = Config {
         servers: vec![base_server()],
         oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
     };
 
     assert_eq!(config, expected);
@@ -50,10 +75,57 @@ fn test_oauth2_config_parsing() {
             auth: Auth::OAuth2Credentials(OAuth2Credentials {
                 user: "user@example.com".to_string(),
                 access_token: "token123".to_string(),
+                auth_mechanism: None,
             }),
             ..base_server()
         }],
         oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
+    };
+
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_oauth2_mechanism_config_parsing() {
+    let yaml = include_str!("fixtures/oauth2_mechanism.yml");
+    let config = must_parse(yaml);
+
+    let expected = Config {
+        servers: vec![ServerConfig {
+            auth: Auth::OAuth2Credentials(OAuth2Credentials {
+                user: "user@example.com".to_string(),
+                access_token: "token123".to_string(),
+                auth_mechanism: Some(OAuth2Mechanism::OAuthBearer),
+            }),
+            ..base_server()
+        }],
+        oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
+    };
+
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_password_mechanism_config_parsing() {
+    let yaml = include_str!("fixtures/password_mechanism.yml");
+    let config = must_parse(yaml);
+
+    let expected = Config {
+        servers: vec![ServerConfig {
+            auth: Auth::Login(LoginCredentials {
+                username: "user@example.com".to_string(),
+                password: PasswordSource::Plain("secret".to_string()),
+                auth_mechanism: Some(PasswordMechanism::ScramSha256),
+            }),
+            ..base_server()
+        }],
+        oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
     };
 
     assert_eq!(config, expected);
@@ -74,10 +146,13 @@ fn test_keyring_config_parsing() {
                         account: None,
                     },
                 },
+                auth_mechanism: None,
             }),
             ..base_server()
         }],
         oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
     };
 
     assert_eq!(config, expected);
@@ -97,6 +172,8 @@ fn test_starttls_config_parsing() {
             ..base_server()
         }],
         oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
     };
 
     assert_eq!(config, expected);
@@ -116,6 +193,30 @@ fn test_starttls_alias_config_parsing() {
             ..base_server()
         }],
         oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
+    };
+
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_plaintext_config_parsing() {
+    let yaml = include_str!("fixtures/plaintext.yml");
+    let config = must_parse(yaml);
+
+    let expected = Config {
+        servers: vec![ServerConfig {
+            tls: TlsConfig {
+                mode: TlsMode::None,
+                allow_remote_plaintext: true,
+                ..base_server().tls
+            },
+            ..base_server()
+        }],
+        oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
     };
 
     assert_eq!(config, expected);
@@ -132,6 +233,8 @@ fn test_idle_timeout_config_parsing() {
             ..base_server()
         }],
         oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
     };
 
     assert_eq!(config, expected);
@@ -152,11 +255,372 @@ fn test_keyring_overrides_config_parsing() {
                         account: Some("user@example.com".to_string()),
                     },
                 },
+                auth_mechanism: None,
             }),
             ..base_server()
         }],
         oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
     };
 
     assert_eq!(config, expected);
 }
+
+#[test]
+fn test_client_certificate_config_parsing() {
+    let yaml = include_str!("fixtures/client_certificate.yml");
+    let config = must_parse(yaml);
+
+    let expected = Config {
+        servers: vec![ServerConfig {
+            tls: TlsConfig {
+                client_certificate: Some(ClientCertificateSource::Files {
+                    certificate_file: "/etc/mail-notifier/device.crt".into(),
+                    key_file: "/etc/mail-notifier/device.key".into(),
+                }),
+                ..base_server().tls
+            },
+            auth: Auth::External(ExternalCredentials { authzid: None }),
+            ..base_server()
+        }],
+        oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
+    };
+
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_client_certificate_keyring_config_parsing() {
+    let yaml = include_str!("fixtures/client_certificate_keyring.yml");
+    let config = must_parse(yaml);
+
+    let expected = Config {
+        servers: vec![ServerConfig {
+            tls: TlsConfig {
+                client_certificate: Some(ClientCertificateSource::Keyring {
+                    keyring: KeyringRef {
+                        service: None,
+                        account: Some("device".to_string()),
+                    },
+                }),
+                ..base_server().tls
+            },
+            auth: Auth::External(ExternalCredentials {
+                authzid: Some("user@example.com".to_string()),
+            }),
+            ..base_server()
+        }],
+        oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
+    };
+
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_oauth2_session_keyring_config_parsing() {
+    let yaml = include_str!("fixtures/oauth2_session_keyring.yml");
+    let config = must_parse(yaml);
+
+    let expected = Config {
+        servers: vec![ServerConfig {
+            auth: Auth::OAuth2Session(OAuth2Session {
+                user: "user@example.com".to_string(),
+                oauth2_client: "example".to_string(),
+                token_storage: OAuth2TokenStorage::Keyring(KeyringRef {
+                    service: Some("mail-notifier-oauth2".to_string()),
+                    account: None,
+                }),
+                expiration_immenance_tolerance_secs: None,
+                auth_mechanism: None,
+            }),
+            ..base_server()
+        }],
+        oauth2_clients: Default::default(),
+        keyring: Default::default(),
+        proxy: None,
+    };
+
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_oauth2_session_file_config_parsing() {
+    let yaml = include_str!("fixtures/oauth2_session_file.yml");
+    let config = must_parse(yaml);
+
+    let expected = Config {
+        servers: vec![ServerConfig {
+            auth: Auth::OAuth2Session(OAuth2Session {
+                user: "user@example.com".to_string(),
+                oauth2_client: "example".to_string(),
+                token_storage: OAuth2TokenStorage::File(OAuth2TokenFile {
+                    path: "/var/lib/mail-notifier/token".into(),
+                    key: FileKeySource::KeyFile {
+                        key_file: "/etc/mail-notifier/token.key".into(),
+                    },
+                }),
+                expiration_immenance_tolerance_secs: None,
+                auth_mechanism: None,
+            }),
+            ..base_server()
+        }],
+        oauth2_clients: [(
+            "example".to_string(),
+            OAuth2ClientConfig {
+                client_id: "client".to_string(),
+                client_secret: "secret".to_string(),
+                token_url: "https://auth.example.com/token".to_string(),
+                auth_url: None,
+                device_authorization_url: None,
+                revocation_url: None,
+            },
+        )]
+        .into(),
+        keyring: Default::default(),
+        proxy: None,
+    };
+
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_keyring_file_backend_config_parsing() {
+    let yaml = include_str!("fixtures/keyring_file_backend.yml");
+    let config = must_parse(yaml);
+
+    let expected = Config {
+        servers: vec![ServerConfig {
+            auth: Auth::Login(LoginCredentials {
+                username: "user@example.com".to_string(),
+                password: PasswordSource::Keyring {
+                    keyring: KeyringRef {
+                        service: None,
+                        account: None,
+                    },
+                },
+                auth_mechanism: None,
+            }),
+            ..base_server()
+        }],
+        oauth2_clients: Default::default(),
+        keyring: KeyringConfig {
+            backend: KeyringBackend::File,
+            file: FileKeyringConfig {
+                path: Some("/var/lib/mail-notifier/keyring.enc".into()),
+                key: Some(FileKeySource::Passphrase {
+                    passphrase: "correct horse".to_string(),
+                }),
+            },
+            secret_service: Default::default(),
+        },
+        proxy: None,
+    };
+
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_keyring_secret_service_config_parsing() {
+    let yaml = include_str!("fixtures/keyring_secret_service.yml");
+    let config = must_parse(yaml);
+
+    let expected = KeyringConfig {
+        backend: KeyringBackend::Platform,
+        file: Default::default(),
+        secret_service: SecretServiceConfig {
+            collection: Some("mail".to_string()),
+            label: Some("mail-notifier: {account}".to_string()),
+        },
+    };
+
+    assert_eq!(config.keyring, expected);
+}
+
+#[test]
+fn test_tls_trust_config_parsing() {
+    let yaml = include_str!("fixtures/tls_trust.yml");
+    let config = must_parse(yaml);
+
+    let expected = TlsConfig {
+        ca_files: vec!["/etc/mail-notifier/internal-ca.pem".into()],
+        native_roots: Some(false),
+        pins: vec![
+            CertificatePin::SpkiSha256("35EV9PYAFAd6S3ZPLTXXgBJrAfIEaUCCowFDwIDyw/Q=".to_string()),
+            CertificatePin::CertificateSha256(
+                "9B:5D:76:F7:13:E7:68:CD:DF:72:6F:17:C7:8B:5C:6A:2C:86:A8:E6:3F:AB:18:AA:09:67:66:F1:B6:4A:13:30"
+                    .to_string(),
+            ),
+        ],
+        ..base_server().tls
+    };
+
+    assert_eq!(config.servers[0].tls, expected);
+}
+
+#[test]
+fn test_tls_policy_config_parsing() {
+    let yaml = include_str!("fixtures/tls_policy.yml");
+    let config = must_parse(yaml);
+
+    let expected = TlsConfig {
+        server_name: Some("mail.internal".to_string()),
+        verify_name: Some("imap.example.com".to_string()),
+        min_version: Some(TlsVersion::V1_3),
+        alpn: Some("imap".to_string()),
+        ..base_server().tls
+    };
+
+    assert_eq!(config.servers[0].tls, expected);
+}
+
+#[test]
+fn test_id_config_parsing() {
+    let yaml = include_str!("fixtures/id.yml");
+    let config = must_parse(yaml);
+
+    let expected = IdConfig {
+        enabled: None,
+        name: Some("Thunderbird".to_string()),
+        version: Some("128.0".to_string()),
+    };
+    assert_eq!(config.servers[0].id, expected);
+
+    let expected = IdConfig {
+        enabled: Some(false),
+        ..Default::default()
+    };
+    assert_eq!(config.servers[1].id, expected);
+}
+
+#[test]
+fn test_compress_config_parsing() {
+    let yaml = include_str!("fixtures/compress.yml");
+    let config = must_parse(yaml);
+
+    assert!(config.servers[0].compress);
+}
+
+#[test]
+fn test_trace_config_parsing() {
+    let yaml = include_str!("fixtures/trace.yml");
+    let config = must_parse(yaml);
+
+    let expected = TraceConfig {
+        enabled: false,
+        file: Some("/tmp/imap-trace.log".into()),
+    };
+    assert_eq!(config.servers[0].trace, expected);
+
+    let expected = TraceConfig {
+        enabled: true,
+        file: None,
+    };
+    assert_eq!(config.servers[1].trace, expected);
+}
+
+#[test]
+fn test_proxy_config_parsing() {
+    let yaml = include_str!("fixtures/proxy.yml");
+    let config = must_parse(yaml);
+
+    let expected = Some(ProxyConfig::Socks5(Socks5ProxyConfig {
+        host: "127.0.0.1".to_string(),
+        port: 9050,
+        remote_dns: None,
+        login: None,
+    }));
+    assert_eq!(config.proxy, expected);
+
+    let expected = Some(ProxyConfig::Http(HttpProxyConfig {
+        host: "proxy.corp.example.com".to_string(),
+        port: 3128,
+        login: Some(ProxyLogin {
+            username: "jdoe".to_string(),
+            password: PasswordSource::Keyring {
+                keyring: KeyringRef {
+                    service: Some("corp-proxy".to_string()),
+                    account: None,
+                },
+            },
+        }),
+    }));
+    assert_eq!(config.servers[0].proxy, expected);
+
+    assert_eq!(config.servers[1].proxy, Some(ProxyConfig::Direct));
+}
+
+#[test]
+fn test_tunnel_config_parsing() {
+    let yaml = include_str!("fixtures/tunnel.yml");
+    let config = must_parse(yaml);
+
+    assert_eq!(
+        config.servers[0].tunnel.as_deref(),
+        Some("ssh -q mailhost /usr/lib/dovecot/imap")
+    );
+}
+
+#[test]
+fn test_socket_config_parsing() {
+    let yaml = include_str!("fixtures/socket.yml");
+    let config = must_parse(yaml);
+
+    assert_eq!(
+        config.servers[0].socket.as_deref(),
+        Some(std::path::Path::new("/run/dovecot/imap"))
+    );
+}
+
+#[test]
+fn test_timeouts_config_parsing() {
+    let yaml = include_str!("fixtures/timeouts.yml");
+    let config = must_parse(yaml);
+
+    let expected = TimeoutsConfig {
+        connect_secs: Some(10),
+        tls_handshake_secs: None,
+        greeting_secs: Some(15),
+        command_secs: Some(120),
+    };
+    assert_eq!(config.servers[0].timeouts, expected);
+}
+
+#[test]
+fn test_ip_family_config_parsing() {
+    let yaml = include_str!("fixtures/ip_family.yml");
+    let config = must_parse(yaml);
+
+    assert_eq!(config.servers[0].ip_family, Some(IpFamily::V4));
+    assert_eq!(config.servers[1].ip_family, Some(IpFamily::V6));
+}
+
+#[test]
+fn test_heartbeat_config_parsing() {
+    let yaml = include_str!("fixtures/heartbeat.yml");
+    let config = must_parse(yaml);
+
+    let server = &config.servers[0];
+    assert_eq!(server.heartbeat_secs, Some(60));
+    assert_eq!(server.mailboxes[0].heartbeat_secs, None);
+    assert_eq!(server.mailboxes[1].heartbeat_secs, Some(30));
+}
+
+#[test]
+fn test_watch_config_parsing() {
+    let yaml = include_str!("fixtures/watch.yml");
+    let config = must_parse(yaml);
+
+    let server = &config.servers[0];
+    assert_eq!(server.watch, None);
+    assert_eq!(server.poll_interval_secs, Some(90));
+    assert_eq!(server.mailboxes[0].watch, None);
+    assert_eq!(server.mailboxes[1].watch, Some(WatchMode::Poll));
+    assert_eq!(server.mailboxes[1].poll_interval_secs, Some(600));
+
+    assert_eq!(config.servers[1].watch, Some(WatchMode::Idle));
+}
@@ -0,0 +1,538 @@
+//! Encrypted, atomically replaced and locked files.
+//!
+//! The contents are sealed with AES-256-GCM. The key is derived from a
+//! passphrase (PBKDF2-HMAC-SHA256) or from the contents of a key file
+//! (HKDF-SHA256), using a random salt kept in the file header.
+//!
+//! Writes replace the file atomically, and all operations take an advisory
+//! lock on a sidecar `.lock` file, so concurrent processes never observe
+//! or produce a partially written file.
+
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The file format magic and version.
+const MAGIC: &[u8; 8] = b"MNTOKEN1";
+
+/// Length of the key derivation salt.
+const SALT_LEN: usize = 16;
+
+/// Length of the file header: magic and salt.
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN;
+
+/// Length of a generated key file.
+const GENERATED_KEY_LEN: usize = 32;
+
+/// PBKDF2 iterations for passphrase-derived keys.
+const PBKDF2_ITERATIONS: std::num::NonZeroU32 = std::num::NonZeroU32::new(600_000).unwrap();
+
+/// Where the encryption key comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    /// Derive the key from a passphrase.
+    Passphrase(String),
+
+    /// Derive the key from the contents of a file.
+    KeyFile(PathBuf),
+}
+
+/// Errors from encrypted file operations.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// File I/O failed.
+    #[error("{}: {source}", path.display())]
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// Underlying I/O error.
+        source: std::io::Error,
+    },
+
+    /// Reading the key file failed.
+    #[error("key file {}: {source}", path.display())]
+    KeyFile {
+        /// The path of the key file.
+        path: PathBuf,
+
+        /// Underlying I/O error.
+        source: std::io::Error,
+    },
+
+    /// The key file is too short to hold a generated key.
+    #[error("key file {} is {len} bytes, at least {GENERATED_KEY_LEN} are needed", path.display())]
+    KeyFileTooShort {
+        /// The path of the key file.
+        path: PathBuf,
+
+        /// The length of the key file.
+        len: usize,
+    },
+
+    /// The file is not in the expected format.
+    #[error("file is not in the expected format")]
+    Format,
+
+    /// The file could not be decrypted.
+    #[error("unable to decrypt the file, the key is wrong or the file is corrupted")]
+    Decrypt,
+
+    /// Encryption or random number generation failed.
+    #[error("cryptographic operation failed")]
+    Crypto,
+}
+
+/// An encrypted file.
+#[derive(Debug)]
+pub struct EncryptedFile {
+    /// The path of the file.
+    path: PathBuf,
+
+    /// The encryption key source.
+    key_source: KeySource,
+
+    /// The last derived key, to avoid re-running the key derivation.
+    derived_key: Mutex<Option<DerivedKey>>,
+}
+
+/// A key derived for a particular salt.
+struct DerivedKey {
+    /// The salt the key was derived with.
+    salt: [u8; SALT_LEN],
+
+    /// The key bytes.
+    key: [u8; 32],
+}
+
+impl std::fmt::Debug for DerivedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DerivedKey").finish_non_exhaustive()
+    }
+}
+
+/// The advisory lock mode.
+#[derive(Debug, Clone, Copy)]
+enum LockMode {
+    /// Allow other readers.
+    Shared,
+
+    /// Exclude everyone else.
+    Exclusive,
+}
+
+/// Exclusive access to the file, held until dropped.
+#[derive(Debug)]
+pub struct WriteGuard<'a> {
+    /// The locked file.
+    file: &'a EncryptedFile,
+
+    /// The held lock.
+    _lock: std::fs::File,
+}
+
+/// Exclusive access to a shared file, held until dropped.
+///
+/// Unlike [`WriteGuard`], it can be moved to another thread, as for
+/// holding the lock across an asynchronous update.
+#[derive(Debug)]
+pub struct OwnedWriteGuard {
+    /// The locked file.
+    file: Arc<EncryptedFile>,
+
+    /// The held lock.
+    _lock: std::fs::File,
+}
+
+impl EncryptedFile {
+    /// Create a handle for the encrypted file at the given path.
+    ///
+    /// The file is not touched until the first operation.
+    pub fn new(path: impl Into<PathBuf>, key_source: KeySource) -> Self {
+        Self {
+            path: path.into(),
+            key_source,
+            derived_key: Mutex::new(None),
+        }
+    }
+
+    /// The path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read and decrypt the file, or return `None` if it does not exist.
+    pub fn read(&self) -> Result<Option<Vec<u8>>, Error> {
+        let sealed = {
+            let _lock = self.lock_file(LockMode::Shared)?;
+            self.read_sealed()?
+        };
+        sealed.map(|sealed| self.open(sealed)).transpose()
+    }
+
+    /// Take the exclusive lock, to read-modify-write the file.
+    pub fn lock(&self) -> Result<WriteGuard<'_>, Error> {
+        let lock = self.lock_file(LockMode::Exclusive)?;
+        Ok(WriteGuard {
+            file: self,
+            _lock: lock,
+        })
+    }
+
+    /// Take the exclusive lock of a shared file, to read-modify-write it.
+    pub fn lock_owned(self: Arc<Self>) -> Result<OwnedWriteGuard, Error> {
+        let lock = self.lock_file(LockMode::Exclusive)?;
+        Ok(OwnedWriteGuard {
+            file: self,
+            _lock: lock,
+        })
+    }
+
+    /// Encrypt and atomically write the file.
+    pub fn write(&self, plaintext: &[u8]) -> Result<(), Error> {
+        self.lock()?.write(plaintext)
+    }
+
+    /// Remove the file, if it exists.
+    pub fn remove(&self) -> Result<(), Error> {
+        self.lock()?.remove()
+    }
+
+    /// Read and decrypt the file, with the exclusive lock held.
+    fn read_locked(&self) -> Result<Option<Vec<u8>>, Error> {
+        self.read_sealed()?
+            .map(|sealed| self.open(sealed))
+            .transpose()
+    }
+
+    /// Encrypt and atomically write the file, with the exclusive lock held.
+    fn write_locked(&self, plaintext: &[u8]) -> Result<(), Error> {
+        let sealed = self.seal(plaintext)?;
+        self.write_atomically(&sealed)
+    }
+
+    /// Remove the file if it exists, with the exclusive lock held.
+    fn remove_locked(&self) -> Result<(), Error> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(self.io_error(error)),
+        }
+    }
+
+    /// Wrap an I/O error on the file.
+    fn io_error(&self, source: std::io::Error) -> Error {
+        Error::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    /// The path with the given suffix appended to the file name.
+    fn sidecar_path(&self, suffix: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    /// Take the advisory lock on the sidecar lock file.
+    ///
+    /// The lock is released when the returned file is dropped.
+    fn lock_file(&self, mode: LockMode) -> Result<std::fs::File, Error> {
+        let path = self.sidecar_path(".lock");
+        let file = private_file_options()
+            .read(true)
+            .write(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|source| Error::Io {
+                path: path.clone(),
+                source,
+            })?;
+        let result = match mode {
+            LockMode::Shared => file.lock_shared(),
+            LockMode::Exclusive => file.lock(),
+        };
+        result.map_err(|source| Error::Io { path, source })?;
+        Ok(file)
+    }
+
+    /// Read the sealed contents, or `None` if the file does not exist.
+    fn read_sealed(&self) -> Result<Option<Vec<u8>>, Error> {
+        match std::fs::read(&self.path) {
+            Ok(sealed) => Ok(Some(sealed)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(self.io_error(error)),
+        }
+    }
+
+    /// Write the contents to a temporary file and move it into place.
+    fn write_atomically(&self, contents: &[u8]) -> Result<(), Error> {
+        let tmp_path = self.sidecar_path(".tmp");
+        let tmp_error = |source| Error::Io {
+            path: tmp_path.clone(),
+            source,
+        };
+
+        let mut file = private_file_options()
+            .write(true)
+            .truncate(true)
+            .open(&tmp_path)
+            .map_err(tmp_error)?;
+        file.write_all(contents).map_err(tmp_error)?;
+        file.sync_all().map_err(tmp_error)?;
+        drop(file);
+
+        std::fs::rename(&tmp_path, &self.path).map_err(|source| self.io_error(source))?;
+
+        #[cfg(unix)]
+        if let Some(parent) = self.path.parent() {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            std::fs::File::open(parent)
+                .and_then(|dir| dir.sync_all())
+                .map_err(|source| self.io_error(source))?;
+        }
+
+        Ok(())
+    }
+
+    /// Obtain the key for the given salt, deriving it if needed.
+    fn key(&self, salt: &[u8; SALT_LEN]) -> Result<ring::aead::LessSafeKey, Error> {
+        let mut derived_key = self.derived_key.lock().unwrap();
+        let key = match derived_key.as_ref() {
+            Some(derived_key) if &derived_key.salt == salt => derived_key.key,
+            _ => {
+                let key = self.derive_key(salt)?;
+                *derived_key = Some(DerivedKey { salt: *salt, key });
+                key
+            }
+        };
+
+        let key = ring::aead::UnboundKey::new(&ring::aead::AES_256_GCM, &key)
+            .map_err(|_| Error::Crypto)?;
+        Ok(ring::aead::LessSafeKey::new(key))
+    }
+
+    /// Derive the key bytes for the given salt.
+    fn derive_key(&self, salt: &[u8; SALT_LEN]) -> Result<[u8; 32], Error> {
+        let mut key = [0u8; 32];
+        match &self.key_source {
+            KeySource::Passphrase(passphrase) => {
+                ring::pbkdf2::derive(
+                    ring::pbkdf2::PBKDF2_HMAC_SHA256,
+                    PBKDF2_ITERATIONS,
+                    salt,
+                    passphrase.as_bytes(),
+                    &mut key,
+                );
+            }
+            KeySource::KeyFile(path) => {
+                let secret = std::fs::read(path).map_err(|source| Error::KeyFile {
+                    path: path.clone(),
+                    source,
+                })?;
+                if secret.len() < GENERATED_KEY_LEN {
+                    return Err(Error::KeyFileTooShort {
+                        path: path.clone(),
+                        len: secret.len(),
+                    });
+                }
+                ring::hkdf::Salt::new(ring::hkdf::HKDF_SHA256, salt)
+                    .extract(&secret)
+                    .expand(&[MAGIC], ring::hkdf::HKDF_SHA256)
+                    .and_then(|okm| okm.fill(&mut key))
+                    .map_err(|_| Error::Crypto)?;
+            }
+        }
+        Ok(key)
+    }
+
+    /// Encrypt the plaintext into the file contents.
+    ///
+    /// Reuses the salt of the last derived key, so the key derivation only
+    /// runs once per process.
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        use ring::rand::SecureRandom as _;
+
+        let rng = ring::rand::SystemRandom::new();
+
+        let cached_salt = self
+            .derived_key
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|derived_key| derived_key.salt);
+        let salt = match cached_salt {
+            Some(salt) => salt,
+            None => {
+                let mut salt = [0u8; SALT_LEN];
+                rng.fill(&mut salt).map_err(|_| Error::Crypto)?;
+                salt
+            }
+        };
+        let key = self.key(&salt)?;
+
+        let mut nonce = [0u8; ring::aead::NONCE_LEN];
+        rng.fill(&mut nonce).map_err(|_| Error::Crypto)?;
+
+        let mut contents = Vec::with_capacity(
+            HEADER_LEN + nonce.len() + plaintext.len() + ring::aead::AES_256_GCM.tag_len(),
+        );
+        contents.extend_from_slice(MAGIC);
+        contents.extend_from_slice(&salt);
+        contents.extend_from_slice(&nonce);
+        contents.extend_from_slice(plaintext);
+
+        let (header, rest) = contents.split_at_mut(HEADER_LEN);
+        let tag = key
+            .seal_in_place_separate_tag(
+                ring::aead::Nonce::assume_unique_for_key(nonce),
+                ring::aead::Aad::from(&*header),
+                &mut rest[ring::aead::NONCE_LEN..],
+            )
+            .map_err(|_| Error::Crypto)?;
+        contents.extend_from_slice(tag.as_ref());
+
+        Ok(contents)
+    }
+
+    /// Decrypt the file contents into the plaintext.
+    fn open(&self, mut contents: Vec<u8>) -> Result<Vec<u8>, Error> {
+        if contents.len() < HEADER_LEN + ring::aead::NONCE_LEN || !contents.starts_with(MAGIC) {
+            return Err(Error::Format);
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&contents[MAGIC.len()..HEADER_LEN]);
+        let mut nonce = [0u8; ring::aead::NONCE_LEN];
+        nonce.copy_from_slice(&contents[HEADER_LEN..HEADER_LEN + ring::aead::NONCE_LEN]);
+
+        let key = self.key(&salt)?;
+
+        let (header, rest) = contents.split_at_mut(HEADER_LEN);
+        let ciphertext = &mut rest[ring::aead::NONCE_LEN..];
+        let plaintext = key
+            .open_in_place(
+                ring::aead::Nonce::assume_unique_for_key(nonce),
+                ring::aead::Aad::from(&*header),
+                ciphertext,
+            )
+            .map_err(|_| Error::Decrypt)?;
+
+        Ok(plaintext.to_vec())
+    }
+}
+
+impl WriteGuard<'_> {
+    /// Read and decrypt the file, or return `None` if it does not exist.
+    pub fn read(&self) -> Result<Option<Vec<u8>>, Error> {
+        self.file.read_locked()
+    }
+
+    /// Encrypt and atomically write the file.
+    pub fn write(&self, plaintext: &[u8]) -> Result<(), Error> {
+        self.file.write_locked(plaintext)
+    }
+
+    /// Remove the file, if it exists.
+    pub fn remove(&self) -> Result<(), Error> {
+        self.file.remove_locked()
+    }
+}
+
+impl OwnedWriteGuard {
+    /// Read and decrypt the file, or return `None` if it does not exist.
+    pub fn read(&self) -> Result<Option<Vec<u8>>, Error> {
+        self.file.read_locked()
+    }
+
+    /// Encrypt and atomically write the file.
+    pub fn write(&self, plaintext: &[u8]) -> Result<(), Error> {
+        self.file.write_locked(plaintext)
+    }
+
+    /// Remove the file, if it exists.
+    pub fn remove(&self) -> Result<(), Error> {
+        self.file.remove_locked()
+    }
+}
+
+/// Create a key file with random contents, unless it already exists.
+///
+/// The parent directory is created when missing. The key is written to a
+/// temporary file and linked into place, so the key file is never seen
+/// partially written, and a concurrent creation keeps the first key.
+pub fn ensure_key_file(path: &Path) -> Result<(), Error> {
+    use ring::rand::SecureRandom as _;
+
+    let io_error = |source| Error::Io {
+        path: path.to_owned(),
+        source,
+    };
+
+    if path.exists() {
+        return Ok(());
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(parent).map_err(io_error)?;
+
+    let rng = ring::rand::SystemRandom::new();
+    let mut key = [0u8; GENERATED_KEY_LEN];
+    rng.fill(&mut key).map_err(|_| Error::Crypto)?;
+    let mut suffix = [0u8; 8];
+    rng.fill(&mut suffix).map_err(|_| Error::Crypto)?;
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_owned();
+    tmp_name.push(format!(".{:016x}.tmp", u64::from_ne_bytes(suffix)));
+    let tmp_path = parent.join(tmp_name);
+    let tmp_error = |source| Error::Io {
+        path: tmp_path.clone(),
+        source,
+    };
+
+    let mut file = private_file_options()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .map_err(tmp_error)?;
+    let written = file
+        .write_all(&key)
+        .and_then(|()| file.sync_all())
+        .map_err(tmp_error);
+    drop(file);
+
+    let linked = written.and_then(|()| match std::fs::hard_link(&tmp_path, path) {
+        Err(error) if error.kind() != std::io::ErrorKind::AlreadyExists => Err(io_error(error)),
+        _ => Ok(()),
+    });
+    let removed = std::fs::remove_file(&tmp_path).map_err(tmp_error);
+    linked?;
+    removed?;
+
+    #[cfg(unix)]
+    std::fs::File::open(parent)
+        .and_then(|dir| dir.sync_all())
+        .map_err(io_error)?;
+
+    Ok(())
+}
+
+/// Options to create a file only readable by the current user.
+fn private_file_options() -> std::fs::OpenOptions {
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,147 @@
+use super::*;
+
+/// Create a key file with the given contents in the directory.
+fn key_file(dir: &tempfile::TempDir, contents: &[u8]) -> KeySource {
+    let path = dir.path().join("key");
+    std::fs::write(&path, contents).unwrap();
+    KeySource::KeyFile(path)
+}
+
+#[test]
+fn roundtrip_with_key_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = EncryptedFile::new(dir.path().join("file"), key_file(&dir, &[b's'; 32]));
+
+    file.write(b"plaintext").unwrap();
+
+    assert_eq!(file.read().unwrap().as_deref(), Some(&b"plaintext"[..]));
+    let contents = std::fs::read(file.path()).unwrap();
+    assert!(contents.starts_with(MAGIC));
+    assert!(!contents.windows(9).any(|window| window == b"plaintext"));
+}
+
+#[test]
+fn roundtrip_with_passphrase_across_instances() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let key_source = KeySource::Passphrase("correct horse".to_owned());
+
+    EncryptedFile::new(&path, key_source.clone())
+        .write(b"plaintext")
+        .unwrap();
+    let plaintext = EncryptedFile::new(&path, key_source).read().unwrap();
+
+    assert_eq!(plaintext.as_deref(), Some(&b"plaintext"[..]));
+}
+
+#[test]
+fn wrong_key_fails_to_decrypt() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    EncryptedFile::new(&path, key_file(&dir, &[b's'; 32]))
+        .write(b"plaintext")
+        .unwrap();
+    let result = EncryptedFile::new(&path, key_file(&dir, &[b'o'; 32])).read();
+
+    assert!(matches!(result, Err(Error::Decrypt)));
+}
+
+#[test]
+fn empty_key_file_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = EncryptedFile::new(dir.path().join("file"), key_file(&dir, b""));
+
+    assert!(matches!(
+        file.write(b"plaintext"),
+        Err(Error::KeyFileTooShort { len: 0, .. })
+    ));
+    assert!(!file.path().exists());
+}
+
+#[test]
+fn short_key_file_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+
+    EncryptedFile::new(&path, key_file(&dir, &[b's'; GENERATED_KEY_LEN]))
+        .write(b"plaintext")
+        .unwrap();
+    let result = EncryptedFile::new(&path, key_file(&dir, &[b's'; GENERATED_KEY_LEN - 1])).read();
+
+    assert!(matches!(
+        result,
+        Err(Error::KeyFileTooShort { len, .. }) if len == GENERATED_KEY_LEN - 1
+    ));
+}
+
+#[test]
+fn tampered_header_fails_to_decrypt() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = EncryptedFile::new(dir.path().join("file"), key_file(&dir, &[b's'; 32]));
+
+    file.write(b"plaintext").unwrap();
+    let mut contents = std::fs::read(file.path()).unwrap();
+    contents[MAGIC.len()] ^= 1;
+    std::fs::write(file.path(), contents).unwrap();
+
+    assert!(matches!(
+        EncryptedFile::new(file.path(), key_file(&dir, &[b's'; 32])).read(),
+        Err(Error::Decrypt)
+    ));
+}
+
+#[test]
+fn missing_file_reads_as_none() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = EncryptedFile::new(dir.path().join("file"), key_file(&dir, &[b's'; 32]));
+
+    assert!(file.read().unwrap().is_none());
+}
+
+#[test]
+fn remove_is_idempotent() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = EncryptedFile::new(dir.path().join("file"), key_file(&dir, &[b's'; 32]));
+
+    file.write(b"plaintext").unwrap();
+    file.remove().unwrap();
+    file.remove().unwrap();
+
+    assert!(!file.path().exists());
+}
+
+#[test]
+fn generated_key_file_is_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("keys/key");
+
+    ensure_key_file(&path).unwrap();
+    let key = std::fs::read(&path).unwrap();
+    ensure_key_file(&path).unwrap();
+
+    assert_eq!(key.len(), GENERATED_KEY_LEN);
+    assert_eq!(std::fs::read(&path).unwrap(), key);
+    assert_eq!(
+        std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+        1
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn files_are_private() {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = EncryptedFile::new(dir.path().join("file"), key_file(&dir, &[b's'; 32]));
+    let key_path = dir.path().join("generated");
+
+    file.write(b"plaintext").unwrap();
+    ensure_key_file(&key_path).unwrap();
+
+    for path in [file.path(), &key_path] {
+        let mode = std::fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
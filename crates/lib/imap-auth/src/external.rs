@@ -0,0 +1,18 @@
+//! The `EXTERNAL` SASL mechanism (RFC 4422, appendix A).
+
+/// The SASL mechanism name.
+pub(crate) const MECHANISM: &str = "EXTERNAL";
+
+/// An authenticator for the `EXTERNAL` mechanism.
+pub(crate) struct Authenticator<'a> {
+    /// Authorization identity, empty to let the server derive it.
+    pub authzid: Option<&'a str>,
+}
+
+impl async_imap::Authenticator for Authenticator<'_> {
+    type Response = String;
+
+    fn process(&mut self, _: &[u8]) -> Self::Response {
+        self.authzid.unwrap_or_default().to_owned()
+    }
+}
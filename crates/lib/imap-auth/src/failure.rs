@@ -0,0 +1,152 @@
+//! Authentication failure classification.
+//!
+//! See [RFC 5530](https://www.rfc-editor.org/rfc/rfc5530) for the response
+//! codes.
+
+/// Authentication related RFC 5530 response codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseCode {
+    /// `AUTHENTICATIONFAILED`: the credentials were rejected.
+    AuthenticationFailed,
+
+    /// `AUTHORIZATIONFAILED`: the credentials are valid, but do not allow
+    /// access as the requested identity.
+    AuthorizationFailed,
+
+    /// `EXPIRED`: the credentials or the account expired.
+    Expired,
+
+    /// `UNAVAILABLE`: the server is temporarily unable to authenticate.
+    Unavailable,
+
+    /// `LIMIT`: a server limit was reached, e.g. too many connections.
+    Limit,
+}
+
+impl ResponseCode {
+    /// Find the response code in the text of a status response.
+    pub fn parse(text: &str) -> Option<Self> {
+        text.split('[').skip(1).find_map(|rest| {
+            let end = rest.find([']', ' ']).unwrap_or(rest.len());
+            Self::from_atom(&rest[..end])
+        })
+    }
+
+    /// Find the response code in a `NO` or `BAD` response error.
+    pub fn from_error(error: &async_imap::error::Error) -> Option<Self> {
+        match error {
+            async_imap::error::Error::No(text) | async_imap::error::Error::Bad(text) => {
+                Self::parse(text)
+            }
+            _ => None,
+        }
+    }
+
+    /// Match the response code atom.
+    fn from_atom(atom: &str) -> Option<Self> {
+        Some(match atom.to_ascii_uppercase().as_str() {
+            "AUTHENTICATIONFAILED" => Self::AuthenticationFailed,
+            "AUTHORIZATIONFAILED" => Self::AuthorizationFailed,
+            "EXPIRED" => Self::Expired,
+            "UNAVAILABLE" => Self::Unavailable,
+            "LIMIT" => Self::Limit,
+            _ => return None,
+        })
+    }
+}
+
+/// The kind of an authentication failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The credentials were rejected.
+    AuthenticationFailed,
+
+    /// The credentials are valid, but do not allow access.
+    AuthorizationFailed,
+
+    /// The credentials or the account expired.
+    Expired,
+
+    /// The server is temporarily unable to authenticate.
+    Unavailable,
+
+    /// A server limit was reached.
+    Limit,
+
+    /// The configured authentication is not supported by the server.
+    Unsupported,
+
+    /// The failure could not be classified.
+    Other,
+}
+
+impl FailureKind {
+    /// Whether retrying with the same credentials and settings is pointless,
+    /// and risks getting the account locked.
+    pub const fn is_permanent(self) -> bool {
+        matches!(
+            self,
+            Self::AuthenticationFailed
+                | Self::AuthorizationFailed
+                | Self::Expired
+                | Self::Unsupported
+        )
+    }
+}
+
+impl From<ResponseCode> for FailureKind {
+    fn from(code: ResponseCode) -> Self {
+        match code {
+            ResponseCode::AuthenticationFailed => Self::AuthenticationFailed,
+            ResponseCode::AuthorizationFailed => Self::AuthorizationFailed,
+            ResponseCode::Expired => Self::Expired,
+            ResponseCode::Unavailable => Self::Unavailable,
+            ResponseCode::Limit => Self::Limit,
+        }
+    }
+}
+
+/// Classify a failed IMAP command by its response code.
+pub(crate) fn classify(error: &async_imap::error::Error) -> FailureKind {
+    ResponseCode::from_error(error).map_or(FailureKind::Other, FailureKind::from)
+}
+
+/// Classify a failed OAuth 2 authentication.
+///
+/// The response code takes precedence, the error challenge status is used
+/// when there is none: an HTTP status for XOAUTH2, or an
+/// [RFC 7628](https://www.rfc-editor.org/rfc/rfc7628#section-3.2.2) error
+/// for OAUTHBEARER.
+pub(crate) fn classify_oauth2(
+    error: &async_imap::error::Error,
+    error_challenge: Option<&crate::OAuth2ErrorChallenge>,
+) -> FailureKind {
+    if let Some(code) = ResponseCode::from_error(error) {
+        return code.into();
+    }
+    match error_challenge.and_then(|challenge| challenge.status.as_deref()) {
+        Some("401" | "invalid_token") => FailureKind::AuthenticationFailed,
+        Some("403" | "insufficient_scope") => FailureKind::AuthorizationFailed,
+        _ => FailureKind::Other,
+    }
+}
+
+/// Classify a SCRAM error reported by the server.
+///
+/// See [RFC 5802 section 7](https://www.rfc-editor.org/rfc/rfc5802#section-7)
+/// for the values.
+pub(crate) fn classify_scram(error: &crate::ScramError) -> FailureKind {
+    match error {
+        crate::ScramError::Server(value) => match value.as_str() {
+            "invalid-proof" | "unknown-user" | "invalid-username-encoding" => {
+                FailureKind::AuthenticationFailed
+            }
+            "channel-bindings-dont-match"
+            | "server-does-support-channel-binding"
+            | "channel-binding-not-supported"
+            | "unsupported-channel-binding-type" => FailureKind::Unsupported,
+            _ => FailureKind::Other,
+        },
+        _ => FailureKind::Other,
+    }
+}
@@ -1,5 +1,16 @@
 //! High-level IMAP authentication utilities.
 
+mod external;
+mod failure;
+mod oauth2;
+mod password;
+mod scram;
+
+pub use failure::{FailureKind, ResponseCode};
+pub use oauth2::{ErrorChallenge as OAuth2ErrorChallenge, OAuth2Mechanism};
+pub use password::PasswordMechanism;
+pub use scram::ScramError;
+
 /// The effective session type we use.
 pub type Session = async_imap::Session<imap_connect::Stream>;
 
@@ -15,6 +26,11 @@ pub enum Params<'a> {
 
         /// Password for IMAP authentication.
         password: &'a str,
+
+        /// The mechanism to use.
+        ///
+        /// Negotiated from the server capabilities when unset.
+        mechanism: Option<PasswordMechanism>,
     },
 
     /// OAuth 2 authnetication.
@@ -26,6 +42,26 @@ pub enum Params<'a> {
 
         /// The access token for IMAP authentication.
         access_token: &'a str,
+
+        /// The SASL mechanism to use.
+        ///
+        /// Negotiated from the server capabilities when unset.
+        mechanism: Option<OAuth2Mechanism>,
+
+        /// The server host, sent with `OAUTHBEARER`.
+        host: &'a str,
+
+        /// The server port, sent with `OAUTHBEARER`.
+        port: u16,
+    },
+
+    /// SASL `EXTERNAL` authentication, typically with a TLS client
+    /// certificate.
+    External {
+        /// Authorization identity to act as.
+        ///
+        /// Derived by the server from the external credentials when unset.
+        authzid: Option<&'a str>,
     },
 }
 
@@ -36,43 +72,250 @@ pub enum Error {
     #[error("login: {0}")]
     Login(async_imap::error::Error),
 
+    /// Password authentication via SASL failed.
+    #[error("{mechanism}: {source}")]
+    Sasl {
+        /// The mechanism used.
+        mechanism: PasswordMechanism,
+
+        /// Underlying IMAP error.
+        source: async_imap::error::Error,
+    },
+
+    /// The SCRAM exchange failed.
+    #[error("{mechanism}: {source}")]
+    Scram {
+        /// The mechanism used.
+        mechanism: PasswordMechanism,
+
+        /// Underlying SCRAM error.
+        source: ScramError,
+    },
+
+    /// The server offers no password mechanism we can use.
+    #[error("server offers no usable password authentication mechanism")]
+    NoPasswordMechanism,
+
+    /// The mechanism requires channel binding, but the connection does not provide it.
+    #[error("{0} requires TLS 1.3 channel binding, which is unavailable")]
+    ChannelBindingUnavailable(PasswordMechanism),
+
+    /// Generating the client nonce failed.
+    #[error("unable to generate a nonce")]
+    Nonce,
+
     /// OAuth 2 failed.
-    #[error("oauth2: {0}")]
-    OAuth2(async_imap::error::Error),
+    #[error("oauth2 {mechanism}: {source}{}", fmt_error_challenge(.error_challenge))]
+    OAuth2 {
+        /// The mechanism used.
+        mechanism: OAuth2Mechanism,
+
+        /// Underlying IMAP error.
+        source: async_imap::error::Error,
+
+        /// The error challenge reported by the server, if any.
+        error_challenge: Option<OAuth2ErrorChallenge>,
+    },
+
+    /// External authentication failed.
+    #[error("external: {0}")]
+    External(async_imap::error::Error),
+
+    /// Querying the server capabilities failed.
+    #[error("capabilities: {0}")]
+    Capabilities(async_imap::error::Error),
+
+    /// Taking over the preauthenticated connection failed.
+    #[error("preauthenticated: {0}")]
+    PreAuth(async_imap::error::Error),
+}
+
+impl Error {
+    /// Classify the failure, to decide whether retrying can help.
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            Self::Login(source) | Self::Sasl { source, .. } | Self::External(source) => {
+                failure::classify(source)
+            }
+            Self::Scram { source, .. } => failure::classify_scram(source),
+            Self::OAuth2 {
+                source,
+                error_challenge,
+                ..
+            } => failure::classify_oauth2(source, error_challenge.as_ref()),
+            Self::NoPasswordMechanism | Self::ChannelBindingUnavailable(_) => {
+                FailureKind::Unsupported
+            }
+            Self::Nonce | Self::Capabilities(_) | Self::PreAuth(_) => FailureKind::Other,
+        }
+    }
+}
+
+/// Format the optional error challenge for the error message.
+fn fmt_error_challenge(error_challenge: &Option<OAuth2ErrorChallenge>) -> String {
+    match error_challenge {
+        Some(error_challenge) => format!(" ({error_challenge})"),
+        None => String::new(),
+    }
 }
 
 /// Authenticate to the client to obtain a session.
-pub async fn auth(client: imap_connect::Client, auth: Params<'_>) -> Result<Session, Error> {
+///
+/// A connection the server greeted with `PREAUTH` is already authenticated,
+/// so it is used as is.
+pub async fn auth(
+    mut client: imap_connect::Client,
+    greeting: imap_tls::Greeting,
+    auth: Params<'_>,
+) -> Result<Session, Error> {
+    if greeting.preauth {
+        return imap_tls::preauthenticated(client)
+            .await
+            .map_err(Error::PreAuth);
+    }
+
     match auth {
-        Params::Login { username, password } => client
-            .login(username, password)
+        Params::Login {
+            username,
+            password,
+            mechanism,
+        } => auth_password(client, greeting.capabilities, username, password, mechanism).await,
+        Params::OAuth2 {
+            user,
+            access_token,
+            mechanism,
+            host,
+            port,
+        } => {
+            let mechanism = match mechanism {
+                Some(mechanism) => mechanism,
+                None => {
+                    let capabilities = capabilities(&mut client, greeting.capabilities).await?;
+                    OAuth2Mechanism::negotiate(&capabilities)
+                }
+            };
+
+            let mut error_challenge = None;
+            let authenticator = oauth2::Authenticator {
+                mechanism,
+                user,
+                access_token,
+                host,
+                port,
+                sent_initial_response: false,
+                error_challenge: &mut error_challenge,
+            };
+
+            let result = client.authenticate(mechanism.name(), authenticator).await;
+            result.map_err(|(source, _client)| Error::OAuth2 {
+                mechanism,
+                source,
+                error_challenge,
+            })
+        }
+        Params::External { authzid } => client
+            .authenticate(external::MECHANISM, external::Authenticator { authzid })
             .await
-            .map_err(|(err, _client)| err)
-            .map_err(Error::Login),
-        Params::OAuth2 { user, access_token } => client
-            .authenticate("XOAUTH2", OAuth2Authenticator { user, access_token })
+            .map_err(|(err, _client)| Error::External(err)),
+    }
+}
+
+/// The capabilities from the greeting, or queried when it lists none.
+async fn capabilities(
+    client: &mut imap_connect::Client,
+    greeting_capabilities: Option<imap_capabilities::Capabilities>,
+) -> Result<imap_capabilities::Capabilities, Error> {
+    match greeting_capabilities {
+        Some(capabilities) => Ok(capabilities),
+        None => imap_capabilities::fetch(client)
             .await
-            .map_err(|(err, _client)| err)
-            .map_err(Error::OAuth2),
+            .map_err(Error::Capabilities),
     }
 }
 
-/// An internal OAuth 2 authenticator for provided credentials.
-struct OAuth2Authenticator<'a> {
-    /// User.
-    user: &'a str,
+/// Authenticate with a username and password.
+async fn auth_password(
+    mut client: imap_connect::Client,
+    greeting_capabilities: Option<imap_capabilities::Capabilities>,
+    username: &str,
+    password: &str,
+    mechanism: Option<PasswordMechanism>,
+) -> Result<Session, Error> {
+    let channel_binding = imap_connect::channel_binding(client.get_ref());
 
-    /// Access token.
-    access_token: &'a str,
-}
+    let (mechanism, capabilities) = match mechanism {
+        Some(mechanism) => (mechanism, None),
+        None => {
+            let capabilities = capabilities(&mut client, greeting_capabilities).await?;
+            let mechanism = PasswordMechanism::negotiate(&capabilities, channel_binding.is_some())
+                .ok_or(Error::NoPasswordMechanism)?;
+            (mechanism, Some(capabilities))
+        }
+    };
 
-impl<'a> async_imap::Authenticator for OAuth2Authenticator<'a> {
-    type Response = String;
+    let Some(hash) = mechanism.scram_hash() else {
+        return match mechanism {
+            PasswordMechanism::Login => client
+                .login(username, password)
+                .await
+                .map_err(|(err, _client)| err)
+                .map_err(Error::Login),
+            _ => client
+                .authenticate(
+                    mechanism.name(),
+                    password::PlainAuthenticator { username, password },
+                )
+                .await
+                .map_err(|(source, _client)| Error::Sasl { mechanism, source }),
+        };
+    };
 
-    fn process(&mut self, _: &[u8]) -> Self::Response {
-        format!(
-            "user={}\x01auth=Bearer {}\x01\x01",
-            self.user, self.access_token
+    let channel_binding = scram_channel_binding(mechanism, channel_binding, capabilities.as_ref())
+        .ok_or(Error::ChannelBindingUnavailable(mechanism))?;
+
+    let nonce = scram::Client::random_nonce().map_err(|_| Error::Nonce)?;
+    let mut scram = scram::Client::new(hash, username, password, nonce, channel_binding);
+    let result = client
+        .authenticate(
+            mechanism.name(),
+            scram::Authenticator { client: &mut scram },
         )
+        .await;
+
+    match result {
+        Ok(session) => {
+            scram
+                .finish()
+                .map_err(|source| Error::Scram { mechanism, source })?;
+            Ok(session)
+        }
+        Err((source, _client)) => Err(match scram.error() {
+            Some(source) => Error::Scram { mechanism, source },
+            None => Error::Sasl { mechanism, source },
+        }),
+    }
+}
+
+/// Choose the SCRAM channel binding, none when the mechanism needs channel
+/// binding data that the connection does not offer.
+///
+/// Without the -PLUS mechanism, a client able to bind tells the server so
+/// unless the server is known to offer -PLUS, letting the server detect a
+/// downgrade that stripped -PLUS from its capabilities.
+fn scram_channel_binding(
+    mechanism: PasswordMechanism,
+    channel_binding: Option<Vec<u8>>,
+    capabilities: Option<&imap_capabilities::Capabilities>,
+) -> Option<scram::ChannelBinding> {
+    let server_offers_plus =
+        capabilities.is_some_and(|capabilities| mechanism.server_offers_plus(capabilities));
+    match (mechanism.requires_channel_binding(), channel_binding) {
+        (true, Some(data)) => Some(scram::ChannelBinding::TlsExporter(data)),
+        (true, None) => None,
+        (false, Some(_)) if !server_offers_plus => Some(scram::ChannelBinding::NotAdvertised),
+        (false, _) => Some(scram::ChannelBinding::Unsupported),
     }
 }
+
+#[cfg(test)]
+mod tests;
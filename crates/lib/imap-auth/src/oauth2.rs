@@ -0,0 +1,151 @@
+//! OAuth 2 SASL mechanisms.
+
+/// The OAuth 2 SASL mechanism to authenticate with.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OAuth2Mechanism {
+    /// The non-standard `XOAUTH2` mechanism.
+    XOAuth2,
+
+    /// The `OAUTHBEARER` mechanism (RFC 7628).
+    OAuthBearer,
+}
+
+impl OAuth2Mechanism {
+    /// The SASL mechanism name.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::XOAuth2 => "XOAUTH2",
+            Self::OAuthBearer => "OAUTHBEARER",
+        }
+    }
+
+    /// Pick the mechanism based on the server capabilities.
+    ///
+    /// Prefers the standard `OAUTHBEARER` when advertised, and falls back to
+    /// `XOAUTH2` otherwise.
+    pub fn negotiate(capabilities: &imap_capabilities::Capabilities) -> Self {
+        if capabilities.has_auth(Self::OAuthBearer.name()) {
+            Self::OAuthBearer
+        } else {
+            Self::XOAuth2
+        }
+    }
+}
+
+impl std::fmt::Display for OAuth2Mechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The JSON error challenge the server sends when OAuth 2 authentication fails.
+///
+/// Both `XOAUTH2` and `OAUTHBEARER` (RFC 7628, section 3.2.2) use this format.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+pub struct ErrorChallenge {
+    /// The HTTP-like status (e.g. `401`, `400`).
+    pub status: Option<String>,
+
+    /// The token schemes the server accepts.
+    pub schemes: Option<String>,
+
+    /// The scope required to access the resource.
+    pub scope: Option<String>,
+
+    /// The OpenID discovery URL.
+    #[serde(rename = "openid-configuration")]
+    pub openid_configuration: Option<String>,
+}
+
+impl ErrorChallenge {
+    /// Parse the error challenge from the decoded server challenge.
+    pub fn parse(challenge: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(challenge)
+    }
+}
+
+impl std::fmt::Display for ErrorChallenge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            status,
+            schemes,
+            scope,
+            openid_configuration: _,
+        } = self;
+        write!(f, "status {}", status.as_deref().unwrap_or("unknown"))?;
+        if let Some(schemes) = schemes {
+            write!(f, ", schemes {schemes}")?;
+        }
+        if let Some(scope) = scope {
+            write!(f, ", scope {scope}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An OAuth 2 authenticator for the provided credentials.
+pub(crate) struct Authenticator<'a, 'b> {
+    /// The mechanism to produce the initial response for.
+    pub mechanism: OAuth2Mechanism,
+
+    /// User.
+    pub user: &'a str,
+
+    /// Access token.
+    pub access_token: &'a str,
+
+    /// Server host, for `OAUTHBEARER`.
+    pub host: &'a str,
+
+    /// Server port, for `OAUTHBEARER`.
+    pub port: u16,
+
+    /// Whether the initial response was sent.
+    pub sent_initial_response: bool,
+
+    /// The error challenge captured from the server.
+    pub error_challenge: &'b mut Option<ErrorChallenge>,
+}
+
+impl<'a, 'b> async_imap::Authenticator for Authenticator<'a, 'b> {
+    type Response = Vec<u8>;
+
+    fn process(&mut self, challenge: &[u8]) -> Self::Response {
+        if !self.sent_initial_response {
+            self.sent_initial_response = true;
+            return self.initial_response().into_bytes();
+        }
+
+        // Any challenge after the initial response is an error report;
+        // acknowledge it so the server completes the command with a `NO`.
+        *self.error_challenge = ErrorChallenge::parse(challenge).ok();
+        match self.mechanism {
+            OAuth2Mechanism::XOAuth2 => Vec::new(),
+            OAuth2Mechanism::OAuthBearer => b"\x01".to_vec(),
+        }
+    }
+}
+
+impl Authenticator<'_, '_> {
+    /// Build the initial client response.
+    fn initial_response(&self) -> String {
+        match self.mechanism {
+            OAuth2Mechanism::XOAuth2 => format!(
+                "user={}\x01auth=Bearer {}\x01\x01",
+                self.user, self.access_token
+            ),
+            OAuth2Mechanism::OAuthBearer => format!(
+                "n,a={},\x01host={}\x01port={}\x01auth=Bearer {}\x01\x01",
+                gs2_escape(self.user),
+                self.host,
+                self.port,
+                self.access_token
+            ),
+        }
+    }
+}
+
+/// Escape the authorization identity for the GS2 header (RFC 5801).
+pub(crate) fn gs2_escape(value: &str) -> String {
+    value.replace('=', "=3D").replace(',', "=2C")
+}
@@ -0,0 +1,119 @@
+//! Password-based authentication mechanisms.
+
+/// The mechanism to authenticate with a username and password.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PasswordMechanism {
+    /// The IMAP `LOGIN` command.
+    Login,
+
+    /// The `PLAIN` SASL mechanism (RFC 4616).
+    Plain,
+
+    /// The `SCRAM-SHA-1` SASL mechanism (RFC 5802).
+    ScramSha1,
+
+    /// The `SCRAM-SHA-1-PLUS` SASL mechanism, with channel binding.
+    ScramSha1Plus,
+
+    /// The `SCRAM-SHA-256` SASL mechanism (RFC 7677).
+    ScramSha256,
+
+    /// The `SCRAM-SHA-256-PLUS` SASL mechanism, with channel binding.
+    ScramSha256Plus,
+}
+
+impl PasswordMechanism {
+    /// The mechanisms in the order of preference.
+    const PREFERENCE: [Self; 6] = [
+        Self::ScramSha256Plus,
+        Self::ScramSha256,
+        Self::ScramSha1Plus,
+        Self::ScramSha1,
+        Self::Plain,
+        Self::Login,
+    ];
+
+    /// The SASL mechanism name, or the command name for `LOGIN`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Login => "LOGIN",
+            Self::Plain => "PLAIN",
+            Self::ScramSha1 => "SCRAM-SHA-1",
+            Self::ScramSha1Plus => "SCRAM-SHA-1-PLUS",
+            Self::ScramSha256 => "SCRAM-SHA-256",
+            Self::ScramSha256Plus => "SCRAM-SHA-256-PLUS",
+        }
+    }
+
+    /// Whether the mechanism requires channel binding.
+    pub const fn requires_channel_binding(self) -> bool {
+        matches!(self, Self::ScramSha1Plus | Self::ScramSha256Plus)
+    }
+
+    /// The SCRAM hash function, if this is a SCRAM mechanism.
+    pub(crate) const fn scram_hash(self) -> Option<crate::scram::Hash> {
+        match self {
+            Self::ScramSha1 | Self::ScramSha1Plus => Some(crate::scram::Hash::Sha1),
+            Self::ScramSha256 | Self::ScramSha256Plus => Some(crate::scram::Hash::Sha256),
+            Self::Login | Self::Plain => None,
+        }
+    }
+
+    /// The channel binding counterpart of a SCRAM mechanism.
+    const fn plus(self) -> Option<Self> {
+        match self {
+            Self::ScramSha1 => Some(Self::ScramSha1Plus),
+            Self::ScramSha256 => Some(Self::ScramSha256Plus),
+            _ => None,
+        }
+    }
+
+    /// Pick the most secure mechanism the server supports.
+    ///
+    /// `LOGIN` is only picked when the server does not advertise
+    /// `LOGINDISABLED`.
+    pub fn negotiate(
+        capabilities: &imap_capabilities::Capabilities,
+        channel_binding_available: bool,
+    ) -> Option<Self> {
+        Self::PREFERENCE
+            .into_iter()
+            .find(|mechanism| match mechanism {
+                Self::Login => !capabilities.has("LOGINDISABLED"),
+                mechanism if mechanism.requires_channel_binding() => {
+                    channel_binding_available && capabilities.has_auth(mechanism.name())
+                }
+                mechanism => capabilities.has_auth(mechanism.name()),
+            })
+    }
+
+    /// Whether the server advertises the channel binding counterpart of this
+    /// mechanism.
+    pub(crate) fn server_offers_plus(self, capabilities: &imap_capabilities::Capabilities) -> bool {
+        self.plus()
+            .is_some_and(|plus| capabilities.has_auth(plus.name()))
+    }
+}
+
+impl std::fmt::Display for PasswordMechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An authenticator for the `PLAIN` mechanism.
+pub(crate) struct PlainAuthenticator<'a> {
+    /// Username.
+    pub username: &'a str,
+
+    /// Password.
+    pub password: &'a str,
+}
+
+impl async_imap::Authenticator for PlainAuthenticator<'_> {
+    type Response = String;
+
+    fn process(&mut self, _: &[u8]) -> Self::Response {
+        format!("\x00{}\x00{}", self.username, self.password)
+    }
+}
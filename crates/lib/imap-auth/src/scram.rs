@@ -0,0 +1,335 @@
+//! SCRAM SASL mechanisms (RFC 5802, RFC 7677).
+
+use base64::Engine as _;
+
+/// The hash function a SCRAM mechanism is built on.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum Hash {
+    /// SHA-1.
+    Sha1,
+
+    /// SHA-256.
+    Sha256,
+}
+
+impl Hash {
+    /// The HMAC algorithm.
+    fn hmac(self) -> ring::hmac::Algorithm {
+        match self {
+            Self::Sha1 => ring::hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+            Self::Sha256 => ring::hmac::HMAC_SHA256,
+        }
+    }
+
+    /// The PBKDF2 algorithm.
+    fn pbkdf2(self) -> ring::pbkdf2::Algorithm {
+        match self {
+            Self::Sha1 => ring::pbkdf2::PBKDF2_HMAC_SHA1,
+            Self::Sha256 => ring::pbkdf2::PBKDF2_HMAC_SHA256,
+        }
+    }
+
+    /// The digest algorithm.
+    fn digest(self) -> &'static ring::digest::Algorithm {
+        match self {
+            Self::Sha1 => &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+            Self::Sha256 => &ring::digest::SHA256,
+        }
+    }
+}
+
+/// How the client handles channel binding, encoded in the GS2 header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ChannelBinding {
+    /// The client does not support channel binding.
+    Unsupported,
+
+    /// The client supports channel binding but thinks the server does not.
+    NotAdvertised,
+
+    /// The client uses `tls-exporter` channel binding with the given data.
+    TlsExporter(Vec<u8>),
+}
+
+impl ChannelBinding {
+    /// The GS2 header for the binding.
+    fn gs2_header(&self) -> &'static str {
+        match self {
+            Self::Unsupported => "n,,",
+            Self::NotAdvertised => "y,,",
+            Self::TlsExporter(_) => "p=tls-exporter,,",
+        }
+    }
+}
+
+/// Errors from the SCRAM exchange.
+#[derive(Debug, thiserror::Error)]
+pub enum ScramError {
+    /// The server sent a message that does not follow the SCRAM syntax.
+    #[error("malformed server message")]
+    MalformedServerMessage,
+
+    /// The server nonce does not extend the client nonce.
+    #[error("server nonce does not match the client nonce")]
+    NonceMismatch,
+
+    /// The server reported an error in the final message.
+    #[error("server reported an error: {0}")]
+    Server(String),
+
+    /// The server signature did not match, so the server is not trusted.
+    #[error("server signature verification failed")]
+    ServerSignature,
+
+    /// The server completed the authentication without proving its identity.
+    #[error("server did not send the final message")]
+    MissingServerFinal,
+}
+
+/// The exchange state.
+enum State {
+    /// Nothing sent yet.
+    Initial,
+
+    /// The client-first message was sent.
+    ClientFirstSent {
+        /// The client-first message without the GS2 header.
+        client_first_bare: String,
+    },
+
+    /// The client-final message was sent.
+    ClientFinalSent {
+        /// The server signature to expect.
+        server_signature: Vec<u8>,
+    },
+
+    /// The server was verified.
+    Verified,
+
+    /// The exchange failed.
+    Failed(ScramError),
+}
+
+/// The SCRAM client side of the exchange.
+pub(crate) struct Client<'a> {
+    /// The hash function.
+    hash: Hash,
+
+    /// The username.
+    username: &'a str,
+
+    /// The password.
+    password: &'a str,
+
+    /// The client nonce.
+    nonce: String,
+
+    /// The channel binding.
+    channel_binding: ChannelBinding,
+
+    /// The exchange state.
+    state: State,
+}
+
+impl<'a> Client<'a> {
+    /// Create a new SCRAM client with the given nonce.
+    pub fn new(
+        hash: Hash,
+        username: &'a str,
+        password: &'a str,
+        nonce: String,
+        channel_binding: ChannelBinding,
+    ) -> Self {
+        Self {
+            hash,
+            username,
+            password,
+            nonce,
+            channel_binding,
+            state: State::Initial,
+        }
+    }
+
+    /// Generate a random client nonce.
+    pub fn random_nonce() -> Result<String, ring::error::Unspecified> {
+        use ring::rand::SecureRandom as _;
+
+        let mut bytes = [0u8; 24];
+        ring::rand::SystemRandom::new().fill(&mut bytes)?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    /// Process the server challenge and produce the next client response.
+    pub fn step(&mut self, challenge: &[u8]) -> Vec<u8> {
+        let state = std::mem::replace(&mut self.state, State::Verified);
+        let (state, response) = match state {
+            State::Initial => {
+                let client_first_bare = format!(
+                    "n={},r={}",
+                    crate::oauth2::gs2_escape(self.username),
+                    self.nonce
+                );
+                let response =
+                    format!("{}{}", self.channel_binding.gs2_header(), client_first_bare);
+                (
+                    State::ClientFirstSent { client_first_bare },
+                    response.into_bytes(),
+                )
+            }
+            State::ClientFirstSent { client_first_bare } => {
+                match self.client_final(&client_first_bare, challenge) {
+                    Ok((response, server_signature)) => (
+                        State::ClientFinalSent { server_signature },
+                        response.into_bytes(),
+                    ),
+                    Err(error) => (State::Failed(error), Vec::new()),
+                }
+            }
+            State::ClientFinalSent { server_signature } => {
+                match verify_server_final(&server_signature, challenge) {
+                    Ok(()) => (State::Verified, Vec::new()),
+                    Err(error) => (State::Failed(error), Vec::new()),
+                }
+            }
+            state @ (State::Verified | State::Failed(_)) => (state, Vec::new()),
+        };
+        self.state = state;
+        response
+    }
+
+    /// Conclude the exchange, checking the server was verified.
+    pub fn finish(self) -> Result<(), ScramError> {
+        match self.state {
+            State::Verified => Ok(()),
+            State::Failed(error) => Err(error),
+            State::Initial | State::ClientFirstSent { .. } | State::ClientFinalSent { .. } => {
+                Err(ScramError::MissingServerFinal)
+            }
+        }
+    }
+
+    /// The error the exchange failed with, if any.
+    pub fn error(self) -> Option<ScramError> {
+        match self.state {
+            State::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Build the client-final message and the expected server signature.
+    fn client_final(
+        &self,
+        client_first_bare: &str,
+        server_first: &[u8],
+    ) -> Result<(String, Vec<u8>), ScramError> {
+        let server_first =
+            std::str::from_utf8(server_first).map_err(|_| ScramError::MalformedServerMessage)?;
+
+        let mut nonce = None;
+        let mut salt = None;
+        let mut iterations = None;
+        for attribute in server_first.split(',') {
+            match attribute.split_once('=') {
+                Some(("r", value)) => nonce = Some(value),
+                Some(("s", value)) => salt = Some(value),
+                Some(("i", value)) => iterations = Some(value),
+                Some(("m", _)) => return Err(ScramError::MalformedServerMessage),
+                _ => {}
+            }
+        }
+        let (Some(nonce), Some(salt), Some(iterations)) = (nonce, salt, iterations) else {
+            return Err(ScramError::MalformedServerMessage);
+        };
+
+        if !nonce.starts_with(&self.nonce) || nonce.len() == self.nonce.len() {
+            return Err(ScramError::NonceMismatch);
+        }
+        let salt = base64::engine::general_purpose::STANDARD
+            .decode(salt)
+            .map_err(|_| ScramError::MalformedServerMessage)?;
+        let iterations: std::num::NonZeroU32 = iterations
+            .parse()
+            .map_err(|_| ScramError::MalformedServerMessage)?;
+
+        let mut channel_binding_input = self.channel_binding.gs2_header().as_bytes().to_vec();
+        if let ChannelBinding::TlsExporter(data) = &self.channel_binding {
+            channel_binding_input.extend_from_slice(data);
+        }
+        let client_final_without_proof = format!(
+            "c={},r={nonce}",
+            base64::engine::general_purpose::STANDARD.encode(channel_binding_input)
+        );
+        let auth_message =
+            format!("{client_first_bare},{server_first},{client_final_without_proof}");
+
+        let mut salted_password = vec![0u8; self.hash.digest().output_len()];
+        ring::pbkdf2::derive(
+            self.hash.pbkdf2(),
+            iterations,
+            &salt,
+            self.password.as_bytes(),
+            &mut salted_password,
+        );
+        let salted_password = ring::hmac::Key::new(self.hash.hmac(), &salted_password);
+
+        let client_key = ring::hmac::sign(&salted_password, b"Client Key");
+        let stored_key = ring::digest::digest(self.hash.digest(), client_key.as_ref());
+        let stored_key = ring::hmac::Key::new(self.hash.hmac(), stored_key.as_ref());
+        let client_signature = ring::hmac::sign(&stored_key, auth_message.as_bytes());
+        let client_proof: Vec<u8> = client_key
+            .as_ref()
+            .iter()
+            .zip(client_signature.as_ref())
+            .map(|(key, signature)| key ^ signature)
+            .collect();
+
+        let server_key = ring::hmac::sign(&salted_password, b"Server Key");
+        let server_key = ring::hmac::Key::new(self.hash.hmac(), server_key.as_ref());
+        let server_signature = ring::hmac::sign(&server_key, auth_message.as_bytes());
+
+        let client_final = format!(
+            "{client_final_without_proof},p={}",
+            base64::engine::general_purpose::STANDARD.encode(client_proof)
+        );
+
+        Ok((client_final, server_signature.as_ref().to_vec()))
+    }
+}
+
+/// Check the server-final message against the expected server signature.
+fn verify_server_final(server_signature: &[u8], server_final: &[u8]) -> Result<(), ScramError> {
+    let server_final =
+        std::str::from_utf8(server_final).map_err(|_| ScramError::MalformedServerMessage)?;
+
+    match server_final
+        .split(',')
+        .next()
+        .and_then(|attribute| attribute.split_once('='))
+    {
+        Some(("v", value)) => {
+            let value = base64::engine::general_purpose::STANDARD
+                .decode(value)
+                .map_err(|_| ScramError::MalformedServerMessage)?;
+            if value != server_signature {
+                return Err(ScramError::ServerSignature);
+            }
+            Ok(())
+        }
+        Some(("e", value)) => Err(ScramError::Server(value.to_owned())),
+        _ => Err(ScramError::MalformedServerMessage),
+    }
+}
+
+/// SCRAM authenticator driving the [`Client`].
+pub(crate) struct Authenticator<'a, 'b> {
+    /// The SCRAM client.
+    pub client: &'b mut Client<'a>,
+}
+
+impl async_imap::Authenticator for Authenticator<'_, '_> {
+    type Response = Vec<u8>;
+
+    fn process(&mut self, challenge: &[u8]) -> Self::Response {
+        self.client.step(challenge)
+    }
+}
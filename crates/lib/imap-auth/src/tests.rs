@@ -0,0 +1,364 @@
+use async_imap::Authenticator as _;
+
+use super::*;
+
+/// Build an authenticator for the given mechanism with the test credentials.
+fn authenticator(
+    mechanism: OAuth2Mechanism,
+    error_challenge: &mut Option<OAuth2ErrorChallenge>,
+) -> oauth2::Authenticator<'static, '_> {
+    oauth2::Authenticator {
+        mechanism,
+        user: "user@example.com",
+        access_token: "token123",
+        host: "imap.example.com",
+        port: 993,
+        sent_initial_response: false,
+        error_challenge,
+    }
+}
+
+#[test]
+fn xoauth2_initial_response() {
+    let mut error_challenge = None;
+    let mut authenticator = authenticator(OAuth2Mechanism::XOAuth2, &mut error_challenge);
+
+    let response = authenticator.process(b"");
+
+    assert_eq!(
+        response,
+        b"user=user@example.com\x01auth=Bearer token123\x01\x01"
+    );
+}
+
+#[test]
+fn oauthbearer_initial_response() {
+    let mut error_challenge = None;
+    let mut authenticator = authenticator(OAuth2Mechanism::OAuthBearer, &mut error_challenge);
+
+    let response = authenticator.process(b"");
+
+    assert_eq!(
+        response,
+        b"n,a=user@example.com,\x01host=imap.example.com\x01port=993\x01auth=Bearer token123\x01\x01"
+    );
+}
+
+#[test]
+fn oauthbearer_error_challenge_is_captured() {
+    let mut error_challenge = None;
+    let mut authenticator = authenticator(OAuth2Mechanism::OAuthBearer, &mut error_challenge);
+
+    let _ = authenticator.process(b"");
+    let response = authenticator.process(
+        br#"{"status":"invalid_token","schemes":"bearer","scope":"https://mail.google.com/"}"#,
+    );
+
+    assert_eq!(response, b"\x01");
+    assert_eq!(
+        error_challenge,
+        Some(OAuth2ErrorChallenge {
+            status: Some("invalid_token".to_owned()),
+            schemes: Some("bearer".to_owned()),
+            scope: Some("https://mail.google.com/".to_owned()),
+            openid_configuration: None,
+        })
+    );
+}
+
+#[test]
+fn gs2_escapes_authzid() {
+    assert_eq!(oauth2::gs2_escape("a=b,c"), "a=3Db=2Cc");
+}
+
+#[test]
+fn negotiates_oauth2_mechanism() {
+    let both: imap_capabilities::Capabilities = ["IMAP4rev1", "AUTH=XOAUTH2", "AUTH=OAUTHBEARER"]
+        .into_iter()
+        .collect();
+    let xoauth2_only: imap_capabilities::Capabilities =
+        ["IMAP4rev1", "AUTH=XOAUTH2"].into_iter().collect();
+    let none: imap_capabilities::Capabilities = ["IMAP4rev1"].into_iter().collect();
+
+    assert_eq!(
+        OAuth2Mechanism::negotiate(&both),
+        OAuth2Mechanism::OAuthBearer
+    );
+    assert_eq!(
+        OAuth2Mechanism::negotiate(&xoauth2_only),
+        OAuth2Mechanism::XOAuth2
+    );
+    assert_eq!(OAuth2Mechanism::negotiate(&none), OAuth2Mechanism::XOAuth2);
+}
+
+#[test]
+fn negotiates_password_mechanism() {
+    let scram: imap_capabilities::Capabilities = [
+        "IMAP4rev1",
+        "AUTH=PLAIN",
+        "AUTH=SCRAM-SHA-1",
+        "AUTH=SCRAM-SHA-256",
+        "AUTH=SCRAM-SHA-256-PLUS",
+    ]
+    .into_iter()
+    .collect();
+    let login_disabled: imap_capabilities::Capabilities =
+        ["IMAP4rev1", "LOGINDISABLED"].into_iter().collect();
+    let plain_only: imap_capabilities::Capabilities = ["IMAP4rev1", "AUTH=PLAIN", "LOGINDISABLED"]
+        .into_iter()
+        .collect();
+    let none: imap_capabilities::Capabilities = ["IMAP4rev1"].into_iter().collect();
+
+    assert_eq!(
+        PasswordMechanism::negotiate(&scram, true),
+        Some(PasswordMechanism::ScramSha256Plus)
+    );
+    assert_eq!(
+        PasswordMechanism::negotiate(&scram, false),
+        Some(PasswordMechanism::ScramSha256)
+    );
+    assert_eq!(
+        PasswordMechanism::negotiate(&plain_only, true),
+        Some(PasswordMechanism::Plain)
+    );
+    assert_eq!(
+        PasswordMechanism::negotiate(&none, false),
+        Some(PasswordMechanism::Login)
+    );
+    assert_eq!(PasswordMechanism::negotiate(&login_disabled, false), None);
+}
+
+/// Run the SCRAM exchange and check the messages against the expected ones.
+fn check_scram_exchange(
+    hash: scram::Hash,
+    nonce: &str,
+    server_first: &str,
+    expected_client_final: &str,
+    server_final: &str,
+) {
+    let mut client = scram::Client::new(
+        hash,
+        "user",
+        "pencil",
+        nonce.to_owned(),
+        scram::ChannelBinding::Unsupported,
+    );
+
+    let client_first = client.step(b"");
+    assert_eq!(client_first, format!("n,,n=user,r={nonce}").into_bytes());
+
+    let client_final = client.step(server_first.as_bytes());
+    assert_eq!(
+        String::from_utf8(client_final).unwrap(),
+        expected_client_final
+    );
+
+    let last = client.step(server_final.as_bytes());
+    assert!(last.is_empty());
+    client.finish().expect("server should be verified");
+}
+
+#[test]
+fn scram_sha_1_rfc5802_exchange() {
+    check_scram_exchange(
+        scram::Hash::Sha1,
+        "fyko+d2lbbFgONRv9qkxdawL",
+        "r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096",
+        "c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts=",
+        "v=rmF9pqV8S7suAoZWja4dJRkFsKQ=",
+    );
+}
+
+#[test]
+fn scram_sha_256_rfc7677_exchange() {
+    check_scram_exchange(
+        scram::Hash::Sha256,
+        "rOprNGfwEbeRWgbNEkqO",
+        "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+        "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=",
+        "v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=",
+    );
+}
+
+#[test]
+fn scram_rejects_forged_server_signature() {
+    let mut client = scram::Client::new(
+        scram::Hash::Sha256,
+        "user",
+        "pencil",
+        "rOprNGfwEbeRWgbNEkqO".to_owned(),
+        scram::ChannelBinding::Unsupported,
+    );
+
+    let _ = client.step(b"");
+    let _ = client.step(
+        b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+    );
+    let _ = client.step(b"v=AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=");
+
+    assert!(matches!(client.finish(), Err(ScramError::ServerSignature)));
+}
+
+#[test]
+fn scram_rejects_foreign_nonce() {
+    let mut client = scram::Client::new(
+        scram::Hash::Sha256,
+        "user",
+        "pencil",
+        "rOprNGfwEbeRWgbNEkqO".to_owned(),
+        scram::ChannelBinding::Unsupported,
+    );
+
+    let _ = client.step(b"");
+    let _ = client.step(b"r=somethingelse,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096");
+
+    assert!(matches!(client.finish(), Err(ScramError::NonceMismatch)));
+}
+
+#[test]
+fn scram_channel_binding_gs2_header() {
+    let mut client = scram::Client::new(
+        scram::Hash::Sha256,
+        "user",
+        "pencil",
+        "nonce".to_owned(),
+        scram::ChannelBinding::TlsExporter(vec![1, 2, 3]),
+    );
+
+    assert_eq!(client.step(b""), b"p=tls-exporter,,n=user,r=nonce");
+
+    let plus: imap_capabilities::Capabilities = ["IMAP4rev1", "AUTH=SCRAM-SHA-256-PLUS"]
+        .into_iter()
+        .collect();
+    let no_plus: imap_capabilities::Capabilities =
+        ["IMAP4rev1", "AUTH=SCRAM-SHA-256"].into_iter().collect();
+    let cases = [
+        (Some(vec![1]), None, b"y,,n=user,r=nonce".as_slice()),
+        (Some(vec![1]), Some(&no_plus), b"y,,n=user,r=nonce"),
+        (Some(vec![1]), Some(&plus), b"n,,n=user,r=nonce"),
+        (None, None, b"n,,n=user,r=nonce"),
+    ];
+    for (data, capabilities, expected) in cases {
+        let channel_binding =
+            scram_channel_binding(PasswordMechanism::ScramSha256, data, capabilities).unwrap();
+        let mut client = scram::Client::new(
+            scram::Hash::Sha256,
+            "user",
+            "pencil",
+            "nonce".to_owned(),
+            channel_binding,
+        );
+
+        assert_eq!(client.step(b""), expected, "{capabilities:?}");
+    }
+}
+
+#[test]
+fn external_response_carries_authzid() {
+    let mut derived = external::Authenticator { authzid: None };
+    let mut explicit = external::Authenticator {
+        authzid: Some("device@example.com"),
+    };
+
+    assert_eq!(derived.process(b""), "");
+    assert_eq!(explicit.process(b""), "device@example.com");
+}
+
+#[test]
+fn parses_rfc5530_response_codes() {
+    let no = |text: &str| async_imap::error::Error::No(format!("code: None, info: Some({text:?})"));
+
+    let cases = [
+        (
+            "[AUTHENTICATIONFAILED] Invalid credentials (Failure)",
+            Some(ResponseCode::AuthenticationFailed),
+        ),
+        (
+            "[AUTHORIZATIONFAILED] No such authorization-ID",
+            Some(ResponseCode::AuthorizationFailed),
+        ),
+        (
+            "[EXPIRED] That password isn't valid",
+            Some(ResponseCode::Expired),
+        ),
+        (
+            "[UNAVAILABLE] Try again later",
+            Some(ResponseCode::Unavailable),
+        ),
+        ("[limit] Too many connections", Some(ResponseCode::Limit)),
+        ("[ALERT] Maintenance tonight", None),
+        ("LOGIN failed", None),
+    ];
+
+    for (text, expected) in cases {
+        assert_eq!(ResponseCode::from_error(&no(text)), expected, "{text}");
+    }
+}
+
+#[test]
+fn classifies_failures() {
+    let no = |text: &str| async_imap::error::Error::No(format!("code: None, info: Some({text:?})"));
+
+    let kind = Error::Login(no("[AUTHENTICATIONFAILED] Authentication failed.")).kind();
+    assert_eq!(kind, FailureKind::AuthenticationFailed);
+    assert!(kind.is_permanent());
+
+    let kind = Error::Login(no("[UNAVAILABLE] Internal error occurred.")).kind();
+    assert_eq!(kind, FailureKind::Unavailable);
+    assert!(!kind.is_permanent());
+
+    let kind = Error::Login(async_imap::error::Error::ConnectionLost).kind();
+    assert_eq!(kind, FailureKind::Other);
+    assert!(!kind.is_permanent());
+
+    let kind = Error::OAuth2 {
+        mechanism: OAuth2Mechanism::OAuthBearer,
+        source: no("SASL authentication failed"),
+        error_challenge: Some(OAuth2ErrorChallenge {
+            status: Some("401".to_owned()),
+            schemes: None,
+            scope: None,
+            openid_configuration: None,
+        }),
+    }
+    .kind();
+    assert_eq!(kind, FailureKind::AuthenticationFailed);
+
+    let kind = Error::Scram {
+        mechanism: PasswordMechanism::ScramSha256,
+        source: ScramError::Server("invalid-proof".to_owned()),
+    }
+    .kind();
+    assert_eq!(kind, FailureKind::AuthenticationFailed);
+
+    assert!(Error::NoPasswordMechanism.kind().is_permanent());
+}
+
+/// The kind of an OAUTHBEARER failure with the error challenge status.
+fn oauthbearer_failure_kind(status: &str) -> FailureKind {
+    Error::OAuth2 {
+        mechanism: OAuth2Mechanism::OAuthBearer,
+        source: async_imap::error::Error::No("SASL authentication failed".to_owned()),
+        error_challenge: Some(OAuth2ErrorChallenge {
+            status: Some(status.to_owned()),
+            schemes: Some("bearer".to_owned()),
+            scope: None,
+            openid_configuration: None,
+        }),
+    }
+    .kind()
+}
+
+#[test]
+fn classifies_oauthbearer_invalid_token() {
+    let kind = oauthbearer_failure_kind("invalid_token");
+    assert_eq!(kind, FailureKind::AuthenticationFailed);
+    assert!(kind.is_permanent());
+}
+
+#[test]
+fn classifies_oauthbearer_insufficient_scope() {
+    let kind = oauthbearer_failure_kind("insufficient_scope");
+    assert_eq!(kind, FailureKind::AuthorizationFailed);
+    assert!(kind.is_permanent());
+}
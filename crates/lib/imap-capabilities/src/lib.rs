@@ -0,0 +1,98 @@
+//! IMAP capability helpers.
+
+use async_imap::imap_proto;
+
+/// A set of capabilities advertised by an IMAP server.
+///
+/// Capability names are case-insensitive, so they are stored uppercased.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Uppercased capability names.
+    names: std::collections::BTreeSet<String>,
+}
+
+impl Capabilities {
+    /// Build the capabilities from the parsed protocol capabilities.
+    pub fn from_proto(capabilities: &[imap_proto::Capability<'_>]) -> Self {
+        capabilities.iter().map(proto_capability_name).collect()
+    }
+
+    /// Check whether the capability is advertised.
+    pub fn has(&self, name: &str) -> bool {
+        self.names.contains(&name.to_ascii_uppercase())
+    }
+
+    /// Check whether the SASL mechanism is advertised via `AUTH=`.
+    pub fn has_auth(&self, mechanism: &str) -> bool {
+        self.has(&format!("AUTH={mechanism}"))
+    }
+
+    /// Iterate over the advertised SASL mechanisms.
+    pub fn auth_mechanisms(&self) -> impl Iterator<Item = &str> {
+        self.names
+            .iter()
+            .filter_map(|name| name.strip_prefix("AUTH="))
+    }
+
+    /// Iterate over all the advertised capabilities.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for Capabilities {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        let names = iter
+            .into_iter()
+            .map(|name| name.as_ref().to_ascii_uppercase())
+            .collect();
+        Self { names }
+    }
+}
+
+impl From<&async_imap::types::Capabilities> for Capabilities {
+    fn from(capabilities: &async_imap::types::Capabilities) -> Self {
+        capabilities
+            .iter()
+            .map(|capability| match capability {
+                async_imap::types::Capability::Imap4rev1 => "IMAP4rev1".to_owned(),
+                async_imap::types::Capability::Auth(mechanism) => format!("AUTH={mechanism}"),
+                async_imap::types::Capability::Atom(atom) => atom.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Render the protocol capability as a capability name.
+fn proto_capability_name(capability: &imap_proto::Capability<'_>) -> String {
+    match capability {
+        imap_proto::Capability::Imap4rev1 => "IMAP4rev1".to_owned(),
+        imap_proto::Capability::Auth(mechanism) => format!("AUTH={mechanism}"),
+        imap_proto::Capability::Atom(atom) => atom.to_string(),
+    }
+}
+
+/// Query the capabilities over the connection.
+///
+/// Works both before and after authentication.
+pub async fn fetch<S>(
+    conn: &mut async_imap::Connection<S>,
+) -> Result<Capabilities, async_imap::error::Error>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + std::fmt::Debug,
+{
+    let (sender, receiver) = async_channel::unbounded();
+    conn.run_command_and_check_ok("CAPABILITY", Some(sender))
+        .await?;
+
+    let mut capabilities = Capabilities::default();
+    while let Ok(response) = receiver.try_recv() {
+        if let async_imap::types::UnsolicitedResponse::Other(data) = response
+            && let imap_proto::Response::Capabilities(list) = data.parsed()
+        {
+            capabilities = Capabilities::from_proto(list);
+        }
+    }
+
+    Ok(capabilities)
+}
@@ -6,19 +6,27 @@ pub enum FetchCountsError {
     /// IMAP protocol error.
     #[error("IMAP error: {0}")]
     Imap(#[from] async_imap::error::Error),
+
+    /// The server did not answer `STATUS` within the time limit.
+    #[error("timed out waiting for STATUS after {0:?}")]
+    Timeout(std::time::Duration),
 }
 
 /// Query the current total and unread counts for the mailbox.
 pub async fn fetch_counts<S>(
     session: &mut async_imap::Session<S>,
     mailbox: &imap_utf7::ImapUtf7Str,
+    timeout: std::time::Duration,
 ) -> Result<crate::MailboxCounts, FetchCountsError>
 where
     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
 {
-    let status = session
-        .status(mailbox.as_str(), "(MESSAGES UNSEEN)")
-        .await?;
+    let status = tokio::time::timeout(
+        timeout,
+        session.status(mailbox.as_str(), "(MESSAGES UNSEEN)"),
+    )
+    .await
+    .map_err(|_| FetchCountsError::Timeout(timeout))??;
     Ok(crate::MailboxCounts {
         total: status.exists,
         unread: status.unseen.unwrap_or(0),
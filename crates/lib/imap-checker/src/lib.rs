@@ -2,8 +2,12 @@
 
 mod fetch_counts;
 mod mailbox_counts;
+mod mailbox_state;
 mod monitor_mailbox_counts;
 
 pub use fetch_counts::*;
 pub use mailbox_counts::*;
 pub use monitor_mailbox_counts::*;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,216 @@
+//! The selected mailbox, kept up to date from the server responses.
+
+use std::collections::{BTreeSet, HashSet};
+
+use async_imap::imap_proto::{AttributeValue, MailboxDatum, Response};
+use async_imap::types::Flag;
+use futures_util::TryStreamExt as _;
+
+/// What is known of the selected mailbox.
+#[derive(Debug)]
+pub(crate) struct MailboxState {
+    /// How many messages the mailbox holds.
+    exists: u32,
+
+    /// The UIDs of the unseen messages.
+    unseen: HashSet<u32>,
+
+    /// The changes reported since the last lookup.
+    changes: Changes,
+}
+
+/// The changes reported by the server that need a lookup.
+#[derive(Debug, Default)]
+struct Changes {
+    /// Whether messages were expunged, so the sequence numbers moved.
+    expunged: bool,
+
+    /// The sequence number of the first message that arrived, if any.
+    arrived: Option<u32>,
+
+    /// The sequence numbers of the messages whose flags changed.
+    flagged: BTreeSet<u32>,
+}
+
+impl MailboxState {
+    /// Select the mailbox and look up its unseen messages.
+    pub(crate) async fn sync<Stream>(
+        session: &mut async_imap::Session<Stream>,
+        mailbox: &imap_utf7::ImapUtf7Str,
+        timeout: std::time::Duration,
+    ) -> Result<Self, crate::MonitorError>
+    where
+        Stream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+    {
+        let selected = crate::limit("SELECT", timeout, session.select(mailbox.as_str())).await?;
+        // Whatever was reported before is superseded by the selection.
+        while session.unsolicited_responses.try_recv().is_ok() {}
+
+        let unseen = crate::limit("UID SEARCH", timeout, session.uid_search("UNSEEN")).await?;
+        Ok(Self {
+            exists: selected.exists,
+            unseen,
+            changes: Changes::default(),
+        })
+    }
+
+    /// The mailbox counts.
+    pub(crate) fn counts(&self) -> crate::MailboxCounts {
+        crate::MailboxCounts {
+            total: self.exists,
+            unread: u32::try_from(self.unseen.len()).unwrap_or(u32::MAX),
+        }
+    }
+
+    /// Note the responses the server sent along with the commands.
+    pub(crate) fn drain(
+        &mut self,
+        unsolicited: &async_channel::Receiver<async_imap::types::UnsolicitedResponse>,
+    ) {
+        while let Ok(response) = unsolicited.try_recv() {
+            match response {
+                async_imap::types::UnsolicitedResponse::Exists(exists) => self.exists(exists),
+                async_imap::types::UnsolicitedResponse::Expunge(_) => self.expunge(),
+                async_imap::types::UnsolicitedResponse::Other(response) => {
+                    self.note(response.parsed());
+                }
+                async_imap::types::UnsolicitedResponse::Recent(_)
+                | async_imap::types::UnsolicitedResponse::Status { .. } => {}
+            }
+        }
+    }
+
+    /// Note a response from the server.
+    ///
+    /// `RECENT` is left out, as the arrivals are told by `EXISTS`.
+    pub(crate) fn note(&mut self, response: &Response<'_>) {
+        match response {
+            Response::MailboxData(MailboxDatum::Exists(exists)) => self.exists(*exists),
+            Response::Expunge(_) => self.expunge(),
+            Response::Fetch(seq, attributes) => self.fetch(*seq, attributes),
+            _ => {}
+        }
+    }
+
+    /// Note the new message count.
+    fn exists(&mut self, exists: u32) {
+        if exists > self.exists {
+            self.changes.arrived.get_or_insert(self.exists + 1);
+        } else if exists < self.exists {
+            // Only an expunge shrinks the mailbox, even if it went untold.
+            self.changes.expunged = true;
+        }
+        self.exists = exists;
+    }
+
+    /// Note an expunged message, whose UID is not told.
+    fn expunge(&mut self) {
+        self.exists = self.exists.saturating_sub(1);
+        self.changes.expunged = true;
+    }
+
+    /// Note the flags of a message, looked up later unless the UID is told.
+    fn fetch(&mut self, seq: u32, attributes: &[AttributeValue<'_>]) {
+        let flags = attributes.iter().find_map(|attribute| match attribute {
+            AttributeValue::Flags(flags) => Some(flags),
+            _ => None,
+        });
+        let Some(flags) = flags else {
+            return;
+        };
+        let seen = flags.iter().any(|flag| flag.eq_ignore_ascii_case("\\Seen"));
+
+        let uid = attributes.iter().find_map(|attribute| match attribute {
+            AttributeValue::Uid(uid) => Some(*uid),
+            _ => None,
+        });
+        match uid {
+            Some(uid) if seen => {
+                self.unseen.remove(&uid);
+            }
+            Some(uid) => {
+                self.unseen.insert(uid);
+            }
+            None => {
+                self.changes.flagged.insert(seq);
+            }
+        }
+    }
+
+    /// Whether changes are waiting to be looked up.
+    pub(crate) fn pending(&self) -> bool {
+        let Changes {
+            expunged,
+            arrived,
+            flagged,
+        } = &self.changes;
+        *expunged || arrived.is_some() || !flagged.is_empty()
+    }
+
+    /// Look up the UIDs and flags of the changed messages, in one `FETCH`.
+    ///
+    /// After an expunge, the sequence numbers noted are stale, so all the
+    /// unseen messages are looked up again.
+    pub(crate) async fn apply<Stream>(
+        &mut self,
+        session: &mut async_imap::Session<Stream>,
+        timeout: std::time::Duration,
+    ) -> Result<(), crate::MonitorError>
+    where
+        Stream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+    {
+        let Changes {
+            expunged,
+            arrived,
+            mut flagged,
+        } = std::mem::take(&mut self.changes);
+
+        if expunged {
+            self.unseen = crate::limit("UID SEARCH", timeout, session.uid_search("UNSEEN")).await?;
+            return Ok(());
+        }
+
+        if let Some(arrived) = arrived {
+            flagged.extend(arrived..=self.exists);
+        }
+        if flagged.is_empty() {
+            return Ok(());
+        }
+
+        let query = sequence_set(&flagged);
+        let fetches = crate::limit("FETCH", timeout, async {
+            let fetches = session.fetch(query, "(UID FLAGS)").await?;
+            fetches.try_collect::<Vec<_>>().await
+        })
+        .await?;
+        for fetch in &fetches {
+            let Some(uid) = fetch.uid else {
+                continue;
+            };
+            if fetch.flags().any(|flag| flag == Flag::Seen) {
+                self.unseen.remove(&uid);
+            } else {
+                self.unseen.insert(uid);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The sequence set of the sequence numbers, with the runs as ranges.
+fn sequence_set(seqs: &BTreeSet<u32>) -> String {
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for &seq in seqs {
+        match runs.last_mut() {
+            Some((_, last)) if *last + 1 == seq => *last = seq,
+            _ => runs.push((seq, seq)),
+        }
+    }
+    runs.iter()
+        .map(|&(first, last)| match first == last {
+            true => first.to_string(),
+            false => format!("{first}:{last}"),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
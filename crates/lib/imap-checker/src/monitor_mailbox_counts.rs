@@ -1,4 +1,6 @@
-//! IMAP IDLE monitoring routine.
+//! IMAP IDLE monitoring routine, with polling for servers without IDLE.
+
+use crate::mailbox_state::MailboxState;
 
 /// Errors returned by the IMAP monitor.
 #[derive(Debug, thiserror::Error)]
@@ -14,13 +16,68 @@ pub enum MonitorError {
     /// IMAP protocol error during IDLE.
     #[error("IMAP error: {0}")]
     Imap(#[from] async_imap::error::Error),
+
+    /// The server did not answer a command within the time limit.
+    #[error("timed out waiting for {command} after {after:?}")]
+    Timeout {
+        /// The command that timed out.
+        command: &'static str,
+
+        /// The time limit.
+        after: std::time::Duration,
+    },
+}
+
+/// How to wait for the mailbox to change.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum WatchMode {
+    /// IDLE when the server supports it, polling otherwise.
+    Auto {
+        /// How often to poll the counts.
+        poll_interval: std::time::Duration,
+    },
+
+    /// IDLE, failing when the server does not support it.
+    Idle,
+
+    /// Poll the counts.
+    Poll {
+        /// How often to poll the counts.
+        interval: std::time::Duration,
+    },
+}
+
+/// Run the command within the time limit.
+pub(crate) async fn limit<T, F>(
+    command: &'static str,
+    after: std::time::Duration,
+    future: F,
+) -> Result<T, MonitorError>
+where
+    F: Future<Output = Result<T, async_imap::error::Error>>,
+{
+    let result = tokio::time::timeout(after, future)
+        .await
+        .map_err(|_| MonitorError::Timeout { command, after })?;
+    Ok(result?)
 }
 
 /// Monitor mailbox counts and send updates on change.
+///
+/// Every command is limited to `command_timeout`. While idle, the counts
+/// follow the changes the server reports, looking up the UIDs of the
+/// unseen messages among them, and are re-synced every `idle_timeout`.
+/// With a `heartbeat`, IDLE is also interrupted that often to check with
+/// `NOOP` that the connection is still alive. When polling, the counts are
+/// queried with `STATUS` at the interval instead, which also checks the
+/// connection.
 pub async fn monitor_mailbox_counts<Stream, Notify, NotifyFut>(
     mut session: async_imap::Session<Stream>,
     mailbox: &imap_utf7::ImapUtf7Str,
+    watch: WatchMode,
     idle_timeout: std::time::Duration,
+    command_timeout: std::time::Duration,
+    heartbeat: Option<std::time::Duration>,
     mut notify: Notify,
 ) -> Result<core::convert::Infallible, MonitorError>
 where
@@ -28,24 +85,101 @@ where
     Notify: FnMut(crate::MailboxCounts) -> NotifyFut + Send,
     NotifyFut: std::future::Future<Output = ()> + Send,
 {
-    let capabilities = session.capabilities().await?;
-    if !capabilities.has_str("IDLE") {
-        return Err(MonitorError::IdleNotSupported);
+    let poll_interval = match watch {
+        WatchMode::Poll { interval } => Some(interval),
+        WatchMode::Auto { .. } | WatchMode::Idle => {
+            let capabilities = limit("CAPABILITY", command_timeout, session.capabilities()).await?;
+            match (capabilities.has_str("IDLE"), watch) {
+                (true, _) => None,
+                (false, WatchMode::Auto { poll_interval }) => {
+                    tracing::info!(
+                        imap_mailbox = %mailbox,
+                        ?poll_interval,
+                        "IMAP server does not advertise IDLE, polling instead"
+                    );
+                    Some(poll_interval)
+                }
+                (false, _) => return Err(MonitorError::IdleNotSupported),
+            }
+        }
+    };
+    if let Some(interval) = poll_interval {
+        return poll_mailbox_counts(session, mailbox, interval, command_timeout, notify).await;
     }
 
-    session.select(mailbox.as_str()).await?;
-    let mut last_counts = crate::fetch_counts(&mut session, mailbox).await?;
+    let unsolicited = session.unsolicited_responses.clone();
+    let mut state = MailboxState::sync(&mut session, mailbox, command_timeout).await?;
+    let mut last_counts = state.counts();
 
     notify(last_counts).await;
 
+    let mut requery_at = tokio::time::Instant::now() + idle_timeout;
     loop {
+        let remaining = requery_at.saturating_duration_since(tokio::time::Instant::now());
+        let wait = heartbeat.map_or(remaining, |heartbeat| heartbeat.min(remaining));
+
         let mut idle_handle = session.idle();
-        idle_handle.init().await?;
-        let (idle_wait, _stop) = idle_handle.wait_with_timeout(idle_timeout);
-        let _idle_response = idle_wait.await?;
-        session = idle_handle.done().await?;
+        limit("IDLE", command_timeout, idle_handle.init()).await?;
+        state.drain(&unsolicited);
+        let (idle_wait, _stop) = idle_handle.wait_with_timeout(wait);
+        let idle_response = idle_wait.await?;
+        session = limit("DONE", command_timeout, idle_handle.done()).await?;
+
+        match idle_response {
+            async_imap::extensions::idle::IdleResponse::NewData(response) => {
+                state.note(response.parsed());
+            }
+            async_imap::extensions::idle::IdleResponse::Timeout
+                if tokio::time::Instant::now() < requery_at =>
+            {
+                limit("NOOP", command_timeout, session.noop()).await?;
+            }
+            async_imap::extensions::idle::IdleResponse::Timeout
+            | async_imap::extensions::idle::IdleResponse::ManualInterrupt => {
+                requery_at = tokio::time::Instant::now() + idle_timeout;
+                state = MailboxState::sync(&mut session, mailbox, command_timeout).await?;
+            }
+        }
+
+        // The server may report more changes along with the lookups, which
+        // are looked up as well before going back to IDLE.
+        loop {
+            state.drain(&unsolicited);
+            if !state.pending() {
+                break;
+            }
+            state.apply(&mut session, command_timeout).await?;
+        }
+
+        let counts = state.counts();
+        if counts != last_counts {
+            last_counts = counts;
+            notify(counts).await;
+        }
+    }
+}
+
+/// Query the mailbox counts at the interval and send updates on change.
+async fn poll_mailbox_counts<Stream, Notify, NotifyFut>(
+    mut session: async_imap::Session<Stream>,
+    mailbox: &imap_utf7::ImapUtf7Str,
+    interval: std::time::Duration,
+    command_timeout: std::time::Duration,
+    mut notify: Notify,
+) -> Result<core::convert::Infallible, MonitorError>
+where
+    Stream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+    Notify: FnMut(crate::MailboxCounts) -> NotifyFut + Send,
+    NotifyFut: std::future::Future<Output = ()> + Send,
+{
+    let mut last_counts = crate::fetch_counts(&mut session, mailbox, command_timeout).await?;
+
+    notify(last_counts).await;
+
+    loop {
+        tokio::time::sleep(interval).await;
 
-        let counts = crate::fetch_counts(&mut session, mailbox).await?;
+        let counts = crate::fetch_counts(&mut session, mailbox, command_timeout).await?;
         if counts != last_counts {
             last_counts = counts;
             notify(counts).await;
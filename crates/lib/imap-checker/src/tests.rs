@@ -0,0 +1,210 @@
+use imap_test_server::{GREETING, serve};
+
+use super::*;
+
+/// Log in to the local port.
+async fn session(port: u16) -> async_imap::Session<tokio::net::TcpStream> {
+    let tcp_stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .unwrap();
+    let mut client = async_imap::Client::new(tcp_stream);
+    client.read_response().await.unwrap().unwrap();
+    client
+        .login("user", "secret")
+        .await
+        .map_err(|(error, _)| error)
+        .unwrap()
+}
+
+/// The exchange up to the first IDLE.
+const MONITOR_START: [(&str, &str); 6] = [
+    GREETING,
+    ("A0001 LOGIN \"user\" \"secret\"", "A0001 OK logged in\r\n"),
+    (
+        "A0002 CAPABILITY",
+        "* CAPABILITY IMAP4rev1 IDLE\r\nA0002 OK done\r\n",
+    ),
+    (
+        "A0003 SELECT \"INBOX\"",
+        "* 2 EXISTS\r\nA0003 OK [READ-WRITE] done\r\n",
+    ),
+    ("A0004 UID SEARCH UNSEEN", "* SEARCH 8\r\nA0004 OK done\r\n"),
+    ("A0005 IDLE", "+ idling\r\n"),
+];
+
+#[tokio::test]
+async fn heartbeat_without_response_fails() {
+    static SCRIPT: [(&str, &str); 8] = [
+        MONITOR_START[0],
+        MONITOR_START[1],
+        MONITOR_START[2],
+        MONITOR_START[3],
+        MONITOR_START[4],
+        MONITOR_START[5],
+        ("DONE", "A0005 OK idle done\r\n"),
+        ("A0006 NOOP", ""),
+    ];
+    let (port, server) = serve(&SCRIPT).await;
+
+    let mut updates = Vec::new();
+    let result = monitor_mailbox_counts(
+        session(port).await,
+        imap_utf7::ImapUtf7Str::new("INBOX").unwrap(),
+        WatchMode::Idle,
+        std::time::Duration::from_secs(300),
+        std::time::Duration::from_millis(100),
+        Some(std::time::Duration::from_millis(100)),
+        |counts| {
+            updates.push(counts);
+            std::future::ready(())
+        },
+    )
+    .await;
+
+    assert!(matches!(
+        result,
+        Err(MonitorError::Timeout {
+            command: "NOOP",
+            ..
+        })
+    ));
+    assert_eq!(
+        updates,
+        [MailboxCounts {
+            total: 2,
+            unread: 1
+        }]
+    );
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn idle_follows_the_reported_changes() {
+    static SCRIPT: [(&str, &str); 13] = [
+        MONITOR_START[0],
+        MONITOR_START[1],
+        MONITOR_START[2],
+        MONITOR_START[3],
+        MONITOR_START[4],
+        ("A0005 IDLE", "+ idling\r\n* 3 EXISTS\r\n* 1 RECENT\r\n"),
+        (
+            "DONE",
+            "* 2 FETCH (FLAGS (\\Seen))\r\nA0005 OK idle done\r\n",
+        ),
+        (
+            "A0006 FETCH 2:3 (UID FLAGS)",
+            "* 2 FETCH (UID 8 FLAGS (\\Seen))\r\n* 3 FETCH (UID 9 FLAGS ())\r\nA0006 OK done\r\n",
+        ),
+        ("A0007 IDLE", "+ idling\r\n* 1 EXPUNGE\r\n"),
+        ("DONE", "A0007 OK idle done\r\n"),
+        (
+            "A0008 UID SEARCH UNSEEN",
+            "* SEARCH 9\r\n* 3 EXISTS\r\nA0008 OK done\r\n",
+        ),
+        (
+            "A0009 FETCH 3 (UID FLAGS)",
+            "* 3 FETCH (UID 10 FLAGS ())\r\nA0009 OK done\r\n",
+        ),
+        ("A0010 IDLE", ""),
+    ];
+    let (port, server) = serve(&SCRIPT).await;
+
+    let mut updates = Vec::new();
+    let result = monitor_mailbox_counts(
+        session(port).await,
+        imap_utf7::ImapUtf7Str::new("INBOX").unwrap(),
+        WatchMode::Idle,
+        std::time::Duration::from_secs(300),
+        std::time::Duration::from_millis(100),
+        None,
+        |counts| {
+            updates.push(counts);
+            std::future::ready(())
+        },
+    )
+    .await;
+
+    assert!(matches!(
+        result,
+        Err(MonitorError::Timeout {
+            command: "IDLE",
+            ..
+        })
+    ));
+    assert_eq!(
+        updates,
+        [
+            MailboxCounts {
+                total: 2,
+                unread: 1
+            },
+            MailboxCounts {
+                total: 3,
+                unread: 1
+            },
+            MailboxCounts {
+                total: 3,
+                unread: 2
+            }
+        ]
+    );
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn polls_without_idle() {
+    static SCRIPT: [(&str, &str); 6] = [
+        GREETING,
+        ("A0001 LOGIN \"user\" \"secret\"", "A0001 OK logged in\r\n"),
+        (
+            "A0002 CAPABILITY",
+            "* CAPABILITY IMAP4rev1\r\nA0002 OK done\r\n",
+        ),
+        (
+            "A0003 STATUS \"INBOX\" (MESSAGES UNSEEN)",
+            "* STATUS INBOX (MESSAGES 2 UNSEEN 1)\r\nA0003 OK done\r\n",
+        ),
+        (
+            "A0004 STATUS \"INBOX\" (MESSAGES UNSEEN)",
+            "* STATUS INBOX (MESSAGES 3 UNSEEN 2)\r\nA0004 OK done\r\n",
+        ),
+        ("A0005 STATUS \"INBOX\" (MESSAGES UNSEEN)", ""),
+    ];
+    let (port, server) = serve(&SCRIPT).await;
+
+    let mut updates = Vec::new();
+    let result = monitor_mailbox_counts(
+        session(port).await,
+        imap_utf7::ImapUtf7Str::new("INBOX").unwrap(),
+        WatchMode::Auto {
+            poll_interval: std::time::Duration::from_millis(10),
+        },
+        std::time::Duration::from_secs(300),
+        std::time::Duration::from_millis(100),
+        None,
+        |counts| {
+            updates.push(counts);
+            std::future::ready(())
+        },
+    )
+    .await;
+
+    assert!(matches!(
+        result,
+        Err(MonitorError::FetchCounts(FetchCountsError::Timeout(_)))
+    ));
+    assert_eq!(
+        updates,
+        [
+            MailboxCounts {
+                total: 2,
+                unread: 1
+            },
+            MailboxCounts {
+                total: 3,
+                unread: 2
+            }
+        ]
+    );
+    server.await.unwrap();
+}
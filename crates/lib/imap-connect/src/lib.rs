@@ -1,11 +1,46 @@
 //! High-level IMAP connection utilities.
 
+mod tcp;
+
+pub use tcp::{IpFamily, race, resolve};
+
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+compile_error!("enable the `rustls` or the `native-tls` feature to pick a TLS backend");
+
+/// The TLS backend, the native one when enabled, as the rustls one is on by
+/// default.
+#[cfg(feature = "native-tls")]
+use imap_tls_native as backend;
+
+/// The TLS backend, the native one when enabled, as the rustls one is on by
+/// default.
+#[cfg(not(feature = "native-tls"))]
+use imap_tls_rustls as backend;
+
+pub use backend::TlsConnectError;
+
 /// The effective data stream type we use.
-pub type Stream = imap_tls_rustls::TlsStream;
+pub type Stream = imap_tls::Stream<backend::TlsStream>;
 
 /// The effective client type we use.
 pub type Client = async_imap::Client<Stream>;
 
+/// Obtain the TLS channel binding data for the stream, if available.
+pub fn channel_binding(stream: &Stream) -> Option<Vec<u8>> {
+    stream.tls().and_then(backend::tls_exporter_channel_binding)
+}
+
+/// The negotiated TLS protocol version and cipher suite, when the backend
+/// tells them.
+pub fn negotiated(stream: &Stream) -> Option<String> {
+    stream.tls().and_then(backend::negotiated)
+}
+
+/// The certificate the server presented over TLS.
+pub fn peer_certificate(stream: &Stream) -> Option<rustls_pki_types::CertificateDer<'static>> {
+    stream.tls().and_then(backend::peer_certificate)
+}
+
 /// IMAP connect params.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Params<'a> {
@@ -20,6 +55,48 @@ pub struct Params<'a> {
 
     /// TLS server name (SNI).
     pub tls_server_name: &'a str,
+
+    /// Client identity for mutual TLS.
+    pub tls_client_identity: Option<&'a imap_tls_core::ClientIdentity>,
+
+    /// How to verify the server certificate.
+    pub tls_trust: &'a imap_tls_core::ServerTrust,
+
+    /// Which TLS versions and application protocol to negotiate.
+    pub tls_policy: &'a imap_tls_core::TlsPolicy,
+
+    /// Allow plaintext connections to servers that are not on a loopback
+    /// address.
+    pub allow_remote_plaintext: bool,
+
+    /// The IP version to connect over, both when unset.
+    pub ip_family: Option<IpFamily>,
+
+    /// Proxy to connect through.
+    pub proxy: Option<&'a imap_proxy::Proxy>,
+
+    /// Command to talk to the server through, instead of connecting to it.
+    ///
+    /// The command is trusted to secure the connection, so the TLS and proxy
+    /// params are not used.
+    pub tunnel: Option<&'a str>,
+
+    /// Unix socket to talk to the server over, instead of connecting to it.
+    ///
+    /// The socket is local to the machine, so the connection is plaintext
+    /// and the TLS and proxy params are not used.
+    pub socket: Option<&'a std::path::Path>,
+
+    /// Time limit for opening the TCP connection, including the proxy
+    /// handshake.
+    pub connect_timeout: std::time::Duration,
+
+    /// Time limits for the TLS handshake, the greeting and the commands
+    /// before the client is handed over.
+    pub timeouts: imap_tls::Timeouts,
+
+    /// Where to trace the exchange with the server, not traced when unset.
+    pub trace: Option<&'a imap_trace::Config>,
 }
 
 /// Errors returned while connecting to an IMAP server.
@@ -29,39 +106,236 @@ pub enum Error {
     #[error("TCP connection error: {0}")]
     TcpConnect(#[source] std::io::Error),
 
+    /// Opening the TCP connection took longer than its time limit.
+    #[error("timed out connecting after {0:?}")]
+    ConnectTimeout(std::time::Duration),
+
+    /// Resolving the server host name failed.
+    #[error("DNS resolution error: {0}")]
+    Resolve(#[source] std::io::Error),
+
+    /// The server host name resolved to no address of the IP version.
+    #[error("no {0} address")]
+    NoAddress(IpFamily),
+
+    /// Connecting through the proxy failed.
+    #[error("proxy: {0}")]
+    Proxy(#[source] imap_proxy::Error),
+
     /// IMAP TLS connector error.
     #[error("IMAP TLS connector error: {0}")]
-    ImapTlsConnector(#[source] imap_tls_rustls::TlsConnectError),
+    ImapTlsConnector(#[source] TlsConnectError),
+
+    /// A plaintext connection to a server that is not on a loopback address
+    /// was refused.
+    #[error("refusing a plaintext connection to the non-loopback address {0}")]
+    RemotePlaintext(std::net::SocketAddr),
+
+    /// A plaintext connection through a proxy was refused, as the server
+    /// can not be told to be on a loopback address.
+    #[error("refusing a plaintext connection through a proxy")]
+    ProxiedPlaintext,
+
+    /// Spawning the tunnel command failed.
+    #[error("tunnel command: {0}")]
+    Tunnel(#[source] std::io::Error),
+
+    /// Connecting to the Unix socket failed.
+    #[error("Unix socket connection error: {0}")]
+    UnixConnect(#[source] std::io::Error),
 
     /// IMAP TLS connection error.
     #[error("IMAP TLS connection error: {0}")]
-    ImapTlsConnect(#[source] imap_tls::ConnectError<imap_tls_rustls::TlsConnectError>),
+    ImapTlsConnect(#[source] imap_tls::ConnectError<TlsConnectError>),
 }
 
-/// Connect to an IMAP server and produce an IMAP client.
-pub async fn connect(params: Params<'_>) -> Result<Client, Error> {
+/// Whether a plaintext connection to the address is allowed.
+///
+/// Only loopback addresses are allowed by default, as the credentials would
+/// otherwise cross the network in the clear.
+pub fn plaintext_allowed(peer_addr: std::net::SocketAddr, allow_remote_plaintext: bool) -> bool {
+    allow_remote_plaintext || peer_addr.ip().to_canonical().is_loopback()
+}
+
+/// Connect to an IMAP server and produce an IMAP client, along with what
+/// the server greeting told about the connection.
+pub async fn connect(params: Params<'_>) -> Result<(Client, imap_tls::Greeting), Error> {
+    let started = std::time::Instant::now();
     let Params {
         host,
         port,
         tls_mode,
         tls_server_name,
+        tls_client_identity,
+        tls_trust,
+        tls_policy,
+        allow_remote_plaintext: _,
+        ip_family,
+        proxy,
+        tunnel,
+        socket,
+        connect_timeout,
+        timeouts,
+        trace,
     } = params;
 
+    if let Some(command) = tunnel {
+        return connect_tunnel(command, timeouts, trace).await;
+    }
+    if let Some(path) = socket {
+        return connect_unix(path, connect_timeout, timeouts, trace).await;
+    }
+
     tracing::debug!(
         imap_host = %host,
         imap_port = port,
         imap_tls_mode = ?tls_mode,
         tls_server_name = %tls_server_name,
+        tls_client_auth = tls_client_identity.is_some(),
+        tls_pins = tls_trust.pins.len(),
+        tls_min_version = tls_policy.min_version.map(tracing::field::display),
+        imap_ip_family = ip_family.map(tracing::field::display),
+        proxy = proxy.map(tracing::field::display),
+        imap_trace = trace.is_some(),
         "connecting to an IMAP server"
     );
 
-    let tcp_stream = tokio::net::TcpStream::connect((host, port))
-        .await
-        .map_err(Error::TcpConnect)?;
-    let tls_connector = imap_tls_rustls::connector().map_err(Error::ImapTlsConnector)?;
-    let client = imap_tls::connect(tcp_stream, tls_server_name, tls_mode, tls_connector)
+    let tcp_stream = tokio::time::timeout(connect_timeout, async {
+        match proxy {
+            Some(proxy) => imap_proxy::connect(proxy, host, port)
+                .await
+                .map_err(Error::Proxy),
+            None => {
+                let addrs = resolve(host, port, ip_family)
+                    .await
+                    .map_err(Error::Resolve)?;
+                if let (Some(ip_family), true) = (ip_family, addrs.is_empty()) {
+                    return Err(Error::NoAddress(ip_family));
+                }
+                race(addrs).await.map_err(Error::TcpConnect)
+            }
+        }
+    })
+    .await
+    .map_err(|_| Error::ConnectTimeout(connect_timeout))??;
+    tcp::keepalive(&tcp_stream);
+
+    connected(start(tcp_stream, &params).await, started)
+}
+
+/// Secure the TCP connection to the IMAP server as the params ask, then
+/// read the greeting.
+///
+/// A plaintext connection is refused unless allowed, see
+/// [`plaintext_allowed`]. The tunnel and the Unix socket are not used.
+pub async fn start(
+    tcp_stream: tokio::net::TcpStream,
+    params: &Params<'_>,
+) -> Result<(Client, imap_tls::Greeting), Error> {
+    let trace = params.trace.map(imap_trace::Trace::open);
+
+    let client = if params.tls_mode == imap_tls::TlsMode::None {
+        if params.proxy.is_some() {
+            if !params.allow_remote_plaintext {
+                return Err(Error::ProxiedPlaintext);
+            }
+        } else {
+            let peer_addr = tcp_stream.peer_addr().map_err(Error::TcpConnect)?;
+            if !plaintext_allowed(peer_addr, params.allow_remote_plaintext) {
+                return Err(Error::RemotePlaintext(peer_addr));
+            }
+        }
+        imap_tls::connect_plaintext(tcp_stream, params.timeouts, trace).await
+    } else {
+        let tls_connector = backend::connector(
+            params.tls_client_identity,
+            params.tls_trust,
+            params.tls_policy,
+        )
+        .map_err(Error::ImapTlsConnector)?;
+        imap_tls::connect(
+            tcp_stream,
+            params.tls_server_name,
+            params.tls_mode,
+            tls_connector,
+            params.timeouts,
+            trace,
+        )
         .await
-        .map_err(Error::ImapTlsConnect)?;
+    };
 
-    Ok(client)
+    client.map_err(Error::ImapTlsConnect)
 }
+
+/// Talk to an IMAP server through the tunnel command and produce an IMAP
+/// client, along with what the server greeting told about the connection.
+pub async fn connect_tunnel(
+    command: &str,
+    timeouts: imap_tls::Timeouts,
+    trace: Option<&imap_trace::Config>,
+) -> Result<(Client, imap_tls::Greeting), Error> {
+    tracing::debug!(
+        imap_tunnel = %command,
+        imap_trace = trace.is_some(),
+        "connecting to an IMAP server through a tunnel"
+    );
+
+    let started = std::time::Instant::now();
+    let tunnel = imap_tunnel::spawn(command).map_err(Error::Tunnel)?;
+    let trace = trace.map(imap_trace::Trace::open);
+    let client = imap_tls::connect_tunnel(tunnel, timeouts, trace).await;
+    connected(client.map_err(Error::ImapTlsConnect), started)
+}
+
+/// Talk to an IMAP server over the Unix socket and produce an IMAP client,
+/// along with what the server greeting told about the connection.
+pub async fn connect_unix(
+    path: &std::path::Path,
+    connect_timeout: std::time::Duration,
+    timeouts: imap_tls::Timeouts,
+    trace: Option<&imap_trace::Config>,
+) -> Result<(Client, imap_tls::Greeting), Error> {
+    tracing::debug!(
+        imap_socket = %path.display(),
+        imap_trace = trace.is_some(),
+        "connecting to an IMAP server over a Unix socket"
+    );
+
+    let started = std::time::Instant::now();
+    #[cfg(unix)]
+    {
+        let unix_stream =
+            tokio::time::timeout(connect_timeout, tokio::net::UnixStream::connect(path))
+                .await
+                .map_err(|_| Error::ConnectTimeout(connect_timeout))?
+                .map_err(Error::UnixConnect)?;
+        let trace = trace.map(imap_trace::Trace::open);
+        let client = imap_tls::connect_unix(unix_stream, timeouts, trace).await;
+        connected(client.map_err(Error::ImapTlsConnect), started)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (connect_timeout, timeouts, started);
+        Err(Error::UnixConnect(std::io::ErrorKind::Unsupported.into()))
+    }
+}
+
+/// Log the established connection, along with how long it took since the
+/// connect started.
+fn connected(
+    client: Result<(Client, imap_tls::Greeting), Error>,
+    started: std::time::Instant,
+) -> Result<(Client, imap_tls::Greeting), Error> {
+    let (client, greeting) = client?;
+    tracing::debug!(
+        imap_preauth = greeting.preauth,
+        imap_greeting_capabilities = greeting.capabilities.is_some(),
+        imap_connect_ms = started.elapsed().as_millis(),
+        tls_resumed = client.get_ref().tls().map(backend::resumed),
+        "connected to an IMAP server"
+    );
+    Ok((client, greeting))
+}
+
+#[cfg(test)]
+mod tests;
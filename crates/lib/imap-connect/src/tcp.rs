@@ -0,0 +1,132 @@
+//! TCP connection with Happy Eyeballs (RFC 8305).
+
+use futures_util::StreamExt as _;
+
+/// How long to wait for an attempt before starting the next one.
+///
+/// The value recommended by RFC 8305.
+const CONNECTION_ATTEMPT_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// How long the connection stays idle before keepalive probes are sent.
+const KEEPALIVE_TIME: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long to wait between unanswered keepalive probes.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+const KEEPALIVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// The IP version to connect over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    /// IPv4 only.
+    V4,
+
+    /// IPv6 only.
+    V6,
+}
+
+impl IpFamily {
+    /// Whether the address belongs to the family.
+    fn contains(self, addr: &std::net::SocketAddr) -> bool {
+        match self {
+            Self::V4 => addr.is_ipv4(),
+            Self::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
+impl std::fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::V4 => "IPv4",
+            Self::V6 => "IPv6",
+        })
+    }
+}
+
+/// Resolve the host to the addresses to try, in order.
+///
+/// Only the addresses of the family are kept when it is set. Otherwise the
+/// families alternate, starting with the one the resolver prefers.
+pub async fn resolve(
+    host: &str,
+    port: u16,
+    ip_family: Option<IpFamily>,
+) -> std::io::Result<Vec<std::net::SocketAddr>> {
+    let addrs = tokio::net::lookup_host((host, port)).await?;
+    Ok(match ip_family {
+        Some(ip_family) => addrs.filter(|addr| ip_family.contains(addr)).collect(),
+        None => interleave(addrs.collect()),
+    })
+}
+
+/// Alternate the address families, keeping the order within each.
+pub(crate) fn interleave(addrs: Vec<std::net::SocketAddr>) -> Vec<std::net::SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return addrs;
+    };
+    let first_is_ipv6 = first.is_ipv6();
+    let (preferred, other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_ipv6);
+
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    let mut other = other.into_iter();
+    for addr in preferred {
+        ordered.push(addr);
+        ordered.extend(other.next());
+    }
+    ordered.extend(other);
+    ordered
+}
+
+/// Enable TCP keepalive, so that a dead peer is noticed while the
+/// connection idles.
+///
+/// Failing to enable it is only logged, as the connection still works.
+pub(crate) fn keepalive(tcp_stream: &tokio::net::TcpStream) {
+    let keepalive = socket2::TcpKeepalive::new().with_time(KEEPALIVE_TIME);
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    let keepalive = keepalive.with_interval(KEEPALIVE_INTERVAL);
+
+    if let Err(error) = socket2::SockRef::from(tcp_stream).set_tcp_keepalive(&keepalive) {
+        tracing::warn!(%error, "unable to enable TCP keepalive");
+    }
+}
+
+/// Connect to the first address that accepts the connection.
+///
+/// The attempts are started in order, each one once the previous one failed
+/// or has not succeeded within the attempt delay, and raced against each
+/// other. The error of the last failed attempt is returned when all fail.
+pub async fn race(addrs: Vec<std::net::SocketAddr>) -> std::io::Result<tokio::net::TcpStream> {
+    let mut pending = addrs.into_iter();
+    let mut attempts = futures_util::stream::FuturesUnordered::new();
+    let mut last_error = None;
+
+    loop {
+        if let Some(addr) = pending.next() {
+            tracing::trace!(imap_addr = %addr, "connecting over TCP");
+            attempts.push(async move { (addr, tokio::net::TcpStream::connect(addr).await) });
+        } else if attempts.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "no address to connect to")
+            }));
+        }
+
+        // Wait for an attempt to finish, or for the next attempt to be due.
+        tokio::select! {
+            Some((addr, result)) = attempts.next() => match result {
+                Ok(tcp_stream) => {
+                    tracing::debug!(imap_addr = %addr, "connected over TCP");
+                    return Ok(tcp_stream);
+                }
+                Err(error) => {
+                    tracing::debug!(imap_addr = %addr, %error, "TCP connection failed");
+                    last_error = Some(error);
+                }
+            },
+            () = tokio::time::sleep(CONNECTION_ATTEMPT_DELAY), if !pending.as_slice().is_empty() => {}
+            else => {}
+        }
+    }
+}
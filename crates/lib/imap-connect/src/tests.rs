@@ -0,0 +1,455 @@
+use std::sync::Arc;
+
+use rustls::pki_types::pem::PemObject as _;
+use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _};
+
+use super::*;
+
+/// The test CA certificate.
+const CA: &[u8] = include_bytes!("fixtures/ca.pem");
+
+/// A certificate for `imap.example.com` issued by the test CA.
+const SERVER_CERTIFICATE: &[u8] = include_bytes!("fixtures/server.pem");
+
+/// The private key of the server certificate.
+const SERVER_KEY: &[u8] = include_bytes!("fixtures/server.key");
+
+/// The connect time limit that the tests stay well within.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Time limits that the tests stay well within.
+const TIMEOUTS: imap_tls::Timeouts = imap_tls::Timeouts {
+    tls_handshake: TIMEOUT,
+    greeting: TIMEOUT,
+    command: TIMEOUT,
+};
+
+/// The default TLS policy.
+static TLS_POLICY: imap_tls_core::TlsPolicy = imap_tls_core::TlsPolicy {
+    min_version: None,
+    alpn: None,
+};
+
+/// Plaintext connect params for the address.
+fn plaintext_params<'a>(
+    host: &'a str,
+    port: u16,
+    tls_trust: &'a imap_tls_core::ServerTrust,
+) -> Params<'a> {
+    Params {
+        host,
+        port,
+        tls_mode: imap_tls::TlsMode::None,
+        tls_server_name: host,
+        tls_client_identity: None,
+        tls_trust,
+        tls_policy: &TLS_POLICY,
+        allow_remote_plaintext: false,
+        ip_family: None,
+        proxy: None,
+        tunnel: None,
+        socket: None,
+        connect_timeout: TIMEOUT,
+        timeouts: TIMEOUTS,
+        trace: None,
+    }
+}
+
+#[test]
+fn plaintext_allowed_for_loopback_only() {
+    let allowed = |addr: &str, allow_remote_plaintext| {
+        plaintext_allowed(addr.parse().unwrap(), allow_remote_plaintext)
+    };
+
+    assert!(allowed("127.0.0.1:143", false));
+    assert!(allowed("[::1]:143", false));
+    assert!(allowed("[::ffff:127.0.0.1]:143", false));
+    assert!(!allowed("192.0.2.1:143", false));
+    assert!(!allowed("[2001:db8::1]:143", false));
+    assert!(allowed("192.0.2.1:143", true));
+}
+
+#[tokio::test]
+async fn plaintext_connect_to_loopback() {
+    use tokio::io::AsyncWriteExt as _;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream.write_all(b"* OK ready\r\n").await.unwrap();
+        stream
+    });
+
+    let tls_trust = imap_tls_core::ServerTrust::default();
+    let (client, greeting) = connect(plaintext_params("127.0.0.1", port, &tls_trust))
+        .await
+        .unwrap();
+
+    assert!(!greeting.preauth);
+    let imap_tls::MaybeTlsStream::Plain(tcp_stream) = client.get_ref().get_ref() else {
+        panic!("the connection is not plaintext");
+    };
+    assert!(socket2::SockRef::from(tcp_stream).keepalive().unwrap());
+    assert_eq!(channel_binding(client.get_ref()), None);
+    drop(server.await.unwrap());
+}
+
+/// Trust settings with only the test CA.
+fn ca_trust() -> imap_tls_core::ServerTrust {
+    let mut trust = imap_tls_core::ServerTrust {
+        native_roots: false,
+        ..Default::default()
+    };
+    trust.add_roots_pem(CA).unwrap();
+    trust
+}
+
+/// The TLS acceptor of the IMAP server stand-in.
+fn tls_acceptor() -> tokio_rustls::TlsAcceptor {
+    let certificates = rustls::pki_types::CertificateDer::pem_slice_iter(SERVER_CERTIFICATE)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = rustls::pki_types::PrivateKeyDer::from_pem_slice(SERVER_KEY).unwrap();
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_no_client_auth()
+    .with_single_cert(certificates, key)
+    .unwrap();
+    tokio_rustls::TlsAcceptor::from(Arc::new(config))
+}
+
+/// Read a command line and check it.
+async fn expect_line<S>(stream: &mut tokio::io::BufReader<S>, expected: &str)
+where
+    S: tokio::io::AsyncRead + Unpin,
+{
+    let mut line = String::new();
+    stream.read_line(&mut line).await.unwrap();
+    assert_eq!(line, expected);
+}
+
+/// Serve an IMAP greeting on a local port, secured with TLS unless the
+/// mode is [`imap_tls::TlsMode::None`].
+async fn imap_server(tls_mode: imap_tls::TlsMode) -> (u16, tokio::task::JoinHandle<()>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        match tls_mode {
+            imap_tls::TlsMode::Implicit => {
+                let mut stream = tls_acceptor().accept(stream).await.unwrap();
+                stream
+                    .write_all(b"* OK [CAPABILITY IMAP4rev1 AUTH=PLAIN] ready\r\n")
+                    .await
+                    .unwrap();
+                stream.flush().await.unwrap();
+                let _ = stream.shutdown().await;
+            }
+            imap_tls::TlsMode::StartTls => {
+                let mut stream = tokio::io::BufReader::new(stream);
+                stream
+                    .write_all(b"* OK [CAPABILITY IMAP4rev1 STARTTLS] ready\r\n")
+                    .await
+                    .unwrap();
+                expect_line(&mut stream, "A0001 STARTTLS\r\n").await;
+                stream.write_all(b"A0001 OK begin TLS\r\n").await.unwrap();
+
+                let stream = tls_acceptor().accept(stream.into_inner()).await.unwrap();
+                let mut stream = tokio::io::BufReader::new(stream);
+                expect_line(&mut stream, "A0001 CAPABILITY\r\n").await;
+                stream
+                    .write_all(b"* CAPABILITY IMAP4rev1 AUTH=PLAIN\r\nA0001 OK done\r\n")
+                    .await
+                    .unwrap();
+                stream.flush().await.unwrap();
+                let _ = stream.shutdown().await;
+            }
+            imap_tls::TlsMode::None => {
+                let mut stream = stream;
+                stream.write_all(b"* OK ready\r\n").await.unwrap();
+            }
+        }
+    });
+    (port, server)
+}
+
+/// Run a SOCKS5 proxy stand-in on a local port, relaying a single
+/// connection to `imap.example.com` to the local server port.
+async fn socks5_proxy(server_port: u16) -> (u16, tokio::task::JoinHandle<()>) {
+    use tokio::io::AsyncReadExt as _;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let proxy = tokio::spawn(async move {
+        let (mut client, _) = listener.accept().await.unwrap();
+
+        let mut greeting = [0; 3];
+        client.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(greeting, [5, 1, 0]);
+        client.write_all(&[5, 0]).await.unwrap();
+
+        let mut request = [0; 5];
+        client.read_exact(&mut request).await.unwrap();
+        assert_eq!(request, [5, 1, 0, 3, 16]);
+        let mut host = [0; 16 + 2];
+        client.read_exact(&mut host).await.unwrap();
+        assert_eq!(&host[..16], b"imap.example.com");
+
+        let mut server = tokio::net::TcpStream::connect(("127.0.0.1", server_port))
+            .await
+            .unwrap();
+        client
+            .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0])
+            .await
+            .unwrap();
+        let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+    });
+    (port, proxy)
+}
+
+/// Connect params for `imap.example.com` through the proxy.
+fn proxied_params<'a>(
+    tls_mode: imap_tls::TlsMode,
+    tls_trust: &'a imap_tls_core::ServerTrust,
+    proxy: &'a imap_proxy::Proxy,
+) -> Params<'a> {
+    Params {
+        host: "imap.example.com",
+        port: 993,
+        tls_mode,
+        tls_server_name: "imap.example.com",
+        tls_client_identity: None,
+        tls_trust,
+        tls_policy: &TLS_POLICY,
+        allow_remote_plaintext: false,
+        ip_family: None,
+        proxy: Some(proxy),
+        tunnel: None,
+        socket: None,
+        connect_timeout: TIMEOUT,
+        timeouts: TIMEOUTS,
+        trace: None,
+    }
+}
+
+/// The SOCKS5 proxy stand-in on the local port.
+fn socks5(port: u16) -> imap_proxy::Proxy {
+    imap_proxy::Proxy::Socks5 {
+        host: "127.0.0.1".to_owned(),
+        port,
+        remote_dns: true,
+        login: None,
+    }
+}
+
+#[tokio::test]
+async fn tls_through_a_proxy() {
+    for tls_mode in [imap_tls::TlsMode::Implicit, imap_tls::TlsMode::StartTls] {
+        let (server_port, server) = imap_server(tls_mode).await;
+        let (proxy_port, proxy) = socks5_proxy(server_port).await;
+
+        let tls_trust = ca_trust();
+        let proxy_config = socks5(proxy_port);
+        let (client, greeting) = connect(proxied_params(tls_mode, &tls_trust, &proxy_config))
+            .await
+            .unwrap();
+
+        assert!(greeting.capabilities.unwrap().has_auth("PLAIN"));
+        // The native backend has no channel binding to offer.
+        assert_eq!(
+            channel_binding(client.get_ref()).is_some(),
+            cfg!(not(feature = "native-tls"))
+        );
+        drop(client);
+        server.await.unwrap();
+        proxy.await.unwrap();
+    }
+}
+
+#[cfg(not(feature = "native-tls"))]
+#[tokio::test]
+async fn tls13_with_a_verify_name() {
+    let (server_port, server) = imap_server(imap_tls::TlsMode::Implicit).await;
+    let (proxy_port, proxy) = socks5_proxy(server_port).await;
+
+    let tls_trust = imap_tls_core::ServerTrust {
+        verify_name: Some("imap.example.com".to_owned()),
+        ..ca_trust()
+    };
+    let tls_policy = imap_tls_core::TlsPolicy {
+        min_version: Some(imap_tls_core::TlsVersion::V1_3),
+        alpn: Some("imap".to_owned()),
+    };
+    let proxy_config = socks5(proxy_port);
+    let params = Params {
+        tls_server_name: "mail.internal",
+        tls_policy: &tls_policy,
+        ..proxied_params(imap_tls::TlsMode::Implicit, &tls_trust, &proxy_config)
+    };
+    let (client, _) = connect(params).await.unwrap();
+
+    let (_, connection) = client.get_ref().tls().unwrap().get_ref();
+    assert_eq!(
+        connection.protocol_version(),
+        Some(rustls::ProtocolVersion::TLSv1_3)
+    );
+    drop(client);
+    server.await.unwrap();
+    proxy.await.unwrap();
+}
+
+#[tokio::test]
+async fn plaintext_through_a_proxy_is_refused() {
+    let (server_port, server) = imap_server(imap_tls::TlsMode::None).await;
+    let (proxy_port, proxy) = socks5_proxy(server_port).await;
+
+    let tls_trust = ca_trust();
+    let proxy_config = socks5(proxy_port);
+    let params = proxied_params(imap_tls::TlsMode::None, &tls_trust, &proxy_config);
+    let result = connect(params.clone()).await;
+    assert!(matches!(result, Err(Error::ProxiedPlaintext)));
+    server.await.unwrap();
+    proxy.await.unwrap();
+
+    let (server_port, server) = imap_server(imap_tls::TlsMode::None).await;
+    let (proxy_port, proxy) = socks5_proxy(server_port).await;
+    let proxy_config = socks5(proxy_port);
+    let params = Params {
+        allow_remote_plaintext: true,
+        ..proxied_params(imap_tls::TlsMode::None, &tls_trust, &proxy_config)
+    };
+    let (client, greeting) = connect(params).await.unwrap();
+    assert!(!greeting.preauth);
+    drop(client);
+    server.await.unwrap();
+    proxy.await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn tunnel_with_preauth() {
+    let tls_trust = imap_tls_core::ServerTrust::default();
+    let params = Params {
+        tunnel: Some("printf '* PREAUTH [CAPABILITY IMAP4rev1] ready\\r\\n'; cat >/dev/null"),
+        ..plaintext_params("192.0.2.1", 143, &tls_trust)
+    };
+    let (client, greeting) = connect(params).await.unwrap();
+
+    assert!(greeting.preauth);
+    assert!(matches!(
+        client.get_ref().get_ref(),
+        imap_tls::MaybeTlsStream::Tunnel(_)
+    ));
+    assert_eq!(channel_binding(client.get_ref()), None);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn tunnel_command_failure() {
+    let tls_trust = imap_tls_core::ServerTrust::default();
+    let params = Params {
+        tunnel: Some("exit 1"),
+        ..plaintext_params("192.0.2.1", 143, &tls_trust)
+    };
+    let result = connect(params).await;
+    assert!(matches!(
+        result,
+        Err(Error::ImapTlsConnect(
+            imap_tls::ConnectError::MissingGreeting
+        ))
+    ));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket_allows_plaintext() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("imap.sock");
+    let listener = tokio::net::UnixListener::bind(&path).unwrap();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream
+            .write_all(b"* OK [CAPABILITY IMAP4rev1 AUTH=PLAIN] ready\r\n")
+            .await
+            .unwrap();
+        let mut rest = Vec::new();
+        let _ = tokio::io::AsyncReadExt::read_to_end(&mut stream, &mut rest).await;
+    });
+
+    let tls_trust = imap_tls_core::ServerTrust::default();
+    let params = Params {
+        socket: Some(&path),
+        ..plaintext_params("192.0.2.1", 143, &tls_trust)
+    };
+    let (client, greeting) = connect(params).await.unwrap();
+
+    assert!(!greeting.preauth);
+    assert!(matches!(
+        client.get_ref().get_ref(),
+        imap_tls::MaybeTlsStream::Unix(_)
+    ));
+    assert_eq!(channel_binding(client.get_ref()), None);
+    drop(client);
+    server.await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket_missing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("imap.sock");
+    let tls_trust = imap_tls_core::ServerTrust::default();
+    let params = Params {
+        socket: Some(&path),
+        ..plaintext_params("192.0.2.1", 143, &tls_trust)
+    };
+    let result = connect(params).await;
+    assert!(matches!(result, Err(Error::UnixConnect(_))));
+}
+
+#[test]
+fn interleave_address_families() {
+    let addrs = [
+        "[2001:db8::1]:993",
+        "[2001:db8::2]:993",
+        "[2001:db8::3]:993",
+        "192.0.2.1:993",
+    ]
+    .map(|addr| addr.parse().unwrap());
+    let ordered = [
+        "[2001:db8::1]:993",
+        "192.0.2.1:993",
+        "[2001:db8::2]:993",
+        "[2001:db8::3]:993",
+    ]
+    .map(|addr| addr.parse().unwrap());
+
+    assert_eq!(tcp::interleave(addrs.to_vec()), ordered);
+}
+
+#[tokio::test]
+async fn race_falls_back_to_the_next_address() {
+    let refused = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let refused_addr = refused.local_addr().unwrap();
+    drop(refused);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let tcp_stream = race(vec![refused_addr, addr]).await.unwrap();
+
+    assert_eq!(tcp_stream.peer_addr().unwrap(), addr);
+    assert!(race(vec![refused_addr]).await.is_err());
+}
+
+#[tokio::test]
+async fn resolve_the_ip_family() {
+    let v4 = resolve("127.0.0.1", 143, Some(IpFamily::V4)).await.unwrap();
+    assert_eq!(v4, ["127.0.0.1:143".parse().unwrap()]);
+
+    let v6 = resolve("127.0.0.1", 143, Some(IpFamily::V6)).await.unwrap();
+    assert!(v6.is_empty());
+}
@@ -0,0 +1,72 @@
+//! The HTTP `CONNECT` handshake.
+
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+
+use crate::{Error, Login};
+
+/// The limit on the size of the response head.
+const MAX_RESPONSE_HEAD: usize = 8192;
+
+/// Ask the proxy to open a tunnel to the server.
+pub(crate) async fn handshake<S>(
+    stream: &mut S,
+    host: &str,
+    port: u16,
+    login: Option<&Login>,
+) -> Result<(), Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    use base64::Engine as _;
+
+    let authority = if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    };
+
+    let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+    if let Some(Login { username, password }) = login {
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+        request.push_str(&format!("Proxy-Authorization: Basic {credentials}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    let head = read_head(stream).await?;
+    let status_line = head.lines().next().ok_or(Error::HttpProtocol)?;
+    let mut parts = status_line.splitn(3, ' ');
+    let (Some(version), Some(status)) = (parts.next(), parts.next()) else {
+        return Err(Error::HttpProtocol);
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(Error::HttpProtocol);
+    }
+    if !status.starts_with('2') || status.len() != 3 {
+        let reason = parts.next().unwrap_or_default();
+        return Err(Error::HttpStatus(
+            format!("{status} {reason}").trim_end().to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Read the response head, up to the empty line.
+///
+/// Reads a byte at a time, so nothing the server sends through the tunnel is
+/// consumed.
+async fn read_head<S>(stream: &mut S) -> Result<String, Error>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() == MAX_RESPONSE_HEAD {
+            return Err(Error::HttpProtocol);
+        }
+        head.push(stream.read_u8().await?);
+    }
+    String::from_utf8(head).map_err(|_| Error::HttpProtocol)
+}
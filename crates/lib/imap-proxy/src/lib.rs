@@ -0,0 +1,148 @@
+//! Connecting to the IMAP server through a proxy.
+
+mod http;
+mod socks5;
+
+/// A proxy to tunnel the IMAP connection through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Proxy {
+    /// A SOCKS5 proxy, see [RFC 1928](https://www.rfc-editor.org/rfc/rfc1928).
+    Socks5 {
+        /// Hostname or IP address of the proxy.
+        host: String,
+
+        /// Port of the proxy.
+        port: u16,
+
+        /// Let the proxy resolve the server host name.
+        ///
+        /// Keeps the lookups from leaking around the proxy, as Tor requires.
+        remote_dns: bool,
+
+        /// Username/password authentication, see
+        /// [RFC 1929](https://www.rfc-editor.org/rfc/rfc1929).
+        login: Option<Login>,
+    },
+
+    /// An HTTP proxy tunneling with `CONNECT`.
+    Http {
+        /// Hostname or IP address of the proxy.
+        host: String,
+
+        /// Port of the proxy.
+        port: u16,
+
+        /// Basic authentication.
+        login: Option<Login>,
+    },
+}
+
+impl std::fmt::Display for Proxy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Socks5 { host, port, .. } => write!(f, "SOCKS5 proxy {host}:{port}"),
+            Self::Http { host, port, .. } => write!(f, "HTTP proxy {host}:{port}"),
+        }
+    }
+}
+
+/// Proxy credentials.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Login {
+    /// The username.
+    pub username: String,
+
+    /// The password.
+    pub password: String,
+}
+
+impl std::fmt::Debug for Login {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Login")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Errors returned while connecting through a proxy.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Connecting to the proxy failed.
+    #[error("connecting to the proxy: {0}")]
+    Connect(#[source] std::io::Error),
+
+    /// Network I/O error while talking to the proxy.
+    #[error("proxy I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The server host name did not resolve.
+    #[error("no address found for {0}")]
+    Resolve(String),
+
+    /// A value does not fit in the SOCKS5 request.
+    #[error("{0} is too long for SOCKS5")]
+    Socks5TooLong(&'static str),
+
+    /// A value is empty, which the SOCKS5 request cannot carry.
+    #[error("{0} must not be empty for SOCKS5")]
+    Socks5Empty(&'static str),
+
+    /// The SOCKS5 proxy accepts none of the offered authentication methods.
+    #[error("SOCKS5 proxy accepts none of the offered authentication methods")]
+    Socks5NoAcceptableMethod,
+
+    /// The SOCKS5 proxy rejected the credentials.
+    #[error("SOCKS5 proxy rejected the credentials")]
+    Socks5Auth,
+
+    /// The SOCKS5 proxy did not connect to the server.
+    #[error("SOCKS5 proxy did not connect: {}", socks5::describe_reply(*.0))]
+    Socks5Reply(u8),
+
+    /// The SOCKS5 proxy sent an invalid response.
+    #[error("invalid SOCKS5 proxy response")]
+    Socks5Protocol,
+
+    /// The HTTP proxy did not connect to the server.
+    #[error("HTTP proxy did not connect: {0}")]
+    HttpStatus(String),
+
+    /// The HTTP proxy sent an invalid response.
+    #[error("invalid HTTP proxy response")]
+    HttpProtocol,
+}
+
+/// Open a TCP stream to the server through the proxy.
+pub async fn connect(proxy: &Proxy, host: &str, port: u16) -> Result<tokio::net::TcpStream, Error> {
+    let (proxy_host, proxy_port) = match proxy {
+        Proxy::Socks5 { host, port, .. } | Proxy::Http { host, port, .. } => (host, *port),
+    };
+    let mut stream = tokio::net::TcpStream::connect((proxy_host.as_str(), proxy_port))
+        .await
+        .map_err(Error::Connect)?;
+
+    match proxy {
+        Proxy::Socks5 {
+            remote_dns, login, ..
+        } => {
+            let target = if *remote_dns {
+                socks5::Target::Domain(host)
+            } else {
+                let addr = tokio::net::lookup_host((host, port))
+                    .await?
+                    .next()
+                    .ok_or_else(|| Error::Resolve(host.to_owned()))?;
+                socks5::Target::Ip(addr.ip())
+            };
+            socks5::handshake(&mut stream, target, port, login.as_ref()).await?;
+        }
+        Proxy::Http { login, .. } => {
+            http::handshake(&mut stream, host, port, login.as_ref()).await?;
+        }
+    }
+
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests;
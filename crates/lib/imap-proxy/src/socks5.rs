@@ -0,0 +1,159 @@
+//! The SOCKS5 client handshake.
+
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+
+use crate::{Error, Login};
+
+/// The protocol version.
+const VERSION: u8 = 0x05;
+
+/// The username/password subnegotiation version.
+const LOGIN_VERSION: u8 = 0x01;
+
+/// The method without authentication.
+const METHOD_NONE: u8 = 0x00;
+
+/// The username/password method.
+const METHOD_LOGIN: u8 = 0x02;
+
+/// The method reply when no offered method is acceptable.
+const METHOD_UNACCEPTABLE: u8 = 0xff;
+
+/// The `CONNECT` command.
+const CONNECT: u8 = 0x01;
+
+/// The IPv4 address type.
+const ATYP_IPV4: u8 = 0x01;
+
+/// The domain name address type.
+const ATYP_DOMAIN: u8 = 0x03;
+
+/// The IPv6 address type.
+const ATYP_IPV6: u8 = 0x04;
+
+/// Where the proxy should connect to.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Target<'a> {
+    /// A host name for the proxy to resolve.
+    Domain(&'a str),
+
+    /// A resolved address.
+    Ip(std::net::IpAddr),
+}
+
+/// Ask the proxy to connect to the target.
+pub(crate) async fn handshake<S>(
+    stream: &mut S,
+    target: Target<'_>,
+    port: u16,
+    login: Option<&Login>,
+) -> Result<(), Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let greeting: &[u8] = match login {
+        Some(_) => &[VERSION, 2, METHOD_NONE, METHOD_LOGIN],
+        None => &[VERSION, 1, METHOD_NONE],
+    };
+    stream.write_all(greeting).await?;
+
+    let mut reply = [0; 2];
+    stream.read_exact(&mut reply).await?;
+    match reply {
+        [VERSION, METHOD_NONE] => {}
+        [VERSION, METHOD_LOGIN] => match login {
+            Some(login) => authenticate(stream, login).await?,
+            None => return Err(Error::Socks5Protocol),
+        },
+        [VERSION, METHOD_UNACCEPTABLE] => return Err(Error::Socks5NoAcceptableMethod),
+        _ => return Err(Error::Socks5Protocol),
+    }
+
+    let mut request = vec![VERSION, CONNECT, 0x00];
+    match target {
+        Target::Domain(host) => {
+            request.push(ATYP_DOMAIN);
+            request.push(length(host.as_bytes(), "host name")?);
+            request.extend_from_slice(host.as_bytes());
+        }
+        Target::Ip(std::net::IpAddr::V4(ip)) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Target::Ip(std::net::IpAddr::V6(ip)) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&ip.octets());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply).await?;
+    let [VERSION, status, _, atyp] = reply else {
+        return Err(Error::Socks5Protocol);
+    };
+    if status != 0x00 {
+        return Err(Error::Socks5Reply(status));
+    }
+
+    // Skip the bound address and port.
+    let address_len = match atyp {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => usize::from(stream.read_u8().await?),
+        _ => return Err(Error::Socks5Protocol),
+    };
+    let mut bound = vec![0; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(())
+}
+
+/// Run the username/password subnegotiation.
+async fn authenticate<S>(stream: &mut S, login: &Login) -> Result<(), Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let username = login.username.as_bytes();
+    let password = login.password.as_bytes();
+
+    let mut request = vec![LOGIN_VERSION, length(username, "username")?];
+    request.extend_from_slice(username);
+    request.push(length(password, "password")?);
+    request.extend_from_slice(password);
+    stream.write_all(&request).await?;
+
+    let mut reply = [0; 2];
+    stream.read_exact(&mut reply).await?;
+    match reply {
+        [LOGIN_VERSION, 0x00] => Ok(()),
+        [LOGIN_VERSION, _] => Err(Error::Socks5Auth),
+        _ => Err(Error::Socks5Protocol),
+    }
+}
+
+/// The length prefix of a value.
+fn length(value: &[u8], what: &'static str) -> Result<u8, Error> {
+    match u8::try_from(value.len()) {
+        Ok(0) => Err(Error::Socks5Empty(what)),
+        Ok(len) => Ok(len),
+        Err(_) => Err(Error::Socks5TooLong(what)),
+    }
+}
+
+/// Describe the reply code of a failed request.
+pub(crate) fn describe_reply(code: u8) -> String {
+    let description = match code {
+        0x01 => "general SOCKS server failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        code => return format!("reply code {code:#04x}"),
+    };
+    description.to_owned()
+}
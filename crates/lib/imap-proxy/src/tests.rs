@@ -0,0 +1,225 @@
+use tokio::io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _};
+
+use super::*;
+
+/// Run a proxy stand-in on a local port, handling a single connection.
+async fn stand_in<F, Fut>(handle: F) -> (u16, tokio::task::JoinHandle<()>)
+where
+    F: FnOnce(tokio::net::TcpStream) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let task = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        handle(stream).await;
+    });
+    (port, task)
+}
+
+/// Read exactly `len` bytes.
+async fn read(stream: &mut tokio::net::TcpStream, len: usize) -> Vec<u8> {
+    let mut buf = vec![0; len];
+    stream.read_exact(&mut buf).await.unwrap();
+    buf
+}
+
+/// Read the first line the server sends through the tunnel.
+async fn first_line(stream: tokio::net::TcpStream) -> String {
+    let mut line = String::new();
+    tokio::io::BufReader::new(stream)
+        .read_line(&mut line)
+        .await
+        .unwrap();
+    line
+}
+
+/// A SOCKS5 proxy on the local port.
+fn socks5(port: u16, remote_dns: bool, login: Option<Login>) -> Proxy {
+    Proxy::Socks5 {
+        host: "127.0.0.1".to_owned(),
+        port,
+        remote_dns,
+        login,
+    }
+}
+
+/// The test credentials.
+fn login() -> Login {
+    Login {
+        username: "user".to_owned(),
+        password: "secret".to_owned(),
+    }
+}
+
+#[tokio::test]
+async fn socks5_remote_dns_with_login() {
+    let (port, proxy) = stand_in(|mut stream| async move {
+        assert_eq!(read(&mut stream, 4).await, [5, 2, 0, 2]);
+        stream.write_all(&[5, 2]).await.unwrap();
+        assert_eq!(read(&mut stream, 13).await, b"\x01\x04user\x06secret");
+        stream.write_all(&[1, 0]).await.unwrap();
+
+        let mut request = vec![5, 1, 0, 3, 16];
+        request.extend_from_slice(b"imap.example.com");
+        request.extend_from_slice(&993u16.to_be_bytes());
+        assert_eq!(read(&mut stream, request.len()).await, request);
+        stream
+            .write_all(b"\x05\x00\x00\x01\x00\x00\x00\x00\x00\x00* OK ready\r\n")
+            .await
+            .unwrap();
+    })
+    .await;
+
+    let stream = connect(&socks5(port, true, Some(login())), "imap.example.com", 993)
+        .await
+        .unwrap();
+
+    assert_eq!(first_line(stream).await, "* OK ready\r\n");
+    proxy.await.unwrap();
+}
+
+#[tokio::test]
+async fn socks5_local_dns_sends_the_address() {
+    let (port, proxy) = stand_in(|mut stream| async move {
+        assert_eq!(read(&mut stream, 3).await, [5, 1, 0]);
+        stream.write_all(&[5, 0]).await.unwrap();
+        assert_eq!(
+            read(&mut stream, 10).await,
+            [5, 1, 0, 1, 127, 0, 0, 1, 0, 143]
+        );
+        stream
+            .write_all(b"\x05\x00\x00\x03\x05proxy\x00\x00* OK ready\r\n")
+            .await
+            .unwrap();
+    })
+    .await;
+
+    let stream = connect(&socks5(port, false, None), "127.0.0.1", 143)
+        .await
+        .unwrap();
+
+    assert_eq!(first_line(stream).await, "* OK ready\r\n");
+    proxy.await.unwrap();
+}
+
+#[tokio::test]
+async fn socks5_reports_refusals() {
+    let (port, proxy) = stand_in(|mut stream| async move {
+        read(&mut stream, 4).await;
+        stream.write_all(&[5, 2]).await.unwrap();
+        read(&mut stream, 13).await;
+        stream.write_all(&[1, 1]).await.unwrap();
+    })
+    .await;
+    let result = connect(&socks5(port, true, Some(login())), "imap.example.com", 993).await;
+    assert!(matches!(result, Err(Error::Socks5Auth)));
+    proxy.await.unwrap();
+
+    let (port, proxy) = stand_in(|mut stream| async move {
+        read(&mut stream, 3).await;
+        stream.write_all(&[5, 0]).await.unwrap();
+        read(&mut stream, 23).await;
+        stream
+            .write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0])
+            .await
+            .unwrap();
+    })
+    .await;
+    let error = connect(&socks5(port, true, None), "imap.example.com", 993)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Socks5Reply(5)));
+    assert_eq!(
+        error.to_string(),
+        "SOCKS5 proxy did not connect: connection refused"
+    );
+    proxy.await.unwrap();
+}
+
+#[tokio::test]
+async fn socks5_rejects_an_empty_password() {
+    let (port, proxy) = stand_in(|mut stream| async move {
+        read(&mut stream, 4).await;
+        stream.write_all(&[5, 2]).await.unwrap();
+    })
+    .await;
+    let login = Login {
+        password: String::new(),
+        ..login()
+    };
+
+    let error = connect(&socks5(port, true, Some(login)), "imap.example.com", 993)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, Error::Socks5Empty("password")));
+    assert_eq!(error.to_string(), "password must not be empty for SOCKS5");
+    proxy.await.unwrap();
+}
+
+#[tokio::test]
+async fn http_connect_with_login() {
+    let (port, proxy) = stand_in(|stream| async move {
+        let mut stream = tokio::io::BufReader::new(stream);
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            stream.read_line(&mut head).await.unwrap();
+        }
+        assert_eq!(
+            head,
+            "CONNECT imap.example.com:993 HTTP/1.1\r\n\
+             Host: imap.example.com:993\r\n\
+             Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n\r\n"
+        );
+        stream
+            .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n* OK ready\r\n")
+            .await
+            .unwrap();
+    })
+    .await;
+
+    let proxy_config = Proxy::Http {
+        host: "127.0.0.1".to_owned(),
+        port,
+        login: Some(login()),
+    };
+    let stream = connect(&proxy_config, "imap.example.com", 993)
+        .await
+        .unwrap();
+
+    assert_eq!(first_line(stream).await, "* OK ready\r\n");
+    proxy.await.unwrap();
+}
+
+#[tokio::test]
+async fn http_connect_reports_the_status() {
+    let (port, proxy) = stand_in(|stream| async move {
+        let mut stream = tokio::io::BufReader::new(stream);
+        let mut line = String::new();
+        while line != "\r\n" {
+            line.clear();
+            stream.read_line(&mut line).await.unwrap();
+        }
+        stream
+            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+            .await
+            .unwrap();
+    })
+    .await;
+
+    let proxy_config = Proxy::Http {
+        host: "127.0.0.1".to_owned(),
+        port,
+        login: None,
+    };
+    let error = connect(&proxy_config, "imap.example.com", 993)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "HTTP proxy did not connect: 407 Proxy Authentication Required"
+    );
+    proxy.await.unwrap();
+}
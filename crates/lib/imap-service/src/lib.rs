@@ -3,13 +3,30 @@
 /// Errors returned while monitoring a mailbox.
 #[derive(Debug, thiserror::Error)]
 pub enum MonitorMailboxError {
-    /// IMAP session error.
-    #[error("IMAP session error: {0}")]
-    Session(#[source] imap_session::Error),
+    /// Connecting to the server failed.
+    #[error("IMAP connect error: {0}")]
+    Connect(#[source] ConnectError),
 
     /// IMAP monitor error.
     #[error("IMAP monitor error: {0}")]
     Monitor(#[source] imap_checker::MonitorError),
+
+    /// Resolving the credentials again failed.
+    #[error("unable to resolve the credentials: {0}")]
+    Credentials(#[source] config_bringup::ResolveCredentialsError),
+}
+
+impl MonitorMailboxError {
+    /// Whether retrying is pointless until the user fixes the credentials
+    /// or the config.
+    pub fn needs_attention(&self) -> bool {
+        match self {
+            Self::Connect(error) => error.needs_attention(),
+            Self::Monitor(imap_checker::MonitorError::IdleNotSupported) => true,
+            Self::Monitor(_) => false,
+            Self::Credentials(error) => !error.is_keyring_locked(),
+        }
+    }
 }
 
 /// Connect and monitor a mailbox based on provided settings.
@@ -25,47 +42,221 @@ where
         server,
         mailbox,
         idle_timeout,
+        heartbeat,
+        watch,
     } = mailbox;
 
-    let session = connect_to_server(server.as_ref())
+    let (session, _) = connect_to_server(server.as_ref())
         .await
-        .map_err(MonitorMailboxError::Session)?;
+        .map_err(MonitorMailboxError::Connect)?;
 
-    imap_checker::monitor_mailbox_counts(session, mailbox, *idle_timeout, notify)
-        .await
-        .map_err(MonitorMailboxError::Monitor)
+    imap_checker::monitor_mailbox_counts(
+        session,
+        mailbox,
+        *watch,
+        *idle_timeout,
+        server.timeouts.command,
+        *heartbeat,
+        notify,
+    )
+    .await
+    .map_err(MonitorMailboxError::Monitor)
 }
 
-/// Connect to a server based on provided settings.
-pub async fn connect_to_server(
-    server: &config_bringup::Server,
-) -> Result<imap_session::Session, imap_session::Error> {
+/// Errors returned while connecting to a server.
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectError {
+    /// Obtaining the OAuth 2 access token failed.
+    #[error("OAuth 2 access token: {0}")]
+    AccessToken(
+        #[source] oauth2_session::GetTokenError<config_bringup::token_storage::TokenStorage>,
+    ),
+
+    /// IMAP session error.
+    #[error("IMAP session error: {0}")]
+    Session(#[source] imap_session::Error),
+}
+
+impl ConnectError {
+    /// Whether retrying is pointless until the user fixes the credentials
+    /// or the config.
+    pub fn needs_attention(&self) -> bool {
+        match self {
+            Self::AccessToken(oauth2_session::GetTokenError::StorageLoad(
+                oauth2_session::token_storage_core::LoadError::NoData(_),
+            )) => true,
+            Self::AccessToken(oauth2_session::GetTokenError::ExchangeRefreshToken(
+                oauth2::RequestTokenError::ServerResponse(response),
+            )) => matches!(
+                response.error(),
+                oauth2::basic::BasicErrorResponseType::InvalidGrant
+                    | oauth2::basic::BasicErrorResponseType::InvalidClient
+                    | oauth2::basic::BasicErrorResponseType::UnauthorizedClient
+            ),
+            Self::AccessToken(_) => false,
+            Self::Session(imap_session::Error::Auth(error)) => error.kind().is_permanent(),
+            Self::Session(
+                imap_session::Error::Capabilities(_)
+                | imap_session::Error::Compress(_)
+                | imap_session::Error::Id(_)
+                | imap_session::Error::Timeout { .. },
+            ) => false,
+            Self::Session(imap_session::Error::Connect(error)) => matches!(
+                error,
+                imap_connect::Error::RemotePlaintext(_)
+                    | imap_connect::Error::ProxiedPlaintext
+                    | imap_connect::Error::ImapTlsConnect(
+                        imap_tls::ConnectError::StartTlsUnsupported
+                            | imap_tls::ConnectError::PreAuthBeforeStartTls
+                    )
+            ),
+        }
+    }
+}
+
+/// The params to connect to the server with.
+pub fn connect_params(server: &config_bringup::Server) -> imap_connect::Params<'_> {
     let config_bringup::Server {
         server_name: _,
         host,
         port,
         tls_mode,
         tls_server_name,
-        auth,
+        tls_client_identity,
+        tls_trust,
+        tls_policy,
+        allow_remote_plaintext,
+        ip_family,
+        proxy,
+        tunnel,
+        socket,
+        connect_timeout,
+        timeouts,
+        client_id: _,
+        compress: _,
+        trace,
+        auth: _,
+        auth_source: _,
     } = server;
 
-    let connect = imap_connect::Params {
+    imap_connect::Params {
         host,
         port: *port,
         tls_mode: *tls_mode,
         tls_server_name,
-    };
+        tls_client_identity: tls_client_identity.as_ref(),
+        tls_trust,
+        tls_policy,
+        allow_remote_plaintext: *allow_remote_plaintext,
+        ip_family: *ip_family,
+        proxy: proxy.as_ref(),
+        tunnel: tunnel.as_deref(),
+        socket: socket.as_deref(),
+        connect_timeout: *connect_timeout,
+        timeouts: *timeouts,
+        trace: trace.as_ref(),
+    }
+}
 
-    let auth = match auth {
-        config_bringup::ServerAuth::Login { username, password } => {
-            imap_auth::Params::Login { username, password }
-        }
-        config_bringup::ServerAuth::OAuth2Credentials { user, access_token } => {
-            imap_auth::Params::OAuth2 { user, access_token }
+/// Connect to a server based on provided settings, along with what the
+/// server told about itself when the client identified itself.
+pub async fn connect_to_server(
+    server: &config_bringup::Server,
+) -> Result<(imap_session::Session, Option<imap_session::ServerInfo>), ConnectError> {
+    let connect = connect_params(server);
+    let config_bringup::Server {
+        host,
+        port,
+        client_id,
+        compress,
+        auth,
+        ..
+    } = server;
+
+    let auth = auth.read().await;
+    let auth = match &*auth {
+        config_bringup::ServerAuth::Login {
+            username,
+            password,
+            mechanism,
+        } => imap_auth::Params::Login {
+            username,
+            password,
+            mechanism: *mechanism,
+        },
+        config_bringup::ServerAuth::OAuth2Credentials {
+            user,
+            access_token,
+            mechanism,
+        } => imap_auth::Params::OAuth2 {
+            user,
+            access_token,
+            mechanism: *mechanism,
+            host,
+            port: *port,
+        },
+        config_bringup::ServerAuth::OAuth2Session {
+            user,
+            mechanism,
+            session,
+        } => {
+            let mut session = session.lock().await;
+            let oauth2 = |access_token| imap_auth::Params::OAuth2 {
+                user,
+                access_token,
+                mechanism: *mechanism,
+                host,
+                port: *port,
+            };
+
+            let access_token = session
+                .get_access_token()
+                .await
+                .map_err(ConnectError::AccessToken)?;
+            let result = imap_session::establish(imap_session::Params {
+                connect: connect.clone(),
+                auth: oauth2(&access_token),
+                id: client_id.as_ref(),
+                compress: *compress,
+            })
+            .await;
+
+            // The server may reject a token that is revoked before its expiry,
+            // so refresh it once before giving up.
+            let Err(imap_session::Error::Auth(error)) = &result else {
+                return result.map_err(ConnectError::Session);
+            };
+            if error.kind() != imap_auth::FailureKind::AuthenticationFailed {
+                return result.map_err(ConnectError::Session);
+            }
+
+            tracing::info!(message = "access token rejected, refreshing it", %error);
+            let access_token = session
+                .refresh_access_token()
+                .await
+                .map_err(ConnectError::AccessToken)?;
+            return imap_session::establish(imap_session::Params {
+                connect,
+                auth: oauth2(&access_token),
+                id: client_id.as_ref(),
+                compress: *compress,
+            })
+            .await
+            .map_err(ConnectError::Session);
         }
+        config_bringup::ServerAuth::External { authzid } => imap_auth::Params::External {
+            authzid: authzid.as_deref(),
+        },
     };
 
-    let session = imap_session::Params { connect, auth };
+    let session = imap_session::Params {
+        connect,
+        auth,
+        id: client_id.as_ref(),
+        compress: *compress,
+    };
 
-    imap_session::establish(session).await
+    imap_session::establish(session)
+        .await
+        .map_err(ConnectError::Session)
 }
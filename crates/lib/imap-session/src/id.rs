@@ -0,0 +1,94 @@
+//! Client and server identification via `ID` (RFC 2971).
+
+use crate::Error;
+
+/// The client identification sent with `ID`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientId {
+    /// The client name.
+    pub name: String,
+
+    /// The client version.
+    pub version: String,
+}
+
+impl Default for ClientId {
+    fn default() -> Self {
+        Self {
+            name: "mail-notifier".to_owned(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+        }
+    }
+}
+
+/// What the server told about itself in its `ID` response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerInfo {
+    /// The fields by lowercased name, such as `name`, `version` and
+    /// `vendor`.
+    pub fields: std::collections::BTreeMap<String, String>,
+}
+
+impl ServerInfo {
+    /// The server software name.
+    pub fn name(&self) -> Option<&str> {
+        self.field("name")
+    }
+
+    /// The server software version.
+    pub fn version(&self) -> Option<&str> {
+        self.field("version")
+    }
+
+    /// The server software vendor.
+    pub fn vendor(&self) -> Option<&str> {
+        self.field("vendor")
+    }
+
+    /// The value of the field.
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+impl std::fmt::Display for ServerInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name().unwrap_or("unnamed server"))?;
+        if let Some(version) = self.version() {
+            write!(f, " {version}")?;
+        }
+        if let Some(vendor) = self.vendor() {
+            write!(f, " by {vendor}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Send the client identification and return what the server told about
+/// itself.
+pub(crate) async fn identify<S>(
+    session: &mut async_imap::Session<S>,
+    id: &ClientId,
+    timeout: std::time::Duration,
+) -> Result<ServerInfo, Error>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+{
+    let identification = [
+        ("name", Some(id.name.as_str())),
+        ("version", Some(id.version.as_str())),
+    ];
+    let fields = crate::limit("ID", timeout, session.id(identification))
+        .await?
+        .map_err(Error::Id)?
+        .unwrap_or_default();
+    let server_info = ServerInfo {
+        fields: fields
+            .into_iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value))
+            .collect(),
+    };
+
+    tracing::debug!(imap_server_info = %server_info, "identified to the IMAP server");
+    Ok(server_info)
+}
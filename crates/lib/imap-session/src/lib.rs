@@ -1,5 +1,8 @@
 //! High-level IMAP session utilities.
 
+mod id;
+
+pub use id::{ClientId, ServerInfo};
 pub use imap_auth::Session;
 
 /// IMAP session params.
@@ -10,6 +13,14 @@ pub struct Params<'a> {
 
     /// Auth params.
     pub auth: imap_auth::Params<'a>,
+
+    /// Client identification to send after auth, when the server supports
+    /// `ID`.
+    pub id: Option<&'a ClientId>,
+
+    /// Whether to compress the session, when the server supports
+    /// `COMPRESS=DEFLATE`.
+    pub compress: bool,
 }
 
 /// Errors returned while establishing a session.
@@ -22,17 +33,107 @@ pub enum Error {
     /// IMAP auth error.
     #[error("auth: {0}")]
     Auth(#[source] imap_auth::Error),
+
+    /// Querying the capabilities after auth failed.
+    #[error("capabilities: {0}")]
+    Capabilities(#[source] async_imap::error::Error),
+
+    /// The server rejected the compression.
+    #[error("COMPRESS: {0}")]
+    Compress(#[source] async_imap::error::Error),
+
+    /// The server rejected the client identification.
+    #[error("ID: {0}")]
+    Id(#[source] async_imap::error::Error),
+
+    /// The server did not answer a command within the time limit.
+    #[error("timed out waiting for {command} after {after:?}")]
+    Timeout {
+        /// The command that timed out.
+        command: &'static str,
+
+        /// The time limit.
+        after: std::time::Duration,
+    },
 }
 
 /// Connect and login to establish an IMAP session.
-pub async fn establish(params: Params<'_>) -> Result<Session, Error> {
-    let Params { connect, auth } = params;
+///
+/// Also negotiates the extensions asked for, see [`negotiate`].
+pub async fn establish(params: Params<'_>) -> Result<(Session, Option<ServerInfo>), Error> {
+    let Params {
+        connect,
+        auth,
+        id,
+        compress,
+    } = params;
+    let command_timeout = connect.timeouts.command;
 
-    let client = imap_connect::connect(connect)
+    let (client, greeting) = imap_connect::connect(connect)
         .await
         .map_err(Error::Connect)?;
 
-    let session = imap_auth::auth(client, auth).await.map_err(Error::Auth)?;
+    let mut session = imap_auth::auth(client, greeting, auth)
+        .await
+        .map_err(Error::Auth)?;
+
+    let server_info = negotiate(&mut session, id, compress, command_timeout).await?;
 
-    Ok(session)
+    Ok((session, server_info))
 }
+
+/// Negotiate the extensions the server supports after auth: compress the
+/// session and identify the client, returning what the server told about
+/// itself.
+///
+/// Some servers refuse to select a mailbox until the client identifies
+/// itself.
+pub async fn negotiate<S>(
+    session: &mut async_imap::Session<imap_tls::Stream<S>>,
+    id: Option<&ClientId>,
+    compress: bool,
+    timeout: std::time::Duration,
+) -> Result<Option<ServerInfo>, Error>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+{
+    if id.is_none() && !compress {
+        return Ok(None);
+    }
+
+    let capabilities = limit("CAPABILITY", timeout, session.capabilities())
+        .await?
+        .map_err(Error::Capabilities)?;
+    let capabilities = imap_capabilities::Capabilities::from(&capabilities);
+
+    if compress && capabilities.has("COMPRESS=DEFLATE") && !session.get_ref().deflating() {
+        limit(
+            "COMPRESS",
+            timeout,
+            session.run_command_and_check_ok("COMPRESS DEFLATE"),
+        )
+        .await?
+        .map_err(Error::Compress)?;
+        session.get_mut().start_deflate();
+        tracing::debug!("compressing the IMAP session");
+    }
+
+    match id {
+        Some(id) if capabilities.has("ID") => Ok(Some(id::identify(session, id, timeout).await?)),
+        _ => Ok(None),
+    }
+}
+
+/// Run the command within the time limit.
+async fn limit<T>(
+    command: &'static str,
+    after: std::time::Duration,
+    future: impl Future<Output = T>,
+) -> Result<T, Error> {
+    tokio::time::timeout(after, future)
+        .await
+        .map_err(|_| Error::Timeout { command, after })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,129 @@
+use imap_test_server::{GREETING, Step};
+
+use super::*;
+
+/// The time limit for the commands.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The stream the tests talk over.
+type Stream = imap_tls::Stream<tokio::net::TcpStream>;
+
+/// Serve a scripted exchange on a local port.
+///
+/// The exchange is deflated once the server accepted `COMPRESS DEFLATE`.
+async fn serve(script: &'static [Step]) -> (u16, imap_test_server::Server) {
+    imap_test_server::serve_over(
+        script,
+        |stream| Stream::new(imap_tls::MaybeTlsStream::Plain(stream)),
+        |command, stream| {
+            if command.ends_with("COMPRESS DEFLATE") {
+                stream.start_deflate();
+            }
+        },
+    )
+    .await
+}
+
+/// Log in to the local port.
+async fn session(port: u16) -> async_imap::Session<Stream> {
+    let tcp_stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .unwrap();
+    let stream = Stream::new(imap_tls::MaybeTlsStream::Plain(tcp_stream));
+    let mut client = async_imap::Client::new(stream);
+    client.read_response().await.unwrap().unwrap();
+    client
+        .login("user", "secret")
+        .await
+        .map_err(|(error, _)| error)
+        .unwrap()
+}
+
+/// The client identification the tests send.
+fn client_id() -> ClientId {
+    ClientId {
+        name: "mail-notifier".to_owned(),
+        version: "1.0".to_owned(),
+    }
+}
+
+#[tokio::test]
+async fn identify_reads_the_server_info() {
+    static SCRIPT: [(&str, &str); 4] = [
+        GREETING,
+        ("A0001 LOGIN \"user\" \"secret\"", "A0001 OK logged in\r\n"),
+        (
+            "A0002 CAPABILITY",
+            "* CAPABILITY IMAP4rev1 ID\r\nA0002 OK done\r\n",
+        ),
+        (
+            "A0003 ID (\"name\" \"mail-notifier\" \"version\" \"1.0\")",
+            "* ID (\"Name\" \"Dovecot\" \"vendor\" \"Dovecot Oy\")\r\nA0003 OK done\r\n",
+        ),
+    ];
+    let (port, server) = serve(&SCRIPT).await;
+
+    let server_info = negotiate(&mut session(port).await, Some(&client_id()), false, TIMEOUT)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(server_info.name(), Some("Dovecot"));
+    assert_eq!(server_info.version(), None);
+    assert_eq!(server_info.to_string(), "Dovecot by Dovecot Oy");
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn identify_skips_servers_without_id() {
+    static SCRIPT: [(&str, &str); 3] = [
+        GREETING,
+        ("A0001 LOGIN \"user\" \"secret\"", "A0001 OK logged in\r\n"),
+        (
+            "A0002 CAPABILITY",
+            "* CAPABILITY IMAP4rev1 IDLE\r\nA0002 OK done\r\n",
+        ),
+    ];
+    let (port, server) = serve(&SCRIPT).await;
+
+    let server_info = negotiate(&mut session(port).await, Some(&client_id()), false, TIMEOUT)
+        .await
+        .unwrap();
+
+    assert_eq!(server_info, None);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn compressed_session_round_trip() {
+    static SCRIPT: [(&str, &str); 6] = [
+        GREETING,
+        ("A0001 LOGIN \"user\" \"secret\"", "A0001 OK logged in\r\n"),
+        (
+            "A0002 CAPABILITY",
+            "* CAPABILITY IMAP4rev1 ID COMPRESS=DEFLATE\r\nA0002 OK done\r\n",
+        ),
+        ("A0003 COMPRESS DEFLATE", "A0003 OK deflating\r\n"),
+        (
+            "A0004 ID (\"name\" \"mail-notifier\" \"version\" \"1.0\")",
+            "* ID (\"name\" \"Dovecot\")\r\nA0004 OK done\r\n",
+        ),
+        (
+            "A0005 STATUS \"INBOX\" (MESSAGES UNSEEN)",
+            "* STATUS INBOX (MESSAGES 2 UNSEEN 1)\r\nA0005 OK done\r\n",
+        ),
+    ];
+    let (port, server) = serve(&SCRIPT).await;
+
+    let mut session = session(port).await;
+    let server_info = negotiate(&mut session, Some(&client_id()), true, TIMEOUT)
+        .await
+        .unwrap();
+    assert!(session.get_ref().deflating());
+    assert_eq!(server_info.unwrap().name(), Some("Dovecot"));
+
+    let mailbox = session.status("INBOX", "(MESSAGES UNSEEN)").await.unwrap();
+    assert_eq!((mailbox.exists, mailbox.unseen), (2, Some(1)));
+    drop(session);
+    server.await.unwrap();
+}
@@ -0,0 +1,91 @@
+//! A scripted IMAP server on a local port, for the tests of the crates
+//! talking to servers.
+
+use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _};
+
+/// A step of the script: the command line to read, none when empty, then
+/// the reply to send, none when empty.
+pub type Step = (&'static str, &'static str);
+
+/// The usual greeting, as the first step.
+pub const GREETING: Step = ("", "* OK ready\r\n");
+
+/// The server task, returning the command lines it read.
+pub type Server = tokio::task::JoinHandle<Vec<String>>;
+
+/// Serve a scripted exchange on a local port.
+///
+/// The server reads and replies as scripted, asserting the commands are
+/// the scripted ones, then keeps the connection open until the client
+/// closes it.
+pub async fn serve(script: &'static [Step]) -> (u16, Server) {
+    serve_over(script, |stream| stream, |_, _| {}).await
+}
+
+/// Serve a scripted exchange on a local port, over a wrapped connection.
+///
+/// The hook is called with each command once replied to, as to start
+/// compressing the connection.
+pub async fn serve_over<Stream>(
+    script: &'static [Step],
+    wrap: impl FnOnce(tokio::net::TcpStream) -> Stream + Send + 'static,
+    mut replied: impl FnMut(&str, &mut Stream) + Send + 'static,
+) -> (u16, Server)
+where
+    Stream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        exchange(script, wrap(stream), &mut replied).await
+    });
+    (port, server)
+}
+
+/// Serve a scripted exchange on each connection to a local port in turn,
+/// as for a client connecting again.
+pub async fn serve_each(scripts: &'static [&'static [Step]]) -> (u16, Server) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let mut commands = Vec::new();
+        for script in scripts {
+            let (stream, _) = listener.accept().await.unwrap();
+            commands.extend(exchange(script, stream, &mut |_, _| {}).await);
+        }
+        commands
+    });
+    (port, server)
+}
+
+/// Read and reply as scripted over the connection, then wait for the
+/// client to close it, returning the command lines read.
+async fn exchange<Stream>(
+    script: &[Step],
+    stream: Stream,
+    replied: &mut impl FnMut(&str, &mut Stream),
+) -> Vec<String>
+where
+    Stream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let mut stream = tokio::io::BufReader::new(stream);
+    let mut commands = Vec::new();
+    for (command, reply) in script {
+        if !command.is_empty() {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            assert_eq!(line.trim_end(), *command);
+            commands.push(line.trim_end().to_owned());
+        }
+        if !reply.is_empty() {
+            stream.write_all(reply.as_bytes()).await.unwrap();
+            stream.flush().await.unwrap();
+        }
+        replied(command, stream.get_mut());
+    }
+
+    let mut rest = Vec::new();
+    let _ = stream.read_until(b'\0', &mut rest).await;
+    commands
+}
@@ -0,0 +1,151 @@
+//! Reading the certificate details shown to the user.
+
+use rustls_pki_types::{CertificateDer, UnixTime};
+
+/// The details of a server certificate shown to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    /// The subject distinguished name, e.g. `C=US, O=Example, CN=example.com`.
+    pub subject: String,
+
+    /// The issuer distinguished name.
+    pub issuer: String,
+
+    /// The end of the validity period.
+    pub not_after: UnixTime,
+}
+
+impl CertificateInfo {
+    /// Read the details of the certificate, none when it is malformed.
+    pub fn read(certificate: &CertificateDer<'_>) -> Option<Self> {
+        let certificate = webpki::EndEntityCert::try_from(certificate).ok()?;
+
+        // webpki only tells the end of the validity period when rejecting
+        // the certificate as expired, which it checks before anything else.
+        let end_of_time = UnixTime::since_unix_epoch(std::time::Duration::from_secs(u64::MAX));
+        let expired = certificate.verify_for_usage(
+            &[],
+            &[],
+            &[],
+            end_of_time,
+            webpki::KeyUsage::server_auth(),
+            None,
+            None,
+        );
+        let Err(webpki::Error::CertExpired { not_after, .. }) = expired else {
+            return None;
+        };
+
+        Some(Self {
+            subject: name(certificate.subject())?,
+            issuer: name(certificate.issuer())?,
+            not_after,
+        })
+    }
+}
+
+/// Render the content of a distinguished name, see
+/// [RFC 4514](https://www.rfc-editor.org/rfc/rfc4514).
+///
+/// The attributes are in the encoded order rather than the reverse one of
+/// RFC 4514, as most tools show them.
+fn name(mut content: &[u8]) -> Option<String> {
+    let mut parts = Vec::new();
+    while !content.is_empty() {
+        let mut attributes = next(&mut content, SET)?;
+        while !attributes.is_empty() {
+            let mut attribute = next(&mut attributes, SEQUENCE)?;
+            let oid = next(&mut attribute, OBJECT_IDENTIFIER)?;
+            let (&tag, _) = attribute.split_first()?;
+            let value = next(&mut attribute, tag)?;
+            let key = match attribute_name(oid) {
+                Some(key) => key.to_owned(),
+                None => object_identifier(oid)?,
+            };
+            parts.push(format!("{key}={}", string(tag, value)));
+        }
+    }
+    Some(parts.join(", "))
+}
+
+/// The DER tag of a `SEQUENCE`.
+const SEQUENCE: u8 = 0x30;
+
+/// The DER tag of a `SET`.
+const SET: u8 = 0x31;
+
+/// The DER tag of an `OBJECT IDENTIFIER`.
+const OBJECT_IDENTIFIER: u8 = 0x06;
+
+/// The DER tag of a `BMPString`.
+const BMP_STRING: u8 = 0x1e;
+
+/// Read the next DER value, which must have the tag, returning its content.
+fn next<'a>(input: &mut &'a [u8], tag: u8) -> Option<&'a [u8]> {
+    let (&actual, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = match first {
+        0..=0x7f => (usize::from(first), rest),
+        0x81 => {
+            let (&len, rest) = rest.split_first()?;
+            (usize::from(len), rest)
+        }
+        0x82 => {
+            let (bytes, rest) = rest.split_first_chunk()?;
+            (usize::from(u16::from_be_bytes(*bytes)), rest)
+        }
+        _ => return None,
+    };
+    let (content, rest) = rest.split_at_checked(len)?;
+    *input = rest;
+    (actual == tag).then_some(content)
+}
+
+/// The short name of a well-known attribute type, by its encoded object
+/// identifier.
+fn attribute_name(oid: &[u8]) -> Option<&'static str> {
+    Some(match oid {
+        [0x55, 0x04, 0x03] => "CN",
+        [0x55, 0x04, 0x06] => "C",
+        [0x55, 0x04, 0x07] => "L",
+        [0x55, 0x04, 0x08] => "ST",
+        [0x55, 0x04, 0x0a] => "O",
+        [0x55, 0x04, 0x0b] => "OU",
+        [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01] => "emailAddress",
+        _ => return None,
+    })
+}
+
+/// Render an object identifier in the dotted form.
+fn object_identifier(content: &[u8]) -> Option<String> {
+    let (&first, rest) = content.split_first()?;
+    let mut arcs = vec![u64::from(first / 40).min(2)];
+    arcs.push(u64::from(first) - arcs[0] * 40);
+
+    let mut arc = 0u64;
+    for &byte in rest {
+        arc = arc.checked_mul(128)? | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            arcs.push(arc);
+            arc = 0;
+        }
+    }
+
+    let arcs = arcs.iter().map(u64::to_string).collect::<Vec<_>>();
+    Some(arcs.join("."))
+}
+
+/// Decode a directory string attribute value.
+fn string(tag: u8, content: &[u8]) -> String {
+    match tag {
+        BMP_STRING => {
+            let units = content
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        _ => String::from_utf8_lossy(content).into_owned(),
+    }
+}
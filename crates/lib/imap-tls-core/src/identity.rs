@@ -0,0 +1,65 @@
+//! Client identity for mutual TLS.
+
+/// A client certificate chain and private key for mutual TLS.
+#[derive(PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// The certificate chain, leaf first.
+    pub certificate_chain: Vec<rustls_pki_types::CertificateDer<'static>>,
+
+    /// The private key for the leaf certificate.
+    pub private_key: rustls_pki_types::PrivateKeyDer<'static>,
+}
+
+/// Errors returned while loading a client identity.
+#[derive(Debug, thiserror::Error)]
+pub enum ClientIdentityError {
+    /// The PEM data could not be parsed.
+    #[error("invalid PEM data: {0}")]
+    Pem(#[from] rustls_pki_types::pem::Error),
+
+    /// The PEM data has no certificates.
+    #[error("no certificates found")]
+    NoCertificates,
+}
+
+impl ClientIdentity {
+    /// Load the identity from PEM data.
+    ///
+    /// The certificate chain and the private key may come from the same
+    /// bundle.
+    pub fn from_pem(
+        certificate_chain: &[u8],
+        private_key: &[u8],
+    ) -> Result<Self, ClientIdentityError> {
+        use rustls_pki_types::pem::PemObject as _;
+
+        let certificate_chain = rustls_pki_types::CertificateDer::pem_slice_iter(certificate_chain)
+            .collect::<Result<Vec<_>, _>>()?;
+        if certificate_chain.is_empty() {
+            return Err(ClientIdentityError::NoCertificates);
+        }
+        let private_key = rustls_pki_types::PrivateKeyDer::from_pem_slice(private_key)?;
+
+        Ok(Self {
+            certificate_chain,
+            private_key,
+        })
+    }
+}
+
+impl Clone for ClientIdentity {
+    fn clone(&self) -> Self {
+        Self {
+            certificate_chain: self.certificate_chain.clone(),
+            private_key: self.private_key.clone_key(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field("certificates", &self.certificate_chain.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -1,4 +1,14 @@
-//! Core TLS connector trait for IMAP clients.
+//! Core TLS connector trait and settings for IMAP clients.
+
+mod certificate;
+mod identity;
+mod policy;
+mod trust;
+
+pub use certificate::CertificateInfo;
+pub use identity::{ClientIdentity, ClientIdentityError};
+pub use policy::{TlsPolicy, TlsVersion};
+pub use trust::{Pin, ServerTrust, TrustError, matches_name};
 
 /// Connector for upgrading a TCP stream to a secured IMAP stream.
 pub trait TlsConnector {
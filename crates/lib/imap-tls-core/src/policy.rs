@@ -0,0 +1,30 @@
+//! TLS protocol settings.
+
+/// Which TLS protocol versions and application protocol to negotiate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsPolicy {
+    /// The oldest TLS version to accept, the backend default when unset.
+    pub min_version: Option<TlsVersion>,
+
+    /// The application protocol to offer through ALPN, none when unset.
+    pub alpn: Option<String>,
+}
+
+/// A TLS protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    /// TLS 1.2.
+    V1_2,
+
+    /// TLS 1.3.
+    V1_3,
+}
+
+impl std::fmt::Display for TlsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::V1_2 => "TLS 1.2",
+            Self::V1_3 => "TLS 1.3",
+        })
+    }
+}
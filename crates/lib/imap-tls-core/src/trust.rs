@@ -0,0 +1,129 @@
+//! Server certificate trust settings.
+
+use rustls_pki_types::CertificateDer;
+
+/// How to verify the server certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerTrust {
+    /// Whether to trust the system root certificates.
+    pub native_roots: bool,
+
+    /// Extra CA certificates to trust.
+    pub extra_roots: Vec<CertificateDer<'static>>,
+
+    /// Fingerprints the server certificate must match one of.
+    ///
+    /// Without any trusted roots, a matching pin is all it takes to trust
+    /// the certificate.
+    pub pins: Vec<Pin>,
+
+    /// The name to verify the certificate for, instead of the TLS server
+    /// name (SNI).
+    pub verify_name: Option<String>,
+}
+
+impl Default for ServerTrust {
+    fn default() -> Self {
+        Self {
+            native_roots: true,
+            extra_roots: Vec::new(),
+            pins: Vec::new(),
+            verify_name: None,
+        }
+    }
+}
+
+impl ServerTrust {
+    /// Trust the CA certificates from PEM data.
+    pub fn add_roots_pem(&mut self, pem: &[u8]) -> Result<(), TrustError> {
+        use rustls_pki_types::pem::PemObject as _;
+
+        let certificates = CertificateDer::pem_slice_iter(pem).collect::<Result<Vec<_>, _>>()?;
+        if certificates.is_empty() {
+            return Err(TrustError::NoCertificates);
+        }
+        self.extra_roots.extend(certificates);
+        Ok(())
+    }
+}
+
+/// A SHA-256 fingerprint pinning the server certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pin {
+    /// Fingerprint of the DER encoded subject public key info.
+    ///
+    /// Survives certificate renewals that keep the key.
+    SpkiSha256([u8; 32]),
+
+    /// Fingerprint of the DER encoded certificate.
+    CertificateSha256([u8; 32]),
+}
+
+impl Pin {
+    /// Parse a fingerprint in hex, optionally colon separated, or in base64.
+    pub fn parse_fingerprint(fingerprint: &str) -> Result<[u8; 32], TrustError> {
+        use base64::Engine as _;
+
+        let invalid = || TrustError::Fingerprint(fingerprint.to_owned());
+
+        let hex = fingerprint.replace(':', "");
+        if hex.len() == 64 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            let mut output = [0; 32];
+            for (byte, digits) in output.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+                let digits = std::str::from_utf8(digits).map_err(|_| invalid())?;
+                *byte = u8::from_str_radix(digits, 16).map_err(|_| invalid())?;
+            }
+            return Ok(output);
+        }
+
+        base64::engine::general_purpose::STANDARD
+            .decode(fingerprint)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(invalid)
+    }
+
+    /// Whether the certificate matches the pin.
+    pub fn matches(&self, certificate: &CertificateDer<'_>) -> bool {
+        match self {
+            Self::SpkiSha256(fingerprint) => webpki::EndEntityCert::try_from(certificate)
+                .is_ok_and(|certificate| {
+                    sha256(&certificate.subject_public_key_info()) == *fingerprint
+                }),
+            Self::CertificateSha256(fingerprint) => sha256(certificate) == *fingerprint,
+        }
+    }
+}
+
+/// Whether the certificate is valid for the DNS name or IP address.
+pub fn matches_name(certificate: &CertificateDer<'_>, name: &str) -> bool {
+    let Ok(name) = rustls_pki_types::ServerName::try_from(name) else {
+        return false;
+    };
+    webpki::EndEntityCert::try_from(certificate)
+        .is_ok_and(|certificate| certificate.verify_is_valid_for_subject_name(&name).is_ok())
+}
+
+/// Compute the SHA-256 digest.
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let digest = ring::digest::digest(&ring::digest::SHA256, data);
+    let mut output = [0; 32];
+    output.copy_from_slice(digest.as_ref());
+    output
+}
+
+/// Errors returned while loading the trust settings.
+#[derive(Debug, thiserror::Error)]
+pub enum TrustError {
+    /// The PEM data could not be parsed.
+    #[error("invalid PEM data: {0}")]
+    Pem(#[from] rustls_pki_types::pem::Error),
+
+    /// The PEM data has no certificates.
+    #[error("no certificates found")]
+    NoCertificates,
+
+    /// The fingerprint is not a SHA-256 digest in hex or base64.
+    #[error("invalid SHA-256 fingerprint '{0}'")]
+    Fingerprint(String),
+}
@@ -0,0 +1,219 @@
+//! TLS connector helpers for IMAP clients, backed by the platform TLS
+//! library.
+
+pub use imap_tls_core::{
+    ClientIdentity, ClientIdentityError, Pin, ServerTrust, TlsPolicy, TlsVersion, TrustError,
+};
+
+/// Native TLS connector wrapper that implements the IMAP TLS connector
+/// trait.
+#[derive(Clone)]
+pub struct NativeTlsConnector {
+    /// The platform connector.
+    inner: tokio_native_tls::TlsConnector,
+
+    /// The pins the server certificate must match one of.
+    pins: Vec<Pin>,
+
+    /// The name to verify the server certificate for, instead of the TLS
+    /// server name.
+    verify_name: Option<String>,
+}
+
+impl imap_tls_core::TlsConnector for NativeTlsConnector {
+    type Stream = TlsStream;
+    type Error = TlsConnectError;
+
+    async fn connect<'a>(
+        &'a self,
+        tls_server_name: &'a str,
+        tcp_stream: tokio::net::TcpStream,
+    ) -> Result<Self::Stream, Self::Error> {
+        let tls_stream = self.inner.connect(tls_server_name, tcp_stream).await?;
+        tracing::debug!(
+            tls_alpn =
+                tls_stream
+                    .get_ref()
+                    .negotiated_alpn()?
+                    .map(|protocol| tracing::field::display(
+                        String::from_utf8_lossy(&protocol).into_owned()
+                    )),
+            "negotiated TLS"
+        );
+        if self.pins.is_empty() && self.verify_name.is_none() {
+            return Ok(tls_stream);
+        }
+
+        // The platform library has no hook into its verification, so the
+        // pins and the name are checked once the handshake is done.
+        let certificate = tls_stream
+            .get_ref()
+            .peer_certificate()?
+            .ok_or(TlsConnectError::PinMismatch)?
+            .to_der()?;
+        let certificate = rustls_pki_types::CertificateDer::from(certificate);
+        if !self.pins.is_empty() && !self.pins.iter().any(|pin| pin.matches(&certificate)) {
+            return Err(TlsConnectError::PinMismatch);
+        }
+        if let Some(name) = &self.verify_name
+            && !imap_tls_core::matches_name(&certificate, name)
+        {
+            return Err(TlsConnectError::NameMismatch(name.clone()));
+        }
+        Ok(tls_stream)
+    }
+}
+
+/// TLS stream type used for IMAP connections.
+pub type TlsStream = tokio_native_tls::TlsStream<tokio::net::TcpStream>;
+
+/// Errors returned while preparing or establishing a TLS connection.
+#[derive(Debug, thiserror::Error)]
+pub enum TlsConnectError {
+    /// An extra CA certificate was rejected.
+    #[error("invalid CA certificate: {0}")]
+    CaCertificate(#[source] native_tls::Error),
+
+    /// Neither root certificates nor pins are trusted.
+    #[error("no trusted root certificates or pins")]
+    NoTrustAnchors,
+
+    /// The client private key is not in PKCS #8 form, the only one the
+    /// platform library loads.
+    #[error("the client private key must be in PKCS #8 form")]
+    UnsupportedClientKey,
+
+    /// The client certificate or key was rejected.
+    #[error("invalid client certificate: {0}")]
+    ClientCertificate(#[source] native_tls::Error),
+
+    /// The server certificate matches none of the pins.
+    #[error("the certificate matches none of the pinned fingerprints")]
+    PinMismatch,
+
+    /// The server certificate is not valid for the name to verify.
+    #[error("the certificate is not valid for '{0}'")]
+    NameMismatch(String),
+
+    /// The platform library can not enforce the minimum TLS version.
+    #[error("{0} can not be required with the native TLS backend")]
+    UnsupportedMinVersion(TlsVersion),
+
+    /// TLS handshake or connector setup error.
+    #[error("TLS error: {0}")]
+    Tls(#[from] native_tls::Error),
+}
+
+/// Compute the `tls-exporter` channel binding (RFC 9266) for the stream.
+///
+/// The platform libraries do not export keying material, so there is never
+/// one.
+pub fn tls_exporter_channel_binding(_stream: &TlsStream) -> Option<Vec<u8>> {
+    None
+}
+
+/// Whether the handshake resumed an earlier TLS session.
+///
+/// The platform libraries do not tell, so this is always false.
+pub fn resumed(_stream: &TlsStream) -> bool {
+    false
+}
+
+/// The negotiated protocol version and cipher suite.
+///
+/// The platform libraries do not tell, so there is never one.
+pub fn negotiated(_stream: &TlsStream) -> Option<String> {
+    None
+}
+
+/// The certificate the server presented.
+pub fn peer_certificate(stream: &TlsStream) -> Option<rustls_pki_types::CertificateDer<'static>> {
+    let certificate = stream.get_ref().peer_certificate().ok()??;
+    Some(certificate.to_der().ok()?.into())
+}
+
+/// Obtain a native TLS connector verifying the server certificate according
+/// to the trust settings and negotiating according to the policy.
+///
+/// Presents the client identity for mutual TLS when provided. The protocol
+/// versions and ciphers otherwise follow the system crypto policy.
+pub fn connector(
+    client_identity: Option<&ClientIdentity>,
+    trust: &ServerTrust,
+    policy: &TlsPolicy,
+) -> Result<NativeTlsConnector, TlsConnectError> {
+    let mut builder = native_tls::TlsConnector::builder();
+    match policy.min_version {
+        None => {}
+        Some(TlsVersion::V1_2) => {
+            builder.min_protocol_version(Some(native_tls::Protocol::Tlsv12));
+        }
+        Some(version @ TlsVersion::V1_3) => {
+            return Err(TlsConnectError::UnsupportedMinVersion(version));
+        }
+    }
+    if let Some(alpn) = &policy.alpn {
+        builder.request_alpns(&[alpn]);
+    }
+
+    builder.disable_built_in_roots(!trust.native_roots);
+    for certificate in &trust.extra_roots {
+        let certificate = native_tls::Certificate::from_der(certificate)
+            .map_err(TlsConnectError::CaCertificate)?;
+        builder.add_root_certificate(certificate);
+    }
+
+    if !trust.native_roots && trust.extra_roots.is_empty() {
+        if trust.pins.is_empty() {
+            return Err(TlsConnectError::NoTrustAnchors);
+        }
+        // Only the pins are trusted, and they are checked after the
+        // handshake.
+        builder.danger_accept_invalid_certs(true);
+    }
+    if trust.verify_name.is_some() {
+        builder.danger_accept_invalid_hostnames(true);
+    }
+
+    if let Some(ClientIdentity {
+        certificate_chain,
+        private_key,
+    }) = client_identity
+    {
+        let rustls_pki_types::PrivateKeyDer::Pkcs8(private_key) = private_key else {
+            return Err(TlsConnectError::UnsupportedClientKey);
+        };
+        let certificate_chain = certificate_chain
+            .iter()
+            .flat_map(|certificate| pem("CERTIFICATE", certificate))
+            .collect::<Vec<_>>();
+        let private_key = pem("PRIVATE KEY", private_key.secret_pkcs8_der());
+        let identity = native_tls::Identity::from_pkcs8(&certificate_chain, &private_key)
+            .map_err(TlsConnectError::ClientCertificate)?;
+        builder.identity(identity);
+    }
+
+    let inner = builder.build()?;
+    Ok(NativeTlsConnector {
+        inner: inner.into(),
+        pins: trust.pins.clone(),
+        verify_name: trust.verify_name.clone(),
+    })
+}
+
+/// Encode DER data as a PEM block with the label.
+fn pem(label: &str, der: &[u8]) -> Vec<u8> {
+    use base64::Engine as _;
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {label}-----\n").into_bytes();
+    for line in encoded.as_bytes().chunks(64) {
+        pem.extend_from_slice(line);
+        pem.push(b'\n');
+    }
+    pem.extend_from_slice(format!("-----END {label}-----\n").as_bytes());
+    pem
+}
+
+#[cfg(test)]
+mod tests;
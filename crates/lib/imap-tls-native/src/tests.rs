@@ -0,0 +1,70 @@
+use super::*;
+
+#[test]
+fn nothing_trusted_is_an_error() {
+    let trust = ServerTrust {
+        native_roots: false,
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        connector(None, &trust, &TlsPolicy::default()),
+        Err(TlsConnectError::NoTrustAnchors)
+    ));
+}
+
+#[test]
+fn pins_alone_are_enough_to_connect() {
+    let trust = ServerTrust {
+        native_roots: false,
+        extra_roots: Vec::new(),
+        pins: vec![Pin::CertificateSha256([0; 32])],
+        verify_name: None,
+    };
+
+    assert_eq!(
+        connector(None, &trust, &TlsPolicy::default()).unwrap().pins,
+        trust.pins
+    );
+}
+
+#[test]
+fn client_key_must_be_pkcs8() {
+    let identity = ClientIdentity {
+        certificate_chain: vec![rustls_pki_types::CertificateDer::from(vec![0x30, 0x00])],
+        private_key: rustls_pki_types::PrivateKeyDer::Pkcs1(vec![0x30, 0x00].into()),
+    };
+
+    assert!(matches!(
+        connector(
+            Some(&identity),
+            &ServerTrust::default(),
+            &TlsPolicy::default()
+        ),
+        Err(TlsConnectError::UnsupportedClientKey)
+    ));
+}
+
+#[test]
+fn tls13_minimum_is_unsupported() {
+    let policy = TlsPolicy {
+        min_version: Some(TlsVersion::V1_3),
+        alpn: None,
+    };
+
+    assert!(matches!(
+        connector(None, &ServerTrust::default(), &policy),
+        Err(TlsConnectError::UnsupportedMinVersion(TlsVersion::V1_3))
+    ));
+}
+
+#[test]
+fn pem_wraps_lines() {
+    let pem = String::from_utf8(pem("CERTIFICATE", &[0; 60])).unwrap();
+    let lines = pem.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.first(), Some(&"-----BEGIN CERTIFICATE-----"));
+    assert_eq!(lines.get(1).map(|line| line.len()), Some(64));
+    assert_eq!(lines.get(2), Some(&"AAAAAAAAAAAAAAAA"));
+    assert_eq!(lines.last(), Some(&"-----END CERTIFICATE-----"));
+}
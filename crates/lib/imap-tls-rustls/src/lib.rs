@@ -1,5 +1,11 @@
 //! TLS connector helpers for IMAP clients.
 
+mod trust;
+
+pub use imap_tls_core::{
+    ClientIdentity, ClientIdentityError, Pin, ServerTrust, TlsPolicy, TlsVersion, TrustError,
+};
+
 /// Rustls connector wrapper that implements the IMAP TLS connector trait.
 #[derive(Clone)]
 pub struct RustlsConnector(pub tokio_rustls::TlsConnector);
@@ -16,6 +22,18 @@ impl imap_tls_core::TlsConnector for RustlsConnector {
         let server_name = rustls::pki_types::ServerName::try_from(tls_server_name.to_string())
             .map_err(|_| TlsConnectError::InvalidDnsName(tls_server_name.to_string()))?;
         let tls_stream = self.0.connect(server_name, tcp_stream).await?;
+
+        let (_, connection) = tls_stream.get_ref();
+        tracing::debug!(
+            tls_version = connection.protocol_version().map(tracing::field::debug),
+            tls_cipher_suite = connection
+                .negotiated_cipher_suite()
+                .map(|suite| tracing::field::debug(suite.suite())),
+            tls_alpn = connection
+                .alpn_protocol()
+                .map(|protocol| tracing::field::display(String::from_utf8_lossy(protocol))),
+            "negotiated TLS"
+        );
         Ok(tls_stream)
     }
 }
@@ -30,27 +48,188 @@ pub enum TlsConnectError {
     #[error("failed to load system root certificates: {0}")]
     RootCerts(#[from] rustls_native_certs::Error),
 
+    /// An extra CA certificate was rejected.
+    #[error("invalid CA certificate: {0}")]
+    CaCertificate(#[source] rustls::Error),
+
+    /// Neither root certificates nor pins are trusted.
+    #[error("no trusted root certificates or pins")]
+    NoTrustAnchors,
+
+    /// Building the certificate verifier failed.
+    #[error("failed to build the certificate verifier: {0}")]
+    Verifier(#[source] rustls::client::VerifierBuilderError),
+
     /// Invalid DNS name for TLS verification.
     #[error("invalid DNS name: {0}")]
     InvalidDnsName(String),
 
+    /// The client certificate or key was rejected.
+    #[error("invalid client certificate: {0}")]
+    ClientCertificate(#[source] rustls::Error),
+
     /// TLS handshake or I/O error.
     #[error("TLS I/O error: {0}")]
     Io(#[from] std::io::Error),
 }
 
-/// Build a rustls connector configured with system root certificates.
-pub fn connector() -> Result<RustlsConnector, TlsConnectError> {
-    let mut root_store = rustls::RootCertStore::empty();
-    let rustls_native_certs::CertificateResult { certs, errors, .. } =
-        rustls_native_certs::load_native_certs();
-    if let Some(err) = errors.into_iter().next() {
-        return Err(TlsConnectError::RootCerts(err));
+/// Compute the `tls-exporter` channel binding (RFC 9266) for the stream.
+///
+/// Only provided for TLS 1.3, as TLS 1.2 exporters are not unique without
+/// the extended master secret.
+pub fn tls_exporter_channel_binding(stream: &TlsStream) -> Option<Vec<u8>> {
+    let (_, connection) = stream.get_ref();
+    if connection.protocol_version() != Some(rustls::ProtocolVersion::TLSv1_3) {
+        return None;
+    }
+
+    connection
+        .export_keying_material(vec![0; 32], b"EXPORTER-Channel-Binding", None)
+        .ok()
+}
+
+/// Whether the handshake resumed an earlier TLS session.
+pub fn resumed(stream: &TlsStream) -> bool {
+    let (_, connection) = stream.get_ref();
+    connection.handshake_kind() == Some(rustls::HandshakeKind::Resumed)
+}
+
+/// The negotiated protocol version and cipher suite, e.g.
+/// `TLSv1_3 (TLS13_AES_256_GCM_SHA384)`.
+pub fn negotiated(stream: &TlsStream) -> Option<String> {
+    let (_, connection) = stream.get_ref();
+    let version = connection.protocol_version()?.as_str()?;
+    let cipher_suite = connection.negotiated_cipher_suite()?.suite().as_str()?;
+    Some(format!("{version} ({cipher_suite})"))
+}
+
+/// The certificate the server presented.
+pub fn peer_certificate(stream: &TlsStream) -> Option<rustls::pki_types::CertificateDer<'static>> {
+    let (_, connection) = stream.get_ref();
+    let certificate = connection.peer_certificates()?.first()?;
+    Some(certificate.clone().into_owned())
+}
+
+/// How many TLS sessions each connector keeps for resumption.
+const SESSION_CACHE_SIZE: usize = 256;
+
+/// The connectors built so far, along with the settings they were built
+/// with.
+///
+/// Sharing them keeps the system root certificates from being reloaded on
+/// every connect, and lets reconnects resume the earlier TLS sessions.
+static CONNECTORS: std::sync::Mutex<Vec<(ConnectorSettings, RustlsConnector)>> =
+    std::sync::Mutex::new(Vec::new());
+
+/// The settings a connector is built with.
+#[derive(PartialEq, Eq)]
+struct ConnectorSettings {
+    /// The client identity for mutual TLS.
+    client_identity: Option<ClientIdentity>,
+
+    /// How to verify the server certificate.
+    trust: ServerTrust,
+
+    /// Which protocol versions and application protocol to negotiate.
+    policy: TlsPolicy,
+}
+
+/// Obtain a rustls connector verifying the server certificate according to
+/// the trust settings and negotiating according to the policy.
+///
+/// Presents the client identity for mutual TLS when provided. The connector
+/// is built once for each distinct settings and shared afterwards, along
+/// with its TLS session cache. It is built without holding the lock, as
+/// loading the system root certificates reads from the disk.
+pub fn connector(
+    client_identity: Option<&ClientIdentity>,
+    trust: &ServerTrust,
+    policy: &TlsPolicy,
+) -> Result<RustlsConnector, TlsConnectError> {
+    if let Some(connector) = shared_connector(client_identity, trust, policy) {
+        return Ok(connector);
+    }
+
+    let connector = build_connector(client_identity, trust, policy)?;
+
+    let mut connectors = CONNECTORS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    // Another connect may have built one meanwhile, share that one so its
+    // sessions are resumed.
+    let shared = connectors
+        .iter()
+        .find(|(settings, _)| settings.matches(client_identity, trust, policy));
+    if let Some((_, connector)) = shared {
+        return Ok(connector.clone());
     }
-    let _ = root_store.add_parsable_certificates(certs);
-    let config = rustls::ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
+    let settings = ConnectorSettings {
+        client_identity: client_identity.cloned(),
+        trust: trust.clone(),
+        policy: policy.clone(),
+    };
+    connectors.push((settings, connector.clone()));
+    Ok(connector)
+}
+
+/// The connector already built for the settings, if any.
+fn shared_connector(
+    client_identity: Option<&ClientIdentity>,
+    trust: &ServerTrust,
+    policy: &TlsPolicy,
+) -> Option<RustlsConnector> {
+    let connectors = CONNECTORS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    connectors
+        .iter()
+        .find(|(settings, _)| settings.matches(client_identity, trust, policy))
+        .map(|(_, connector)| connector.clone())
+}
+
+impl ConnectorSettings {
+    /// Whether the connector was built with these settings.
+    fn matches(
+        &self,
+        client_identity: Option<&ClientIdentity>,
+        trust: &ServerTrust,
+        policy: &TlsPolicy,
+    ) -> bool {
+        self.client_identity.as_ref() == client_identity
+            && self.trust == *trust
+            && self.policy == *policy
+    }
+}
+
+/// Build a rustls connector verifying the server certificate according to
+/// the trust settings and negotiating according to the policy.
+fn build_connector(
+    client_identity: Option<&ClientIdentity>,
+    trust: &ServerTrust,
+    policy: &TlsPolicy,
+) -> Result<RustlsConnector, TlsConnectError> {
+    let verifier = trust::Verifier::new(trust)?;
+    let versions: &[&rustls::SupportedProtocolVersion] = match policy.min_version {
+        None | Some(TlsVersion::V1_2) => rustls::DEFAULT_VERSIONS,
+        Some(TlsVersion::V1_3) => &[&rustls::version::TLS13],
+    };
+    let builder = rustls::ClientConfig::builder_with_protocol_versions(versions)
+        .dangerous()
+        .with_custom_certificate_verifier(std::sync::Arc::new(verifier));
+    let mut config = match client_identity {
+        Some(ClientIdentity {
+            certificate_chain,
+            private_key,
+        }) => builder
+            .with_client_auth_cert(certificate_chain.clone(), private_key.clone_key())
+            .map_err(TlsConnectError::ClientCertificate)?,
+        None => builder.with_no_client_auth(),
+    };
+    config.resumption = rustls::client::Resumption::in_memory_sessions(SESSION_CACHE_SIZE);
+    config.alpn_protocols = policy.alpn.iter().map(|alpn| alpn.clone().into()).collect();
     let inner = tokio_rustls::TlsConnector::from(std::sync::Arc::new(config));
     Ok(RustlsConnector(inner))
 }
+
+#[cfg(test)]
+mod tests;
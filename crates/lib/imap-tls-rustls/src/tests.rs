@@ -0,0 +1,181 @@
+use rustls::client::danger::ServerCertVerifier as _;
+use rustls::pki_types::pem::PemObject as _;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+
+use super::*;
+
+/// The test CA certificate.
+const CA: &[u8] = include_bytes!("fixtures/ca.pem");
+
+/// A certificate for `imap.example.com` issued by the test CA.
+const LEAF: &[u8] = include_bytes!("fixtures/leaf.pem");
+
+/// A self-signed certificate for `homelab`.
+const SELF_SIGNED: &[u8] = include_bytes!("fixtures/self_signed.pem");
+
+/// The SHA-256 fingerprint of the leaf certificate, as printed by OpenSSL.
+const LEAF_FINGERPRINT: &str = "9B:5D:76:F7:13:E7:68:CD:DF:72:6F:17:C7:8B:5C:6A:2C:86:A8:E6:3F:AB:18:AA:09:67:66:F1:B6:4A:13:30";
+
+/// The base64 SHA-256 fingerprint of the leaf certificate public key.
+const LEAF_SPKI_FINGERPRINT: &str = "35EV9PYAFAd6S3ZPLTXXgBJrAfIEaUCCowFDwIDyw/Q=";
+
+/// The base64 SHA-256 fingerprint of the self-signed certificate public key.
+const SELF_SIGNED_SPKI_FINGERPRINT: &str = "fjjqqAH+NAjkJBh14SxvKlKYPhxleW0wLtk0mMbytmc=";
+
+/// Parse a single PEM certificate.
+fn certificate(pem: &[u8]) -> CertificateDer<'static> {
+    CertificateDer::from_pem_slice(pem).unwrap()
+}
+
+/// Run the server certificate verification at a time the fixtures are valid.
+fn verify(trust: &ServerTrust, pem: &[u8], server_name: &str) -> Result<(), rustls::Error> {
+    let verifier = trust::Verifier::new(trust).unwrap();
+    let server_name = ServerName::try_from(server_name.to_owned()).unwrap();
+    // 2030-01-01T00:00:00Z.
+    let now = UnixTime::since_unix_epoch(std::time::Duration::from_secs(1_893_456_000));
+    verifier
+        .verify_server_cert(&certificate(pem), &[], &server_name, &[], now)
+        .map(|_| ())
+}
+
+/// Trust settings with only the test CA.
+fn ca_trust() -> ServerTrust {
+    let mut trust = ServerTrust {
+        native_roots: false,
+        ..Default::default()
+    };
+    trust.add_roots_pem(CA).unwrap();
+    trust
+}
+
+#[test]
+fn parse_fingerprint_formats() {
+    let hex = Pin::parse_fingerprint(LEAF_FINGERPRINT).unwrap();
+    assert_eq!(
+        Pin::parse_fingerprint(&LEAF_FINGERPRINT.replace(':', "").to_lowercase()).unwrap(),
+        hex
+    );
+    assert_eq!(hex[..2], [0x9b, 0x5d]);
+
+    assert!(Pin::parse_fingerprint(LEAF_SPKI_FINGERPRINT).is_ok());
+    assert!(matches!(
+        Pin::parse_fingerprint("9B:5D"),
+        Err(TrustError::Fingerprint(_))
+    ));
+}
+
+#[test]
+fn pins_match_certificate_and_public_key() {
+    let leaf = certificate(LEAF);
+    let self_signed = certificate(SELF_SIGNED);
+
+    let pin = Pin::CertificateSha256(Pin::parse_fingerprint(LEAF_FINGERPRINT).unwrap());
+    assert!(pin.matches(&leaf));
+    assert!(!pin.matches(&self_signed));
+
+    let pin = Pin::SpkiSha256(Pin::parse_fingerprint(LEAF_SPKI_FINGERPRINT).unwrap());
+    assert!(pin.matches(&leaf));
+    assert!(!pin.matches(&self_signed));
+}
+
+#[test]
+fn add_roots_pem_requires_certificates() {
+    let mut trust = ServerTrust::default();
+    assert!(matches!(
+        trust.add_roots_pem(b""),
+        Err(TrustError::NoCertificates)
+    ));
+}
+
+#[test]
+fn extra_roots_verify_the_chain() {
+    let trust = ca_trust();
+
+    verify(&trust, LEAF, "imap.example.com").unwrap();
+    assert!(verify(&trust, LEAF, "other.example.com").is_err());
+    assert!(verify(&trust, SELF_SIGNED, "homelab").is_err());
+}
+
+#[test]
+fn pins_restrict_the_trusted_chain() {
+    let mut trust = ca_trust();
+    trust.pins = vec![Pin::SpkiSha256(
+        Pin::parse_fingerprint(SELF_SIGNED_SPKI_FINGERPRINT).unwrap(),
+    )];
+    assert!(verify(&trust, LEAF, "imap.example.com").is_err());
+
+    trust.pins = vec![Pin::SpkiSha256(
+        Pin::parse_fingerprint(LEAF_SPKI_FINGERPRINT).unwrap(),
+    )];
+    verify(&trust, LEAF, "imap.example.com").unwrap();
+}
+
+#[test]
+fn pins_alone_trust_a_self_signed_certificate() {
+    let trust = ServerTrust {
+        native_roots: false,
+        extra_roots: Vec::new(),
+        pins: vec![Pin::SpkiSha256(
+            Pin::parse_fingerprint(SELF_SIGNED_SPKI_FINGERPRINT).unwrap(),
+        )],
+        verify_name: None,
+    };
+
+    verify(&trust, SELF_SIGNED, "homelab").unwrap();
+    assert!(verify(&trust, LEAF, "imap.example.com").is_err());
+}
+
+#[test]
+fn verify_name_replaces_the_server_name() {
+    let mut trust = ca_trust();
+    trust.verify_name = Some("imap.example.com".to_owned());
+
+    verify(&trust, LEAF, "10.0.0.1").unwrap();
+    assert!(verify(&trust, SELF_SIGNED, "homelab").is_err());
+}
+
+#[test]
+fn nothing_trusted_is_an_error() {
+    let trust = ServerTrust {
+        native_roots: false,
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        trust::Verifier::new(&trust),
+        Err(TlsConnectError::NoTrustAnchors)
+    ));
+}
+
+#[test]
+fn connectors_are_shared_by_settings() {
+    let config = |trust: &ServerTrust| {
+        std::sync::Arc::clone(
+            connector(None, trust, &TlsPolicy::default())
+                .unwrap()
+                .0
+                .config(),
+        )
+    };
+
+    let trust = ca_trust();
+    assert!(std::sync::Arc::ptr_eq(
+        &config(&trust),
+        &config(&trust.clone())
+    ));
+
+    let mut pinned = ca_trust();
+    pinned.pins.push(Pin::CertificateSha256([0; 32]));
+    assert!(!std::sync::Arc::ptr_eq(&config(&trust), &config(&pinned)));
+}
+
+#[test]
+fn policy_offers_the_alpn_protocol() {
+    let policy = TlsPolicy {
+        min_version: Some(TlsVersion::V1_3),
+        alpn: Some("imap".to_owned()),
+    };
+    let connector = connector(None, &ca_trust(), &policy).unwrap();
+
+    assert_eq!(connector.0.config().alpn_protocols, [b"imap".to_vec()]);
+}
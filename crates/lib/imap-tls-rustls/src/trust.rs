@@ -0,0 +1,141 @@
+//! Server certificate verification.
+
+use std::sync::Arc;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+
+use crate::{Pin, ServerTrust, TlsConnectError};
+
+/// The server certificate matches none of the pins.
+#[derive(Debug, thiserror::Error)]
+#[error("the certificate matches none of the pinned fingerprints")]
+struct PinMismatch;
+
+/// Verifies the server certificate according to the trust settings.
+#[derive(Debug)]
+pub(crate) struct Verifier {
+    /// Chain verification against the trusted roots, unset when only the
+    /// pins are trusted.
+    webpki: Option<Arc<rustls::client::WebPkiServerVerifier>>,
+
+    /// The pins the certificate must match one of.
+    pins: Vec<Pin>,
+
+    /// The name to verify the certificate for, instead of the TLS server
+    /// name.
+    verify_name: Option<ServerName<'static>>,
+
+    /// The algorithms to verify the handshake signatures with.
+    algorithms: rustls::crypto::WebPkiSupportedAlgorithms,
+}
+
+impl Verifier {
+    /// Build the verifier, loading the system root certificates when
+    /// enabled.
+    pub(crate) fn new(trust: &ServerTrust) -> Result<Self, TlsConnectError> {
+        let mut roots = rustls::RootCertStore::empty();
+        if trust.native_roots {
+            let rustls_native_certs::CertificateResult { certs, errors, .. } =
+                rustls_native_certs::load_native_certs();
+            if let Some(err) = errors.into_iter().next() {
+                return Err(TlsConnectError::RootCerts(err));
+            }
+            let _ = roots.add_parsable_certificates(certs);
+        }
+        for certificate in &trust.extra_roots {
+            roots
+                .add(certificate.clone())
+                .map_err(TlsConnectError::CaCertificate)?;
+        }
+
+        let provider = rustls::crypto::CryptoProvider::get_default()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(rustls::crypto::ring::default_provider()));
+
+        let webpki = if roots.is_empty() {
+            if trust.pins.is_empty() {
+                return Err(TlsConnectError::NoTrustAnchors);
+            }
+            None
+        } else {
+            let verifier = rustls::client::WebPkiServerVerifier::builder_with_provider(
+                Arc::new(roots),
+                Arc::clone(&provider),
+            )
+            .build()
+            .map_err(TlsConnectError::Verifier)?;
+            Some(verifier)
+        };
+
+        let verify_name = trust
+            .verify_name
+            .as_ref()
+            .map(|name| {
+                ServerName::try_from(name.clone())
+                    .map_err(|_| TlsConnectError::InvalidDnsName(name.clone()))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            webpki,
+            pins: trust.pins.clone(),
+            verify_name,
+            algorithms: provider.signature_verification_algorithms,
+        })
+    }
+}
+
+impl ServerCertVerifier for Verifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let server_name = self.verify_name.as_ref().unwrap_or(server_name);
+        if let Some(webpki) = &self.webpki {
+            webpki.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            )?;
+        }
+
+        // Only the end entity is checked, as the server may send any
+        // intermediate along with it.
+        if self.pins.is_empty() || self.pins.iter().any(|pin| pin.matches(end_entity)) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::Other(rustls::OtherError(Arc::new(PinMismatch))),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,167 @@
+//! The `COMPRESS=DEFLATE` codec (RFC 4978).
+
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// How much compressed data to read from the connection at once.
+const READ_CHUNK: usize = 4096;
+
+/// Raw deflate in both directions, flushed at the end of each command so
+/// the other side can act on it.
+pub(crate) struct Deflate {
+    /// Compresses the data sent.
+    compress: flate2::Compress,
+
+    /// Decompresses the data received.
+    decompress: flate2::Decompress,
+
+    /// Received data not decompressed yet.
+    input: Vec<u8>,
+
+    /// Whether the last decompression filled the buffer, so it may hold
+    /// more output.
+    draining: bool,
+
+    /// Compressed data not sent yet.
+    output: Vec<u8>,
+
+    /// Whether data was compressed since the last flush.
+    unflushed: bool,
+}
+
+impl std::fmt::Debug for Deflate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Deflate")
+            .field("total_in", &self.compress.total_in())
+            .field("total_out", &self.compress.total_out())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Deflate {
+    /// Start both directions of the codec.
+    pub(crate) fn new() -> Self {
+        Self {
+            compress: flate2::Compress::new(flate2::Compression::default(), false),
+            decompress: flate2::Decompress::new(false),
+            input: Vec::new(),
+            draining: false,
+            output: Vec::new(),
+            unflushed: false,
+        }
+    }
+
+    /// Read from the connection and decompress into the buffer.
+    pub(crate) fn poll_read<R: AsyncRead + Unpin>(
+        &mut self,
+        inner: &mut R,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            if !self.input.is_empty() || self.draining {
+                let space = buf.remaining();
+                let total_in = self.decompress.total_in();
+                let total_out = self.decompress.total_out();
+                let status = self
+                    .decompress
+                    .decompress(
+                        &self.input,
+                        buf.initialize_unfilled(),
+                        flate2::FlushDecompress::Sync,
+                    )
+                    .map_err(std::io::Error::other)?;
+                let consumed = progress(total_in, self.decompress.total_in());
+                let produced = progress(total_out, self.decompress.total_out());
+                self.input.drain(..consumed);
+                buf.advance(produced);
+                self.draining = produced == space;
+
+                if produced > 0 || status == flate2::Status::StreamEnd {
+                    return Poll::Ready(Ok(()));
+                }
+                if consumed > 0 {
+                    continue;
+                }
+            }
+
+            let mut chunk = [0; READ_CHUNK];
+            let mut chunk = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut *inner).poll_read(cx, &mut chunk))?;
+            if chunk.filled().is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            self.input.extend_from_slice(chunk.filled());
+        }
+    }
+
+    /// Compress the data, sending it on the next flush.
+    pub(crate) fn poll_write<W: AsyncWrite + Unpin>(
+        &mut self,
+        inner: &mut W,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        ready!(self.poll_send(inner, cx))?;
+
+        let mut rest = buf;
+        while !rest.is_empty() {
+            self.output.reserve(rest.len() / 2 + 64);
+            let total_in = self.compress.total_in();
+            self.compress
+                .compress_vec(rest, &mut self.output, flate2::FlushCompress::None)
+                .map_err(std::io::Error::other)?;
+            rest = &rest[progress(total_in, self.compress.total_in())..];
+        }
+        self.unflushed |= !buf.is_empty();
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    /// Finish the compressed block and send everything.
+    pub(crate) fn poll_flush<W: AsyncWrite + Unpin>(
+        &mut self,
+        inner: &mut W,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        while self.unflushed {
+            self.output.reserve(64);
+            let spare = self.output.capacity() - self.output.len();
+            let total_out = self.compress.total_out();
+            self.compress
+                .compress_vec(&[], &mut self.output, flate2::FlushCompress::Sync)
+                .map_err(std::io::Error::other)?;
+            // The flush is complete once it no longer fills the space.
+            self.unflushed = progress(total_out, self.compress.total_out()) == spare;
+        }
+
+        ready!(self.poll_send(inner, cx))?;
+        Pin::new(inner).poll_flush(cx)
+    }
+
+    /// Send the compressed data.
+    fn poll_send<W: AsyncWrite + Unpin>(
+        &mut self,
+        inner: &mut W,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        while !self.output.is_empty() {
+            let written = ready!(Pin::new(&mut *inner).poll_write(cx, &self.output))?;
+            if written == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+            self.output.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// How far a codec counter moved.
+fn progress(before: u64, after: u64) -> usize {
+    usize::try_from(after - before).unwrap_or(usize::MAX)
+}
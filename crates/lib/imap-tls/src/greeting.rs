@@ -0,0 +1,64 @@
+//! The server greeting.
+
+use async_imap::imap_proto::{Response, ResponseCode, Status};
+
+/// What the server greeting tells about the connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Greeting {
+    /// Whether the connection is already authenticated (`PREAUTH`).
+    pub preauth: bool,
+
+    /// The capabilities listed in the greeting, if any.
+    ///
+    /// After STARTTLS these are the capabilities re-read over TLS instead.
+    pub capabilities: Option<imap_capabilities::Capabilities>,
+}
+
+impl Greeting {
+    /// Interpret the first response sent by the server.
+    pub fn parse(response: &Response<'_>) -> Result<Self, GreetingError> {
+        let Response::Data {
+            status,
+            code,
+            information,
+        } = response
+        else {
+            return Err(GreetingError::Unexpected(format!("{response:?}")));
+        };
+
+        let preauth = match status {
+            Status::Ok => false,
+            Status::PreAuth => true,
+            Status::Bye => {
+                return Err(GreetingError::Rejected(
+                    information.as_deref().unwrap_or_default().to_owned(),
+                ));
+            }
+            status => return Err(GreetingError::Unexpected(format!("{status:?}"))),
+        };
+
+        let capabilities = match code {
+            Some(ResponseCode::Capabilities(list)) => {
+                Some(imap_capabilities::Capabilities::from_proto(list))
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            preauth,
+            capabilities,
+        })
+    }
+}
+
+/// Errors in the server greeting.
+#[derive(Debug, thiserror::Error)]
+pub enum GreetingError {
+    /// The server closed the connection right away (`BYE`).
+    #[error("server rejected the connection: {0}")]
+    Rejected(String),
+
+    /// The first response is not a greeting.
+    #[error("unexpected greeting: {0}")]
+    Unexpected(String),
+}
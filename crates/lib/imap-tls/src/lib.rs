@@ -1,5 +1,16 @@
 //! IMAP connect helpers.
 
+mod deflate;
+mod greeting;
+mod stream;
+mod timeouts;
+
+pub use greeting::{Greeting, GreetingError};
+pub use stream::{MaybeTlsStream, Stream};
+pub use timeouts::{Step, Timeouts};
+
+use timeouts::limit;
+
 /// Errors returned while connecting to the IMAP server.
 #[derive(Debug, thiserror::Error)]
 pub enum ConnectError<E>
@@ -21,6 +32,29 @@ where
     /// The server did not send the expected greeting.
     #[error("IMAP server sent no greeting")]
     MissingGreeting,
+
+    /// The server greeting is not usable.
+    #[error("IMAP greeting: {0}")]
+    Greeting(#[from] GreetingError),
+
+    /// The server does not advertise STARTTLS.
+    #[error("IMAP server does not advertise STARTTLS")]
+    StartTlsUnsupported,
+
+    /// The server preauthenticated the connection before it could be
+    /// upgraded with STARTTLS.
+    #[error("IMAP server sent PREAUTH, so the connection cannot be upgraded with STARTTLS")]
+    PreAuthBeforeStartTls,
+
+    /// A step took longer than its time limit.
+    #[error("timed out waiting for {step} after {after:?}")]
+    Timeout {
+        /// The step that timed out.
+        step: Step,
+
+        /// The time limit.
+        after: std::time::Duration,
+    },
 }
 
 /// How to secure the IMAP connection.
@@ -31,48 +65,204 @@ pub enum TlsMode {
 
     /// Start with plaintext and upgrade using STARTTLS (usually port 143).
     StartTls,
+
+    /// Plaintext without TLS (usually port 143).
+    ///
+    /// Only meant for trusted local connections.
+    None,
 }
 
 /// Connect to the IMAP server using the provided connector.
+///
+/// The connector is not used with [`TlsMode::None`], see
+/// [`connect_plaintext`] to connect without one. The trace, if any, starts
+/// with the greeting.
 pub async fn connect<C>(
     tcp_stream: tokio::net::TcpStream,
     tls_server_name: &str,
     tls_mode: TlsMode,
     connector: C,
-) -> Result<async_imap::Client<C::Stream>, ConnectError<C::Error>>
+    timeouts: Timeouts,
+    trace: Option<imap_trace::Trace>,
+) -> Result<(async_imap::Client<Stream<C::Stream>>, Greeting), ConnectError<C::Error>>
 where
     C: imap_tls_core::TlsConnector,
     C::Stream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
     C::Error: std::error::Error + Send + Sync + 'static,
 {
-    let client = match tls_mode {
+    match tls_mode {
         TlsMode::Implicit => {
-            let stream = connector
-                .connect(tls_server_name, tcp_stream)
-                .await
-                .map_err(ConnectError::Tls)?;
-            let mut client = async_imap::Client::new(stream);
-            client
-                .read_response()
-                .await?
-                .ok_or(ConnectError::MissingGreeting)?;
-            client
+            let stream = handshake(&connector, tls_server_name, tcp_stream, timeouts).await?;
+            start(MaybeTlsStream::Tls(stream), timeouts, trace).await
         }
         TlsMode::StartTls => {
-            let mut client = async_imap::Client::new(tcp_stream);
-            client
-                .read_response()
-                .await?
-                .ok_or(ConnectError::MissingGreeting)?;
-            client.run_command_and_check_ok("STARTTLS", None).await?;
-            let tcp_stream = client.into_inner();
-            let stream = connector
-                .connect(tls_server_name, tcp_stream)
-                .await
-                .map_err(ConnectError::Tls)?;
-            async_imap::Client::new(stream)
+            let stream =
+                Stream::<C::Stream>::new(MaybeTlsStream::Plain(tcp_stream)).with_trace(trace);
+            let mut client = async_imap::Client::new(stream);
+            let greeting = read_greeting(&mut client, timeouts.greeting).await?;
+            if greeting.preauth {
+                return Err(ConnectError::PreAuthBeforeStartTls);
+            }
+            let capabilities = match greeting.capabilities {
+                Some(capabilities) => capabilities,
+                None => fetch_capabilities(&mut client, timeouts).await?,
+            };
+            if !capabilities.has("STARTTLS") {
+                return Err(ConnectError::StartTlsUnsupported);
+            }
+            limit(Step::Command("STARTTLS"), timeouts.command, async {
+                Ok(client.run_command_and_check_ok("STARTTLS", None).await?)
+            })
+            .await?;
+
+            // Anything buffered past the tagged `OK` is dropped along with
+            // the client, so nothing sent before the handshake is kept.
+            let Some((tcp_stream, trace)) = client.into_inner().into_plain() else {
+                unreachable!("the connection is plaintext until the handshake");
+            };
+            let stream = handshake(&connector, tls_server_name, tcp_stream, timeouts).await?;
+            let stream = Stream::new(MaybeTlsStream::Tls(stream)).with_trace(trace);
+            let mut client = async_imap::Client::new(stream);
+            let capabilities = fetch_capabilities(&mut client, timeouts).await?;
+            let greeting = Greeting {
+                preauth: false,
+                capabilities: Some(capabilities),
+            };
+            Ok((client, greeting))
         }
-    };
+        TlsMode::None => connect_plaintext(tcp_stream, timeouts, trace).await,
+    }
+}
+
+/// Run the TLS handshake within its time limit.
+async fn handshake<C>(
+    connector: &C,
+    tls_server_name: &str,
+    tcp_stream: tokio::net::TcpStream,
+    timeouts: Timeouts,
+) -> Result<C::Stream, ConnectError<C::Error>>
+where
+    C: imap_tls_core::TlsConnector,
+    C::Error: std::error::Error + Send + Sync + 'static,
+{
+    limit(Step::TlsHandshake, timeouts.tls_handshake, async {
+        connector
+            .connect(tls_server_name, tcp_stream)
+            .await
+            .map_err(ConnectError::Tls)
+    })
+    .await
+}
+
+/// Query the capabilities within the command time limit.
+async fn fetch_capabilities<S, E>(
+    client: &mut async_imap::Client<S>,
+    timeouts: Timeouts,
+) -> Result<imap_capabilities::Capabilities, ConnectError<E>>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    limit(Step::Command("CAPABILITY"), timeouts.command, async {
+        Ok(imap_capabilities::fetch(client).await?)
+    })
+    .await
+}
+
+/// Connect to the IMAP server without TLS.
+pub async fn connect_plaintext<S, E>(
+    tcp_stream: tokio::net::TcpStream,
+    timeouts: Timeouts,
+    trace: Option<imap_trace::Trace>,
+) -> Result<(async_imap::Client<Stream<S>>, Greeting), ConnectError<E>>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    start(MaybeTlsStream::Plain(tcp_stream), timeouts, trace).await
+}
+
+/// Talk to the IMAP server over the pipes of a tunnel command.
+///
+/// The command is trusted to secure the connection, so TLS is not used.
+pub async fn connect_tunnel<S, E>(
+    tunnel: imap_tunnel::Stream,
+    timeouts: Timeouts,
+    trace: Option<imap_trace::Trace>,
+) -> Result<(async_imap::Client<Stream<S>>, Greeting), ConnectError<E>>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    start(MaybeTlsStream::Tunnel(tunnel), timeouts, trace).await
+}
+
+/// Talk to the IMAP server over a Unix socket.
+///
+/// The socket is local to the machine, so TLS is not used.
+#[cfg(unix)]
+pub async fn connect_unix<S, E>(
+    unix_stream: tokio::net::UnixStream,
+    timeouts: Timeouts,
+    trace: Option<imap_trace::Trace>,
+) -> Result<(async_imap::Client<Stream<S>>, Greeting), ConnectError<E>>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    start(MaybeTlsStream::Unix(unix_stream), timeouts, trace).await
+}
+
+/// Start the client over the stream, reading the greeting.
+async fn start<S, E>(
+    stream: MaybeTlsStream<S>,
+    timeouts: Timeouts,
+    trace: Option<imap_trace::Trace>,
+) -> Result<(async_imap::Client<Stream<S>>, Greeting), ConnectError<E>>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut client = async_imap::Client::new(Stream::new(stream).with_trace(trace));
+    let greeting = read_greeting(&mut client, timeouts.greeting).await?;
+    Ok((client, greeting))
+}
+
+/// Read and interpret the server greeting within the time limit.
+pub async fn read_greeting<S, E>(
+    client: &mut async_imap::Client<S>,
+    timeout: std::time::Duration,
+) -> Result<Greeting, ConnectError<E>>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    limit(Step::Greeting, timeout, async {
+        let response = client
+            .read_response()
+            .await?
+            .ok_or(ConnectError::MissingGreeting)?;
+        Ok(Greeting::parse(response.parsed())?)
+    })
+    .await
+}
 
-    Ok(client)
+/// Turn the client of a preauthenticated connection into a session.
+///
+/// async-imap only makes a session out of a successful login, so a `LOGIN`
+/// command is answered locally instead of being sent to the server.
+pub async fn preauthenticated<S>(
+    mut client: async_imap::Client<Stream<S>>,
+) -> Result<async_imap::Session<Stream<S>>, async_imap::error::Error>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug,
+{
+    client.get_mut().answer_locally();
+    client
+        .login("preauth", "preauth")
+        .await
+        .map_err(|(error, _client)| error)
 }
+
+#[cfg(test)]
+mod tests;
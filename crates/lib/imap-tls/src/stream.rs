@@ -0,0 +1,261 @@
+//! The streams under the IMAP client.
+
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// An IMAP connection stream, plaintext or secured with TLS.
+#[derive(Debug)]
+pub enum MaybeTlsStream<S> {
+    /// A plaintext TCP stream.
+    Plain(tokio::net::TcpStream),
+
+    /// A TLS stream.
+    Tls(S),
+
+    /// The pipes of a tunnel command.
+    Tunnel(imap_tunnel::Stream),
+
+    /// A plaintext Unix socket stream.
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+}
+
+impl<S> MaybeTlsStream<S> {
+    /// The TLS stream, unless the connection is plaintext.
+    pub fn tls(&self) -> Option<&S> {
+        match self {
+            Self::Plain(_) | Self::Tunnel(_) => None,
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+            Self::Tls(stream) => Some(stream),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for MaybeTlsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tunnel(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for MaybeTlsStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tunnel(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tunnel(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Tunnel(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+/// The stream under the IMAP client.
+///
+/// Passes the data through to the connection, except that it can answer a
+/// single command locally, which is how a session is made for a
+/// preauthenticated connection, see [`preauthenticated`](crate::preauthenticated).
+/// Once compression is negotiated, the data is deflated on the way. The
+/// exchange with the server is traced when asked for.
+#[derive(Debug)]
+pub struct Stream<S> {
+    /// The connection.
+    inner: MaybeTlsStream<S>,
+
+    /// The command answered locally, if any.
+    local: Local,
+
+    /// The compression codec, once negotiated.
+    deflate: Option<Box<crate::deflate::Deflate>>,
+
+    /// The trace of the exchange with the server, if any.
+    trace: Option<Box<imap_trace::Trace>>,
+}
+
+/// The state of the command answered locally.
+#[derive(Debug, Default)]
+enum Local {
+    /// Everything goes to the connection.
+    #[default]
+    Off,
+
+    /// Collecting the command line to answer.
+    Command(Vec<u8>),
+
+    /// Handing out the tagged reply to the command.
+    Reply {
+        /// The reply line.
+        reply: Vec<u8>,
+
+        /// How much of the reply was read.
+        position: usize,
+    },
+}
+
+impl<S> Stream<S> {
+    /// Wrap the connection.
+    pub fn new(inner: MaybeTlsStream<S>) -> Self {
+        Self {
+            inner,
+            local: Local::Off,
+            deflate: None,
+            trace: None,
+        }
+    }
+
+    /// Trace the exchange with the server, from now on.
+    pub fn with_trace(mut self, trace: Option<imap_trace::Trace>) -> Self {
+        self.trace = trace.map(Box::new);
+        self
+    }
+
+    /// Take the plaintext connection and the trace back, to secure the
+    /// connection.
+    pub(crate) fn into_plain(self) -> Option<(tokio::net::TcpStream, Option<imap_trace::Trace>)> {
+        match self.inner {
+            MaybeTlsStream::Plain(tcp_stream) => Some((tcp_stream, self.trace.map(|trace| *trace))),
+            MaybeTlsStream::Tls(_) | MaybeTlsStream::Tunnel(_) => None,
+            #[cfg(unix)]
+            MaybeTlsStream::Unix(_) => None,
+        }
+    }
+
+    /// The connection.
+    pub fn get_ref(&self) -> &MaybeTlsStream<S> {
+        &self.inner
+    }
+
+    /// The TLS stream, unless the connection is plaintext.
+    pub fn tls(&self) -> Option<&S> {
+        self.inner.tls()
+    }
+
+    /// Deflate the data from now on, once the server accepted
+    /// `COMPRESS DEFLATE`.
+    pub fn start_deflate(&mut self) {
+        self.deflate = Some(Box::new(crate::deflate::Deflate::new()));
+    }
+
+    /// Whether the data is deflated.
+    pub fn deflating(&self) -> bool {
+        self.deflate.is_some()
+    }
+
+    /// Answer the next command with a tagged `OK` instead of sending it.
+    pub(crate) fn answer_locally(&mut self) {
+        self.local = Local::Command(Vec::new());
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Stream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let Local::Reply { reply, position } = &mut this.local else {
+            let filled = buf.filled().len();
+            ready!(match &mut this.deflate {
+                Some(deflate) => deflate.poll_read(&mut this.inner, cx, buf),
+                None => Pin::new(&mut this.inner).poll_read(cx, buf),
+            })?;
+            if let Some(trace) = &mut this.trace {
+                trace.received(&buf.filled()[filled..]);
+            }
+            return Poll::Ready(Ok(()));
+        };
+
+        let len = buf.remaining().min(reply.len() - *position);
+        buf.put_slice(&reply[*position..*position + len]);
+        *position += len;
+        if *position == reply.len() {
+            this.local = Local::Off;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Stream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        match &mut this.local {
+            Local::Off => {
+                let written = ready!(match &mut this.deflate {
+                    Some(deflate) => deflate.poll_write(&mut this.inner, cx, buf),
+                    None => Pin::new(&mut this.inner).poll_write(cx, buf),
+                })?;
+                if let Some(trace) = &mut this.trace {
+                    trace.sent(&buf[..written]);
+                }
+                Poll::Ready(Ok(written))
+            }
+            Local::Command(command) => {
+                command.extend_from_slice(buf);
+                if command.ends_with(b"\r\n") {
+                    let tag = command.split(|&byte| byte == b' ').next();
+                    let mut reply = tag.unwrap_or_default().to_vec();
+                    reply.extend_from_slice(b" OK already authenticated\r\n");
+                    this.local = Local::Reply { reply, position: 0 };
+                }
+                Poll::Ready(Ok(buf.len()))
+            }
+            Local::Reply { .. } => Poll::Ready(Err(std::io::Error::other(
+                "command sent before the local reply was read",
+            ))),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        match (&this.local, &mut this.deflate) {
+            (Local::Off, Some(deflate)) => deflate.poll_flush(&mut this.inner, cx),
+            (Local::Off, None) => Pin::new(&mut this.inner).poll_flush(cx),
+            (Local::Command(_) | Local::Reply { .. }, _) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,220 @@
+use async_imap::imap_proto;
+use imap_test_server::serve;
+use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _};
+
+use super::*;
+
+/// A connector that skips the TLS handshake.
+fn no_tls(
+    _tls_server_name: &str,
+    tcp_stream: tokio::net::TcpStream,
+) -> std::future::Ready<Result<tokio::net::TcpStream, std::io::Error>> {
+    std::future::ready(Ok(tcp_stream))
+}
+
+/// Time limits that the tests stay well within.
+const TIMEOUTS: Timeouts = Timeouts {
+    tls_handshake: std::time::Duration::from_secs(10),
+    greeting: std::time::Duration::from_secs(10),
+    command: std::time::Duration::from_secs(10),
+};
+
+/// Connect to the local port.
+async fn tcp(port: u16) -> tokio::net::TcpStream {
+    tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .unwrap()
+}
+
+/// Parse a single response.
+fn parse(response: &[u8]) -> Result<Greeting, GreetingError> {
+    let (_, response) = imap_proto::parser::parse_response(response).unwrap();
+    Greeting::parse(&response)
+}
+
+#[test]
+fn parse_greetings() {
+    let greeting = parse(b"* OK [CAPABILITY IMAP4rev1 STARTTLS] ready\r\n").unwrap();
+    assert!(!greeting.preauth);
+    assert!(greeting.capabilities.unwrap().has("starttls"));
+
+    let greeting = parse(b"* PREAUTH ready\r\n").unwrap();
+    assert!(greeting.preauth);
+    assert_eq!(greeting.capabilities, None);
+
+    assert!(matches!(
+        parse(b"* BYE too many connections\r\n"),
+        Err(GreetingError::Rejected(text)) if text == "too many connections"
+    ));
+}
+
+#[tokio::test]
+async fn starttls_requires_the_capability() {
+    let (port, server) = serve(&[
+        ("", "* OK ready\r\n"),
+        (
+            "A0001 CAPABILITY",
+            "* CAPABILITY IMAP4rev1\r\nA0001 OK done\r\n",
+        ),
+    ])
+    .await;
+
+    let result = connect(
+        tcp(port).await,
+        "localhost",
+        TlsMode::StartTls,
+        no_tls,
+        TIMEOUTS,
+        None,
+    )
+    .await;
+
+    assert!(matches!(result, Err(ConnectError::StartTlsUnsupported)));
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn starttls_refuses_preauth() {
+    let (port, server) =
+        serve(&[("", "* PREAUTH [CAPABILITY IMAP4rev1 STARTTLS] ready\r\n")]).await;
+
+    let result = connect(
+        tcp(port).await,
+        "localhost",
+        TlsMode::StartTls,
+        no_tls,
+        TIMEOUTS,
+        None,
+    )
+    .await;
+
+    assert!(matches!(result, Err(ConnectError::PreAuthBeforeStartTls)));
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn starttls_rereads_capabilities() {
+    let (port, server) = serve(&[
+        (
+            "",
+            "* OK [CAPABILITY IMAP4rev1 STARTTLS LOGINDISABLED] ready\r\n",
+        ),
+        ("A0001 STARTTLS", "A0001 OK begin TLS\r\n"),
+        (
+            "A0001 CAPABILITY",
+            "* CAPABILITY IMAP4rev1 AUTH=PLAIN\r\nA0001 OK done\r\n",
+        ),
+    ])
+    .await;
+
+    let (client, greeting) = connect(
+        tcp(port).await,
+        "localhost",
+        TlsMode::StartTls,
+        no_tls,
+        TIMEOUTS,
+        None,
+    )
+    .await
+    .unwrap();
+
+    let capabilities = greeting.capabilities.unwrap();
+    assert!(capabilities.has_auth("PLAIN"));
+    assert!(!capabilities.has("LOGINDISABLED"));
+    assert!(!capabilities.has("STARTTLS"));
+    drop(client);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn preauthenticated_session_skips_login() {
+    let (port, server) = serve(&[
+        ("", "* PREAUTH ready\r\n"),
+        ("A0002 NOOP", "A0002 OK done\r\n"),
+    ])
+    .await;
+
+    let (client, greeting) =
+        connect_plaintext::<tokio::net::TcpStream, std::io::Error>(tcp(port).await, TIMEOUTS, None)
+            .await
+            .unwrap();
+    assert!(greeting.preauth);
+
+    let mut session = preauthenticated(client).await.unwrap();
+    session.noop().await.unwrap();
+    drop(session);
+
+    assert_eq!(server.await.unwrap(), ["A0002 NOOP"]);
+}
+
+#[tokio::test]
+async fn greeting_times_out() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move { listener.accept().await.unwrap() });
+
+    let timeouts = Timeouts {
+        greeting: std::time::Duration::from_millis(50),
+        ..TIMEOUTS
+    };
+    let result =
+        connect_plaintext::<tokio::net::TcpStream, std::io::Error>(tcp(port).await, timeouts, None)
+            .await;
+
+    assert!(matches!(
+        result,
+        Err(ConnectError::Timeout {
+            step: Step::Greeting,
+            ..
+        })
+    ));
+    drop(server.await.unwrap());
+}
+
+#[tokio::test]
+async fn deflate_speaks_raw_deflate() {
+    use tokio::io::AsyncReadExt as _;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let mut compressed = vec![0; 256];
+        let len = stream.read(&mut compressed).await.unwrap();
+        let mut decompress = flate2::Decompress::new(false);
+        let mut command = Vec::with_capacity(256);
+        decompress
+            .decompress_vec(
+                &compressed[..len],
+                &mut command,
+                flate2::FlushDecompress::Sync,
+            )
+            .unwrap();
+        assert_eq!(command, b"A0001 NOOP\r\n");
+
+        let mut compress = flate2::Compress::new(flate2::Compression::default(), false);
+        let mut reply = Vec::with_capacity(256);
+        compress
+            .compress_vec(
+                b"A0001 OK done\r\n",
+                &mut reply,
+                flate2::FlushCompress::Sync,
+            )
+            .unwrap();
+        stream.write_all(&reply).await.unwrap();
+    });
+
+    let mut stream = Stream::<tokio::net::TcpStream>::new(MaybeTlsStream::Plain(tcp(port).await));
+    stream.start_deflate();
+    stream.write_all(b"A0001 NOOP\r\n").await.unwrap();
+    stream.flush().await.unwrap();
+
+    let mut reply = String::new();
+    tokio::io::BufReader::new(&mut stream)
+        .read_line(&mut reply)
+        .await
+        .unwrap();
+    assert_eq!(reply, "A0001 OK done\r\n");
+    server.await.unwrap();
+}
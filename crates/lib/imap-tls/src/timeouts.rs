@@ -0,0 +1,54 @@
+//! Time limits for establishing the connection.
+
+use std::time::Duration;
+
+/// Time limits for the steps of establishing the connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Limit for the TLS handshake.
+    pub tls_handshake: Duration,
+
+    /// Limit for receiving the server greeting.
+    pub greeting: Duration,
+
+    /// Limit for each command, e.g. `STARTTLS` and `CAPABILITY`.
+    pub command: Duration,
+}
+
+/// A step of establishing the connection that can time out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// The TLS handshake.
+    TlsHandshake,
+
+    /// Receiving the server greeting.
+    Greeting,
+
+    /// A command before the connection is handed over.
+    Command(&'static str),
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TlsHandshake => f.write_str("the TLS handshake"),
+            Self::Greeting => f.write_str("the greeting"),
+            Self::Command(command) => write!(f, "{command}"),
+        }
+    }
+}
+
+/// Limit the step to the time, failing with [`crate::ConnectError::Timeout`].
+pub(crate) async fn limit<T, E, F>(
+    step: Step,
+    after: Duration,
+    future: F,
+) -> Result<T, crate::ConnectError<E>>
+where
+    F: Future<Output = Result<T, crate::ConnectError<E>>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    tokio::time::timeout(after, future)
+        .await
+        .map_err(|_| crate::ConnectError::Timeout { step, after })?
+}
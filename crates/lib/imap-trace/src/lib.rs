@@ -0,0 +1,296 @@
+//! A trace of the IMAP exchange, for debugging servers.
+//!
+//! The lines sent and received are logged with the `imap_wire` target, in a
+//! span of the same name with a `server_name` field, optionally appended to
+//! a file too. All the servers share the target, so the trace of one server
+//! is selected by the field, e.g. `imap_wire[{server_name=work}]=info`. The
+//! credentials and the message data are left out: the `LOGIN` arguments,
+//! the `AUTHENTICATE` exchange and the literal contents are replaced with
+//! `<redacted>`.
+
+use std::io::Write as _;
+
+/// What replaces the redacted data.
+const REDACTED: &str = "<redacted>";
+
+/// How much of a line is logged.
+const MAX_LINE: usize = 1000;
+
+/// Where to trace the exchange with a server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The server name, the `server_name` field of the span of the lines.
+    pub server: String,
+
+    /// The file to append the lines to, besides logging them.
+    pub file: Option<std::path::PathBuf>,
+}
+
+/// The trace of a connection.
+#[derive(Debug)]
+pub struct Trace {
+    /// The span of the lines, with the server name.
+    span: tracing::Span,
+
+    /// The lines sent to the server.
+    sent: Lines,
+
+    /// The lines received from the server.
+    received: Lines,
+
+    /// The tag of the `AUTHENTICATE` command in progress, if any.
+    authenticating: Option<String>,
+
+    /// The lines to append to the file, if any.
+    file: Option<std::sync::mpsc::Sender<String>>,
+}
+
+impl Trace {
+    /// Start the trace of a connection, along with the writer of the file
+    /// if any.
+    ///
+    /// A file that cannot be opened is logged and left out.
+    pub fn open(config: &Config) -> Self {
+        let file = config
+            .file
+            .as_ref()
+            .and_then(|path| writer(&config.server, path));
+        Self {
+            span: tracing::info_span!(
+                target: "imap_wire",
+                "imap_wire",
+                server_name = %config.server
+            ),
+            sent: Lines::default(),
+            received: Lines::default(),
+            authenticating: None,
+            file,
+        }
+    }
+
+    /// Trace data sent to the server.
+    pub fn sent(&mut self, data: &[u8]) {
+        for line in self.sent_lines(data) {
+            self.log("C:", &line);
+        }
+    }
+
+    /// Trace data received from the server.
+    pub fn received(&mut self, data: &[u8]) {
+        for line in self.received_lines(data) {
+            self.log("S:", &line);
+        }
+    }
+
+    /// The redacted lines completed by data sent to the server.
+    fn sent_lines(&mut self, data: &[u8]) -> Vec<String> {
+        self.sent
+            .feed(data)
+            .into_iter()
+            .map(|line| {
+                if self.authenticating.is_some() {
+                    return REDACTED.to_owned();
+                }
+                let mut words = line.splitn(3, ' ');
+                let (tag, command, arguments) = (words.next(), words.next(), words.next());
+                match (tag, command, arguments) {
+                    (Some(tag), Some(command), Some(_))
+                        if command.eq_ignore_ascii_case("LOGIN") =>
+                    {
+                        format!("{tag} {command} {REDACTED}")
+                    }
+                    (Some(tag), Some(command), arguments)
+                        if command.eq_ignore_ascii_case("AUTHENTICATE") =>
+                    {
+                        self.authenticating = Some(tag.to_owned());
+                        match arguments.map(|arguments| arguments.split_once(' ')) {
+                            Some(Some((mechanism, _initial_response))) => {
+                                format!("{tag} {command} {mechanism} {REDACTED}")
+                            }
+                            _ => line,
+                        }
+                    }
+                    _ => line,
+                }
+            })
+            .collect()
+    }
+
+    /// The redacted lines completed by data received from the server.
+    fn received_lines(&mut self, data: &[u8]) -> Vec<String> {
+        self.received
+            .feed(data)
+            .into_iter()
+            .map(|line| {
+                let Some(tag) = &self.authenticating else {
+                    return line;
+                };
+                if line.starts_with('+') {
+                    return format!("+ {REDACTED}");
+                }
+                if line.split(' ').next() == Some(tag.as_str()) {
+                    self.authenticating = None;
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// Log a line, and append it to the file if any.
+    fn log(&mut self, direction: &str, line: &str) {
+        self.span.in_scope(|| {
+            tracing::info!(target: "imap_wire", "{direction} {line}");
+        });
+
+        let Some(file) = &self.file else {
+            return;
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let line = format!(
+            "{}.{:03} {direction} {line}",
+            now.as_secs(),
+            now.subsec_millis()
+        );
+        if file.send(line).is_err() {
+            // The writer stopped, after logging why.
+            self.file = None;
+        }
+    }
+}
+
+/// Start the thread appending the lines to the file.
+///
+/// The file is written from its own thread, so the connection never waits
+/// on the disk. The lines are buffered, and flushed once none is pending.
+fn writer(server: &str, path: &std::path::Path) -> Option<std::sync::mpsc::Sender<String>> {
+    let (sender, receiver) = std::sync::mpsc::channel::<String>();
+    let (server_name, path) = (server.to_owned(), path.to_owned());
+    let spawned = std::thread::Builder::new()
+        .name("imap-trace".to_owned())
+        .spawn(move || {
+            let file = match private_file_options().append(true).open(&path) {
+                Ok(file) => file,
+                Err(error) => {
+                    tracing::warn!(
+                        %server_name,
+                        path = %path.display(),
+                        %error,
+                        "unable to open the IMAP trace file"
+                    );
+                    return;
+                }
+            };
+
+            let mut file = std::io::BufWriter::new(file);
+            let written = receiver.iter().try_for_each(|line| {
+                writeln!(file, "{line}")?;
+                for line in receiver.try_iter() {
+                    writeln!(file, "{line}")?;
+                }
+                file.flush()
+            });
+            if let Err(error) = written {
+                tracing::warn!(
+                    %server_name,
+                    path = %path.display(),
+                    %error,
+                    "unable to write the IMAP trace file, no longer writing it"
+                );
+            }
+        });
+
+    match spawned {
+        Ok(_) => Some(sender),
+        Err(error) => {
+            tracing::warn!(
+                server_name = %server,
+                %error,
+                "unable to start the IMAP trace file writer"
+            );
+            None
+        }
+    }
+}
+
+/// Options to create a file only readable by the current user, as the
+/// trace holds mailbox names, addresses and server details.
+fn private_file_options() -> std::fs::OpenOptions {
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+}
+
+/// Splits one direction of the exchange into lines, leaving the literal
+/// contents out.
+#[derive(Debug, Default)]
+struct Lines {
+    /// The line so far.
+    line: Vec<u8>,
+
+    /// How much of the current literal is still to come.
+    literal: usize,
+}
+
+impl Lines {
+    /// The lines completed by the data.
+    ///
+    /// A line announcing a literal goes on past it, the literal contents
+    /// being replaced.
+    fn feed(&mut self, mut data: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        while !data.is_empty() {
+            if self.literal > 0 {
+                let skipped = self.literal.min(data.len());
+                self.literal -= skipped;
+                data = &data[skipped..];
+                continue;
+            }
+
+            let Some(end) = data.iter().position(|&byte| byte == b'\n') else {
+                self.line.extend_from_slice(data);
+                break;
+            };
+            self.line.extend_from_slice(&data[..end]);
+            data = &data[end + 1..];
+            if self.line.last() == Some(&b'\r') {
+                self.line.pop();
+            }
+
+            if let Some(literal) = literal_size(&self.line) {
+                self.literal = literal;
+                self.line.extend_from_slice(REDACTED.as_bytes());
+                continue;
+            }
+
+            let mut line = String::from_utf8_lossy(&self.line).into_owned();
+            if line.len() > MAX_LINE {
+                let end = line.floor_char_boundary(MAX_LINE);
+                line.truncate(end);
+                line.push_str("...");
+            }
+            lines.push(line);
+            self.line.clear();
+        }
+        lines
+    }
+}
+
+/// The size of the literal announced at the end of the line, as in `{42}`
+/// or `{42+}`.
+fn literal_size(line: &[u8]) -> Option<usize> {
+    let line = line.strip_suffix(b"}")?;
+    let start = line.iter().rposition(|&byte| byte == b'{')?;
+    let size = &line[start + 1..];
+    let size = size.strip_suffix(b"+").unwrap_or(size);
+    if size.is_empty() || !size.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(size).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,92 @@
+use super::*;
+
+/// A trace without a file.
+fn trace() -> Trace {
+    Trace::open(&Config {
+        server: "test".to_owned(),
+        file: None,
+    })
+}
+
+#[test]
+fn login_arguments_are_redacted() {
+    let mut trace = trace();
+
+    assert_eq!(
+        trace.sent_lines(b"A0001 LOGIN \"user\" \"secret\"\r\nA0002 CAPA"),
+        ["A0001 LOGIN <redacted>"]
+    );
+    assert_eq!(trace.sent_lines(b"BILITY\r\n"), ["A0002 CAPABILITY"]);
+}
+
+#[test]
+fn authenticate_exchange_is_redacted() {
+    let mut trace = trace();
+
+    assert_eq!(
+        trace.sent_lines(b"A0001 AUTHENTICATE PLAIN\r\n"),
+        ["A0001 AUTHENTICATE PLAIN"]
+    );
+    assert_eq!(trace.received_lines(b"+ \r\n"), ["+ <redacted>"]);
+    assert_eq!(trace.sent_lines(b"AHVzZXIAc2VjcmV0\r\n"), ["<redacted>"]);
+    assert_eq!(
+        trace.received_lines(b"A0001 OK logged in\r\n"),
+        ["A0001 OK logged in"]
+    );
+    assert_eq!(trace.sent_lines(b"A0002 NOOP\r\n"), ["A0002 NOOP"]);
+
+    assert_eq!(
+        trace.sent_lines(b"A0003 AUTHENTICATE XOAUTH2 dXNlcj1...\r\n"),
+        ["A0003 AUTHENTICATE XOAUTH2 <redacted>"]
+    );
+}
+
+#[test]
+fn literal_contents_are_redacted() {
+    let mut trace = trace();
+
+    assert_eq!(
+        trace.sent_lines(b"A0001 LOGIN {4}\r\nuser {6+}\r\nsec"),
+        Vec::<String>::new()
+    );
+    assert_eq!(trace.sent_lines(b"ret\r\n"), ["A0001 LOGIN <redacted>"]);
+
+    assert_eq!(
+        trace.received_lines(b"* 1 FETCH (BODY[] {8}\r\nHi\r\n\r\nyo)\r\n* 2 EXISTS\r\n"),
+        ["* 1 FETCH (BODY[] {8}<redacted>)", "* 2 EXISTS"]
+    );
+}
+
+#[test]
+fn file_is_private_and_redacted() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trace.log");
+    let mut trace = Trace::open(&Config {
+        server: "test".to_owned(),
+        file: Some(path.clone()),
+    });
+
+    trace.sent(b"A0001 LOGIN \"user\" \"secret\"\r\n");
+    drop(trace);
+
+    // The writer thread appends the line on its own time.
+    let mut contents = String::new();
+    for _ in 0..100 {
+        contents = std::fs::read_to_string(&path).unwrap_or_default();
+        if !contents.is_empty() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(
+        contents.ends_with(" C: A0001 LOGIN <redacted>\n"),
+        "{contents}"
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,103 @@
+//! IMAP over the pipes of a spawned command.
+//!
+//! Runs commands such as `ssh mailhost /usr/lib/dovecot/imap`, like the
+//! `Tunnel` setting of Mutt and isync.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncBufReadExt as _, AsyncRead, AsyncWrite, ReadBuf};
+
+/// The stdin and stdout of the tunnel command as a stream.
+///
+/// The command is killed when the stream is dropped.
+#[derive(Debug)]
+pub struct Stream {
+    /// The command, kept to be killed along with the stream.
+    _child: tokio::process::Child,
+
+    /// The command stdin, written to.
+    stdin: tokio::process::ChildStdin,
+
+    /// The command stdout, read from.
+    stdout: tokio::process::ChildStdout,
+}
+
+/// Spawn the command with the shell.
+///
+/// The command stderr is logged.
+pub fn spawn(command: &str) -> std::io::Result<Stream> {
+    let mut child = shell(command)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let (Some(stdin), Some(stdout), Some(stderr)) =
+        (child.stdin.take(), child.stdout.take(), child.stderr.take())
+    else {
+        unreachable!("all the pipes were requested");
+    };
+    tokio::spawn(log_stderr(stderr, command.to_owned()));
+
+    Ok(Stream {
+        _child: child,
+        stdin,
+        stdout,
+    })
+}
+
+/// Build the shell invocation of the command.
+fn shell(command: &str) -> tokio::process::Command {
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = tokio::process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = tokio::process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+
+/// Log the lines the command writes to stderr.
+async fn log_stderr(stderr: tokio::process::ChildStderr, command: String) {
+    let mut lines = tokio::io::BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        tracing::warn!(message = "tunnel command", %command, stderr = %line);
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdout).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stdin).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdin).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdin).poll_shutdown(cx)
+    }
+}
@@ -1,9 +1,19 @@
 //! Keyring store initialization helpers.
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use keyring_core::CredentialStore;
 
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+mod secret_service;
+
+/// File name of the default encrypted file store.
+const DEFAULT_FILE_NAME: &str = "keyring.enc";
+
+/// File name of the generated key for the default encrypted file store.
+const DEFAULT_KEY_FILE_NAME: &str = "keyring.key";
+
 /// Errors returned while initializing the default keyring store.
 #[derive(Debug, thiserror::Error)]
 pub enum KeyringInitError {
@@ -17,6 +27,90 @@ pub enum KeyringInitError {
         /// Underlying keyring error.
         source: keyring_core::Error,
     },
+
+    /// There is no local data directory to keep the encrypted file store in.
+    #[error("no local data directory for the encrypted keyring file, set its path explicitly")]
+    NoDataDir,
+
+    /// Failed to generate the key of the encrypted file store.
+    #[error("failed to generate the keyring key file: {0}")]
+    KeyFile(#[source] encrypted_file::Error),
+}
+
+/// Which credential store to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Use the platform store, falling back to the encrypted file store when
+    /// it is unavailable.
+    #[default]
+    Auto,
+
+    /// Use only the platform store.
+    Platform,
+
+    /// Use only the encrypted file store.
+    File,
+}
+
+/// Settings of the encrypted file store.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileStoreOptions {
+    /// Path of the encrypted file, `keyring.enc` in the local data directory
+    /// by default.
+    pub path: Option<PathBuf>,
+
+    /// Source of the encryption key.
+    ///
+    /// By default a random key is generated into `keyring.key` next to the
+    /// encrypted file. This only protects the credentials from being read
+    /// out of a copy of the encrypted file alone, not from anyone who can
+    /// read the files of the user.
+    pub key_source: Option<encrypted_file::KeySource>,
+}
+
+/// Settings of the Secret Service store, used on Linux and FreeBSD.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecretServiceOptions {
+    /// Label of the collection to keep the items in, the default collection
+    /// if unset.
+    pub collection: Option<String>,
+
+    /// Label of newly created items, with `{service}` and `{account}`
+    /// replaced by the entry service and account.
+    pub label: Option<String>,
+}
+
+/// Keyring store initialization options.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Which credential store to use.
+    pub backend: Backend,
+
+    /// Settings of the encrypted file store.
+    pub file: FileStoreOptions,
+
+    /// Settings of the Secret Service store.
+    pub secret_service: SecretServiceOptions,
+}
+
+/// The keyring stayed locked, the user did not unlock it when prompted.
+///
+/// Reported as the source of [`keyring_core::Error::NoStorageAccess`], the
+/// operation may succeed once the keyring is unlocked.
+#[derive(Debug, thiserror::Error)]
+#[error("the keyring is locked")]
+pub struct LockedError {
+    /// The error of the platform store.
+    #[source]
+    pub source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+/// Whether the keyring operation failed because the keyring is locked.
+pub fn is_locked(error: &keyring_core::Error) -> bool {
+    match error {
+        keyring_core::Error::NoStorageAccess(source) => source.is::<LockedError>(),
+        _ => false,
+    }
 }
 
 /// Guard that keeps the default keyring store initialized.
@@ -24,9 +118,22 @@ pub enum KeyringInitError {
 pub struct KeyringGuard;
 
 impl KeyringGuard {
-    /// Initialize the default keyring store for the current platform.
-    pub fn init_default() -> Result<Self, KeyringInitError> {
-        let store = platform_store()?;
+    /// Initialize the default keyring store with the given options.
+    pub fn init(options: &Options) -> Result<Self, KeyringInitError> {
+        let store = match options.backend {
+            Backend::Platform => platform_store(options)?,
+            Backend::File => file_store(&options.file)?,
+            Backend::Auto => match platform_store(options) {
+                Ok(store) => store,
+                Err(error) => {
+                    tracing::warn!(
+                        message = "platform keyring unavailable, using the encrypted file store",
+                        %error
+                    );
+                    file_store(&options.file)?
+                }
+            },
+        };
         keyring_core::set_default_store(store);
         Ok(Self)
     }
@@ -38,25 +145,41 @@ impl Drop for KeyringGuard {
     }
 }
 
-#[cfg(target_os = "linux")]
-/// Build the default credential store for the current platform.
-fn platform_store() -> Result<Arc<CredentialStore>, KeyringInitError> {
-    dbus_secret_service_keyring_store::Store::new()
-        .map(|store| store as Arc<CredentialStore>)
-        .map_err(|source| KeyringInitError::Store { source })
+/// Build the encrypted file credential store.
+fn file_store(options: &FileStoreOptions) -> Result<Arc<CredentialStore>, KeyringInitError> {
+    let path = match &options.path {
+        Some(path) => path.clone(),
+        None => dirs::data_local_dir()
+            .ok_or(KeyringInitError::NoDataDir)?
+            .join("mail-notifier")
+            .join(DEFAULT_FILE_NAME),
+    };
+
+    let key_source = match &options.key_source {
+        Some(key_source) => key_source.clone(),
+        None => {
+            let key_file = path.with_file_name(DEFAULT_KEY_FILE_NAME);
+            encrypted_file::ensure_key_file(&key_file).map_err(KeyringInitError::KeyFile)?;
+            encrypted_file::KeySource::KeyFile(key_file)
+        }
+    };
+
+    tracing::debug!(message = "using the encrypted file keyring store", path = %path.display());
+
+    Ok(keyring_store_file::Store::new(path, key_source) as Arc<CredentialStore>)
 }
 
-#[cfg(target_os = "freebsd")]
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
 /// Build the default credential store for the current platform.
-fn platform_store() -> Result<Arc<CredentialStore>, KeyringInitError> {
-    dbus_secret_service_keyring_store::Store::new()
+fn platform_store(options: &Options) -> Result<Arc<CredentialStore>, KeyringInitError> {
+    secret_service::Store::new(options.secret_service.clone())
         .map(|store| store as Arc<CredentialStore>)
         .map_err(|source| KeyringInitError::Store { source })
 }
 
 #[cfg(target_os = "windows")]
 /// Build the default credential store for the current platform.
-fn platform_store() -> Result<Arc<CredentialStore>, KeyringInitError> {
+fn platform_store(_options: &Options) -> Result<Arc<CredentialStore>, KeyringInitError> {
     windows_native_keyring_store::Store::new()
         .map(|store| store as Arc<CredentialStore>)
         .map_err(|source| KeyringInitError::Store { source })
@@ -64,7 +187,7 @@ fn platform_store() -> Result<Arc<CredentialStore>, KeyringInitError> {
 
 #[cfg(target_os = "macos")]
 /// Build the default credential store for the current platform.
-fn platform_store() -> Result<Arc<CredentialStore>, KeyringInitError> {
+fn platform_store(_options: &Options) -> Result<Arc<CredentialStore>, KeyringInitError> {
     apple_native_keyring_store::keychain::Store::new()
         .map(|store| store as Arc<CredentialStore>)
         .map_err(|source| KeyringInitError::Store { source })
@@ -72,7 +195,7 @@ fn platform_store() -> Result<Arc<CredentialStore>, KeyringInitError> {
 
 #[cfg(target_os = "ios")]
 /// Build the default credential store for the current platform.
-fn platform_store() -> Result<Arc<CredentialStore>, KeyringInitError> {
+fn platform_store(_options: &Options) -> Result<Arc<CredentialStore>, KeyringInitError> {
     apple_native_keyring_store::protected::Store::new()
         .map(|store| store as Arc<CredentialStore>)
         .map_err(|source| KeyringInitError::Store { source })
@@ -86,6 +209,9 @@ fn platform_store() -> Result<Arc<CredentialStore>, KeyringInitError> {
     target_os = "ios",
 )))]
 /// Build the default credential store for the current platform.
-fn platform_store() -> Result<Arc<CredentialStore>, KeyringInitError> {
+fn platform_store(_options: &Options) -> Result<Arc<CredentialStore>, KeyringInitError> {
     Err(KeyringInitError::UnsupportedPlatform)
 }
+
+#[cfg(test)]
+mod tests;
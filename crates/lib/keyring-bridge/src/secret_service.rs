@@ -0,0 +1,215 @@
+//! Secret Service store with collection and label settings and unlock
+//! handling.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use keyring_core::api::{CredentialApi, CredentialStoreApi};
+use keyring_core::{Credential, CredentialPersistence, Entry};
+
+use crate::{LockedError, SecretServiceOptions};
+
+/// The name of the default collection in the underlying store.
+const DEFAULT_COLLECTION: &str = "default";
+
+/// Secret Service store applying the configured collection and label.
+#[derive(Debug)]
+pub(crate) struct Store {
+    /// The underlying store.
+    inner: Arc<dbus_secret_service_keyring_store::Store>,
+
+    /// The collection and label settings.
+    options: SecretServiceOptions,
+}
+
+/// A credential that unlocks its collection when it is locked.
+#[derive(Debug)]
+struct Cred {
+    /// The underlying entry.
+    inner: Entry,
+
+    /// The label of the collection the entry is in.
+    collection: Option<String>,
+}
+
+impl Store {
+    /// Connect to the Secret Service.
+    pub(crate) fn new(options: SecretServiceOptions) -> keyring_core::Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            inner: dbus_secret_service_keyring_store::Store::new()?,
+            options,
+        }))
+    }
+}
+
+impl CredentialStoreApi for Store {
+    fn vendor(&self) -> String {
+        self.inner.vendor()
+    }
+
+    fn id(&self) -> String {
+        self.inner.id()
+    }
+
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        modifiers: Option<&HashMap<&str, &str>>,
+    ) -> keyring_core::Result<Entry> {
+        let label = self.options.label.as_ref().map(|label| {
+            label
+                .replace("{service}", service)
+                .replace("{account}", user)
+        });
+
+        let mut modifiers = modifiers.cloned().unwrap_or_default();
+        if let Some(collection) = &self.options.collection {
+            modifiers.entry("target").or_insert(collection);
+        }
+        if let Some(label) = &label {
+            modifiers.entry("label").or_insert(label);
+        }
+
+        let collection = modifiers.get("target").map(|target| target.to_string());
+        let inner = self.inner.build(service, user, Some(&modifiers))?;
+        Ok(Entry::new_with_credential(Arc::new(Cred {
+            inner,
+            collection,
+        })))
+    }
+
+    fn search(&self, spec: &HashMap<&str, &str>) -> keyring_core::Result<Vec<Entry>> {
+        let collection = spec.get("target").map(|target| target.to_string());
+        let entries = self.inner.search(spec)?;
+        Ok(entries
+            .into_iter()
+            .map(|inner| {
+                Entry::new_with_credential(Arc::new(Cred {
+                    inner,
+                    collection: collection.clone(),
+                }))
+            })
+            .collect())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn persistence(&self) -> CredentialPersistence {
+        self.inner.persistence()
+    }
+}
+
+impl Cred {
+    /// Run the operation, unlocking the collection and retrying once if it
+    /// is locked.
+    fn unlocked<T>(
+        &self,
+        op: impl Fn(&Entry) -> keyring_core::Result<T>,
+    ) -> keyring_core::Result<T> {
+        retry_unlocked(|| op(&self.inner), || self.unlock())
+    }
+
+    /// Unlock the collection, prompting the user.
+    fn unlock(&self) -> Result<(), dbus_secret_service::Error> {
+        let service = dbus_secret_service::SecretService::connect(
+            dbus_secret_service::EncryptionType::Plain,
+        )?;
+        let collection = match self.collection.as_deref() {
+            None | Some(DEFAULT_COLLECTION) => service.get_default_collection()?,
+            Some(label) => service
+                .get_all_collections()?
+                .into_iter()
+                .find(|collection| collection.get_label().is_ok_and(|l| l == label))
+                .ok_or(dbus_secret_service::Error::NoResult)?,
+        };
+        collection.ensure_unlocked()
+    }
+}
+
+impl CredentialApi for Cred {
+    fn set_secret(&self, secret: &[u8]) -> keyring_core::Result<()> {
+        self.unlocked(|entry| entry.set_secret(secret))
+    }
+
+    fn get_secret(&self) -> keyring_core::Result<Vec<u8>> {
+        self.unlocked(Entry::get_secret)
+    }
+
+    fn get_attributes(&self) -> keyring_core::Result<HashMap<String, String>> {
+        self.unlocked(Entry::get_attributes)
+    }
+
+    fn update_attributes(&self, attributes: &HashMap<&str, &str>) -> keyring_core::Result<()> {
+        self.unlocked(|entry| entry.update_attributes(attributes))
+    }
+
+    fn delete_credential(&self) -> keyring_core::Result<()> {
+        self.unlocked(Entry::delete_credential)
+    }
+
+    fn get_credential(&self) -> keyring_core::Result<Option<Arc<Credential>>> {
+        let inner = self.unlocked(Entry::get_credential)?;
+        Ok(Some(Arc::new(Cred {
+            inner,
+            collection: self.collection.clone(),
+        })))
+    }
+
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        self.inner.get_specifiers()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Run the operation, unlocking and retrying once if it is locked.
+///
+/// Still being locked is reported as [`LockedError`].
+pub(crate) fn retry_unlocked<T>(
+    op: impl Fn() -> keyring_core::Result<T>,
+    unlock: impl FnOnce() -> Result<(), dbus_secret_service::Error>,
+) -> keyring_core::Result<T> {
+    match op() {
+        Err(error) if is_service_locked(&error) => {}
+        result => return result,
+    }
+
+    tracing::info!(message = "keyring is locked, requesting unlock");
+    if let Err(error) = unlock() {
+        return Err(locked(Box::new(error)));
+    }
+
+    op().map_err(|error| {
+        if is_service_locked(&error) {
+            locked(Box::new(error))
+        } else {
+            error
+        }
+    })
+}
+
+/// Whether the error is the Secret Service refusing access to a locked
+/// object, or the unlock prompt being dismissed.
+fn is_service_locked(error: &keyring_core::Error) -> bool {
+    let (keyring_core::Error::NoStorageAccess(source)
+    | keyring_core::Error::PlatformFailure(source)) = error
+    else {
+        return false;
+    };
+    matches!(
+        source.downcast_ref::<dbus_secret_service::Error>(),
+        Some(dbus_secret_service::Error::Locked | dbus_secret_service::Error::Prompt)
+    )
+}
+
+/// Report the keyring as locked.
+fn locked(source: Box<dyn std::error::Error + Send + Sync>) -> keyring_core::Error {
+    keyring_core::Error::NoStorageAccess(Box::new(LockedError {
+        source: Some(source),
+    }))
+}
@@ -0,0 +1,85 @@
+use super::*;
+
+#[test]
+fn locked_error_is_locked() {
+    let error = keyring_core::Error::NoStorageAccess(Box::new(LockedError { source: None }));
+
+    assert!(is_locked(&error));
+}
+
+#[test]
+fn other_errors_are_not_locked() {
+    let denied = keyring_core::Error::NoStorageAccess(Box::new(std::io::Error::other("denied")));
+
+    assert!(!is_locked(&keyring_core::Error::NoEntry));
+    assert!(!is_locked(&denied));
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))]
+mod secret_service {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::secret_service::retry_unlocked;
+
+    /// The error of the Secret Service refusing access to a locked item.
+    fn service_locked() -> keyring_core::Error {
+        keyring_core::Error::NoStorageAccess(Box::new(dbus_secret_service::Error::Locked))
+    }
+
+    #[test]
+    fn unlocks_and_retries() {
+        let calls = Cell::new(0);
+        let unlocked = Cell::new(false);
+
+        let result = retry_unlocked(
+            || {
+                calls.set(calls.get() + 1);
+                match unlocked.get() {
+                    true => Ok("secret"),
+                    false => Err(service_locked()),
+                }
+            },
+            || {
+                unlocked.set(true);
+                Ok(())
+            },
+        );
+
+        assert_eq!(result.unwrap(), "secret");
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn dismissed_unlock_is_locked() {
+        let calls = Cell::new(0);
+
+        let result = retry_unlocked(
+            || {
+                calls.set(calls.get() + 1);
+                Err::<(), _>(service_locked())
+            },
+            || Err(dbus_secret_service::Error::Prompt),
+        );
+
+        assert!(is_locked(&result.unwrap_err()));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn still_locked_after_unlock_is_locked() {
+        let result = retry_unlocked(|| Err::<(), _>(service_locked()), || Ok(()));
+
+        assert!(is_locked(&result.unwrap_err()));
+    }
+
+    #[test]
+    fn other_errors_do_not_unlock() {
+        let result = retry_unlocked(
+            || Err::<(), _>(keyring_core::Error::NoEntry),
+            || panic!("unlocked without being locked"),
+        );
+
+        assert!(matches!(result, Err(keyring_core::Error::NoEntry)));
+    }
+}
@@ -38,6 +38,25 @@ pub enum SetError {
     },
 }
 
+/// Errors returned while deleting passwords from a keyring.
+#[derive(Debug, thiserror::Error)]
+pub enum DeleteError {
+    /// Failed to delete the keyring entry.
+    #[error(
+        "failed to delete keyring entry for service '{service}' and account '{account}': {source}"
+    )]
+    Delete {
+        /// Keyring service name.
+        service: String,
+
+        /// Keyring account name.
+        account: String,
+
+        /// Underlying keyring error.
+        source: keyring_core::Error,
+    },
+}
+
 /// Get a password from the keyring for the given service/account pair.
 pub fn get(service: &str, account: &str) -> Result<String, GetError> {
     let entry = keyring_core::Entry::new(service, account).map_err(|source| GetError::Resolve {
@@ -70,5 +89,23 @@ pub fn set(service: &str, account: &str, password: &str) -> Result<(), SetError>
         })
 }
 
+/// Delete a password from the keyring for the given service/account pair.
+pub fn delete(service: &str, account: &str) -> Result<(), DeleteError> {
+    let entry =
+        keyring_core::Entry::new(service, account).map_err(|source| DeleteError::Delete {
+            service: service.to_string(),
+            account: account.to_string(),
+            source,
+        })?;
+
+    entry
+        .delete_credential()
+        .map_err(|source| DeleteError::Delete {
+            service: service.to_string(),
+            account: account.to_string(),
+            source,
+        })
+}
+
 #[cfg(test)]
 mod tests;
@@ -209,3 +209,26 @@ fn errors_when_keyring_entry_missing() {
         }
     }
 }
+
+#[test]
+fn deletes_password_from_keyring() {
+    let _lock = KEYRING_TEST_LOCK
+        .lock()
+        .expect("keyring test lock poisoned");
+
+    let store = InMemoryStore::new();
+    store.insert_password("mail-notifier", "user@example.com", "secret");
+    let store: Arc<CredentialStore> = store;
+    let _guard = DefaultStoreGuard::install(store);
+
+    delete("mail-notifier", "user@example.com").expect("password should delete");
+
+    let error = get("mail-notifier", "user@example.com").expect_err("deleted entry should error");
+    assert!(matches!(
+        error,
+        GetError::Resolve {
+            source: keyring_core::Error::NoEntry,
+            ..
+        }
+    ));
+}
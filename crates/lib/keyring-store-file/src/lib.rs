@@ -0,0 +1,218 @@
+//! Encrypted file-based keyring credential store.
+//!
+//! Keeps all the credentials in a single [`encrypted_file::EncryptedFile`],
+//! for systems without a usable platform keyring. Every operation
+//! re-reads the file, so changes by other processes are always observed.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use base64::Engine as _;
+use keyring_core::api::{CredentialApi, CredentialStoreApi};
+use keyring_core::{Credential, CredentialPersistence, Entry};
+
+pub use encrypted_file::KeySource;
+
+/// The vendor string of the store.
+const VENDOR: &str = "mail-notifier encrypted file store";
+
+/// Encrypted file-based credential store.
+#[derive(Debug)]
+pub struct Store {
+    /// The credentials file.
+    file: Arc<encrypted_file::EncryptedFile>,
+}
+
+/// A credential in the store.
+#[derive(Debug)]
+struct Cred {
+    /// The credentials file.
+    file: Arc<encrypted_file::EncryptedFile>,
+
+    /// The service the credential is for.
+    service: String,
+
+    /// The user the credential is for.
+    user: String,
+}
+
+/// The decrypted file contents.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct Contents {
+    /// The stored credentials.
+    entries: Vec<StoredEntry>,
+}
+
+/// A stored credential.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct StoredEntry {
+    /// The service the credential is for.
+    service: String,
+
+    /// The user the credential is for.
+    user: String,
+
+    /// The base64-encoded secret.
+    secret: String,
+}
+
+impl Store {
+    /// Create a store backed by the encrypted file at the given path.
+    ///
+    /// The file is not touched until the first credential operation.
+    pub fn new(path: impl Into<std::path::PathBuf>, key_source: KeySource) -> Arc<Self> {
+        Arc::new(Self {
+            file: Arc::new(encrypted_file::EncryptedFile::new(path, key_source)),
+        })
+    }
+
+    /// The path of the credentials file.
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+}
+
+impl CredentialStoreApi for Store {
+    fn vendor(&self) -> String {
+        VENDOR.to_owned()
+    }
+
+    fn id(&self) -> String {
+        self.file.path().display().to_string()
+    }
+
+    fn build(
+        &self,
+        service: &str,
+        user: &str,
+        modifiers: Option<&HashMap<&str, &str>>,
+    ) -> keyring_core::Result<Entry> {
+        if modifiers.is_some_and(|modifiers| !modifiers.is_empty()) {
+            return Err(keyring_core::Error::NotSupportedByStore(format!(
+                "{VENDOR} does not support modifiers"
+            )));
+        }
+        Ok(Entry::new_with_credential(Arc::new(Cred {
+            file: Arc::clone(&self.file),
+            service: service.to_owned(),
+            user: user.to_owned(),
+        })))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn persistence(&self) -> CredentialPersistence {
+        CredentialPersistence::UntilDelete
+    }
+}
+
+impl Cred {
+    /// Whether the stored entry is for this credential.
+    fn matches(&self, entry: &StoredEntry) -> bool {
+        entry.service == self.service && entry.user == self.user
+    }
+}
+
+impl CredentialApi for Cred {
+    fn set_secret(&self, secret: &[u8]) -> keyring_core::Result<()> {
+        let guard = self.file.lock().map_err(file_error)?;
+        let mut contents = decode(guard.read().map_err(file_error)?)?;
+
+        let secret = base64::engine::general_purpose::STANDARD.encode(secret);
+        match contents
+            .entries
+            .iter_mut()
+            .find(|entry| self.matches(entry))
+        {
+            Some(entry) => entry.secret = secret,
+            None => contents.entries.push(StoredEntry {
+                service: self.service.clone(),
+                user: self.user.clone(),
+                secret,
+            }),
+        }
+
+        guard.write(&encode(&contents)?).map_err(file_error)
+    }
+
+    fn get_secret(&self) -> keyring_core::Result<Vec<u8>> {
+        let contents = decode(self.file.read().map_err(file_error)?)?;
+        let entry = contents
+            .entries
+            .iter()
+            .find(|entry| self.matches(entry))
+            .ok_or(keyring_core::Error::NoEntry)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(&entry.secret)
+            .map_err(|error| {
+                keyring_core::Error::BadDataFormat(entry.secret.clone().into(), Box::new(error))
+            })
+    }
+
+    fn delete_credential(&self) -> keyring_core::Result<()> {
+        let guard = self.file.lock().map_err(file_error)?;
+        let mut contents = decode(guard.read().map_err(file_error)?)?;
+
+        let len = contents.entries.len();
+        contents.entries.retain(|entry| !self.matches(entry));
+        if contents.entries.len() == len {
+            return Err(keyring_core::Error::NoEntry);
+        }
+
+        guard.write(&encode(&contents)?).map_err(file_error)
+    }
+
+    fn get_credential(&self) -> keyring_core::Result<Option<Arc<Credential>>> {
+        let contents = decode(self.file.read().map_err(file_error)?)?;
+        if contents.entries.iter().any(|entry| self.matches(entry)) {
+            Ok(None)
+        } else {
+            Err(keyring_core::Error::NoEntry)
+        }
+    }
+
+    fn get_specifiers(&self) -> Option<(String, String)> {
+        Some((self.service.clone(), self.user.clone()))
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Parse the decrypted file contents, treating a missing file as empty.
+fn decode(plaintext: Option<Vec<u8>>) -> keyring_core::Result<Contents> {
+    let Some(plaintext) = plaintext else {
+        return Ok(Contents::default());
+    };
+    serde_json::from_slice(&plaintext)
+        .map_err(|error| keyring_core::Error::BadDataFormat(Vec::new(), Box::new(error)))
+}
+
+/// Serialize the file contents.
+fn encode(contents: &Contents) -> keyring_core::Result<Vec<u8>> {
+    serde_json::to_vec(contents)
+        .map_err(|error| keyring_core::Error::PlatformFailure(Box::new(error)))
+}
+
+/// Map a file error to a keyring error.
+///
+/// Errors that a correct key or accessible file would avoid are reported as
+/// missing storage access, the rest as platform failures.
+fn file_error(error: encrypted_file::Error) -> keyring_core::Error {
+    match error {
+        encrypted_file::Error::Io { .. }
+        | encrypted_file::Error::KeyFile { .. }
+        | encrypted_file::Error::KeyFileTooShort { .. }
+        | encrypted_file::Error::Decrypt => keyring_core::Error::NoStorageAccess(Box::new(error)),
+        encrypted_file::Error::Format | encrypted_file::Error::Crypto => {
+            keyring_core::Error::PlatformFailure(Box::new(error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,82 @@
+use super::*;
+
+/// Create a store in the directory, with a generated key file.
+fn store(dir: &tempfile::TempDir) -> Arc<Store> {
+    let key_file = dir.path().join("keyring.key");
+    encrypted_file::ensure_key_file(&key_file).unwrap();
+    Store::new(dir.path().join("keyring.enc"), KeySource::KeyFile(key_file))
+}
+
+#[test]
+fn roundtrip_across_instances() {
+    let dir = tempfile::tempdir().unwrap();
+
+    store(&dir)
+        .build("service", "user", None)
+        .unwrap()
+        .set_password("password")
+        .unwrap();
+    let password = store(&dir)
+        .build("service", "user", None)
+        .unwrap()
+        .get_password()
+        .unwrap();
+
+    assert_eq!(password, "password");
+}
+
+#[test]
+fn entries_are_independent() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = store(&dir);
+    let first = store.build("service", "first", None).unwrap();
+    let second = store.build("service", "second", None).unwrap();
+
+    first.set_password("one").unwrap();
+    second.set_password("two").unwrap();
+    first.set_password("three").unwrap();
+    second.delete_credential().unwrap();
+
+    assert_eq!(first.get_password().unwrap(), "three");
+    assert!(matches!(
+        second.get_password(),
+        Err(keyring_core::Error::NoEntry)
+    ));
+}
+
+#[test]
+fn missing_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let entry = store(&dir).build("service", "user", None).unwrap();
+
+    assert!(matches!(
+        entry.get_password(),
+        Err(keyring_core::Error::NoEntry)
+    ));
+    assert!(matches!(
+        entry.delete_credential(),
+        Err(keyring_core::Error::NoEntry)
+    ));
+}
+
+#[test]
+fn wrong_key_is_no_storage_access() {
+    let dir = tempfile::tempdir().unwrap();
+    store(&dir)
+        .build("service", "user", None)
+        .unwrap()
+        .set_password("password")
+        .unwrap();
+
+    let entry = Store::new(
+        dir.path().join("keyring.enc"),
+        KeySource::Passphrase("wrong".to_owned()),
+    )
+    .build("service", "user", None)
+    .unwrap();
+
+    assert!(matches!(
+        entry.get_password(),
+        Err(keyring_core::Error::NoStorageAccess(_))
+    ));
+}
@@ -11,6 +11,24 @@ pub trait Workload {
     /// An error that the workload may fail with.
     type Error: Send + Sync + 'static;
 
+    /// Whether the error needs the user to act before the workload is
+    /// retried.
+    fn needs_attention(error: &Self::Error) -> bool {
+        let _ = error;
+        false
+    }
+
+    /// Prepare the workload to run again once the user acted on an error
+    /// that needs attention.
+    ///
+    /// Fails like a run, before the workload is run again.
+    fn resume(
+        item: &Self::Item,
+    ) -> impl std::future::Future<Output = Result<(), Self::Error>> + std::marker::Send {
+        let _ = item;
+        async { Ok(()) }
+    }
+
     /// Run the workload.
     fn run<Notify, NotifyFut>(
         item: &Self::Item,
@@ -47,6 +47,10 @@ pub struct SpawnMonitorsParams<'a, WorkloadItem, RegisterState, WorkloadNotify,
 
     /// Notifier used to report supervisor events.
     pub supervisor_notify: SupervisorNotify,
+
+    /// Signal to resume the monitors parked on an error that needs
+    /// attention.
+    pub resume: std::sync::Arc<tokio::sync::Notify>,
 }
 
 /// Spawn monitor tasks for the provided configs.
@@ -86,6 +90,7 @@ pub fn spawn_monitors<
         workload_notify,
         supervisor_notify,
         mut register_state,
+        resume,
     } = params;
 
     for workload_item in workload_items {
@@ -95,16 +100,22 @@ pub fn spawn_monitors<
 
         let workload_notify = workload_notify.clone();
         let supervisor_notify = supervisor_notify.clone();
+        let resume = std::sync::Arc::clone(&resume);
 
         join_set.spawn(async move {
             let entry = entry.clone();
             let workload_item = workload_item.clone();
 
+            // Set once resumed, until the workload is prepared to run again.
+            let resumed = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+
             let work = {
                 let entry = entry.clone();
                 let workload_item = workload_item.clone();
+                let resumed = std::sync::Arc::clone(&resumed);
                 move || {
                     let workload_item = workload_item.clone();
+                    let resumed = std::sync::Arc::clone(&resumed);
 
                     let workload_notify = {
                         let entry = entry.clone();
@@ -123,6 +134,10 @@ pub fn spawn_monitors<
                     };
 
                     std::panic::AssertUnwindSafe(async move {
+                        if resumed.load(std::sync::atomic::Ordering::Relaxed) {
+                            Workload::resume(&workload_item).await?;
+                            resumed.store(false, std::sync::atomic::Ordering::Relaxed);
+                        }
                         Workload::run(&workload_item, workload_notify).await
                     })
                 }
@@ -152,6 +167,15 @@ pub fn spawn_monitors<
                 notifier: supervisor_notify,
                 sleep: tokio::time::sleep,
                 retries_backoff,
+                needs_attention: Workload::needs_attention,
+                resume: || {
+                    let resume = std::sync::Arc::clone(&resume);
+                    let resumed = std::sync::Arc::clone(&resumed);
+                    async move {
+                        resume.notified().await;
+                        resumed.store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
+                },
             })
             .await
         });
@@ -13,6 +13,18 @@ impl monitoring_core::Workload for Mailbox {
     type Update = imap_checker::MailboxCounts;
     type Error = imap_service::MonitorMailboxError;
 
+    fn needs_attention(error: &Self::Error) -> bool {
+        error.needs_attention()
+    }
+
+    /// The credentials the server rejected may have been fixed, so they
+    /// are resolved again.
+    async fn resume(item: &Self::Item) -> Result<(), Self::Error> {
+        config_bringup::refresh_auth(&item.server)
+            .await
+            .map_err(imap_service::MonitorMailboxError::Credentials)
+    }
+
     async fn run<Notify, NotifyFut>(
         item: &Self::Item,
         notify: Notify,
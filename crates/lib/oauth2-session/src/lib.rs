@@ -72,49 +72,190 @@ impl<
     HasRevocationUrl,
 > Manager<TokenStorage, HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl>
 where
-    HasAuthUrl: oauth2::EndpointState,
-    HasDeviceAuthUrl: oauth2::EndpointState,
-    HasIntrospectionUrl: oauth2::EndpointState,
-    HasRevocationUrl: oauth2::EndpointState,
+    HasAuthUrl: oauth2::EndpointState + Send + Sync,
+    HasDeviceAuthUrl: oauth2::EndpointState + Send + Sync,
+    HasIntrospectionUrl: oauth2::EndpointState + Send + Sync,
+    HasRevocationUrl: oauth2::EndpointState + Send + Sync,
 {
     /// Get an up-to-date access token.
     pub async fn get_access_token(&mut self) -> Result<String, GetTokenError<TokenStorage>> {
-        let mut data = self
+        let data = self
             .storage
             .load()
             .await
             .map_err(GetTokenError::StorageLoad)?;
-
-        if let Some(expires_at) = data.expires_at
-            && std::time::SystemTime::now() + self.expiration_immenance_tolerance > expires_at
-        {
-            let res = self
-                .oauth2_client
-                .exchange_refresh_token(&oauth2::RefreshToken::new(data.refresh_token))
-                .request_async(&self.http_client)
-                .await
-                .map_err(GetTokenError::ExchangeRefreshToken)?;
-
-            let Some(refresh_token) = res.refresh_token() else {
-                return Err(GetTokenError::NoRefreshTokenInResponse);
-            };
-
-            data = oauth2_token_storage_core::Data {
-                access_token: res.access_token().secret().clone(),
-                expires_at: res
-                    .expires_in()
-                    .map(|expires_in| std::time::SystemTime::now() + expires_in),
-                refresh_token: refresh_token.secret().clone(),
-            };
-
-            self.storage
-                .store(data.as_ref())
-                .await
-                .map_err(GetTokenError::StorageStore)?;
+        if !expires_soon(&data, self.expiration_immenance_tolerance) {
+            return Ok(data.access_token);
         }
 
+        // Another process may have refreshed the token meanwhile.
+        self.refresh(None).await
+    }
+
+    /// Get a new access token, even if the stored one has not expired.
+    ///
+    /// Use when the server rejects the stored access token.
+    pub async fn refresh_access_token(&mut self) -> Result<String, GetTokenError<TokenStorage>> {
+        let rejected = self
+            .storage
+            .load()
+            .await
+            .map_err(GetTokenError::StorageLoad)?
+            .access_token;
+
+        // Another process may have replaced the rejected token meanwhile.
+        self.refresh(Some(rejected)).await
+    }
+
+    /// Exchange the refresh token and store the new tokens, unless the
+    /// access token as last stored is neither rejected nor expiring.
+    ///
+    /// The storage is locked meanwhile, so that concurrent processes do not
+    /// exchange the one-time refresh token twice.
+    async fn refresh(
+        &self,
+        rejected: Option<String>,
+    ) -> Result<String, GetTokenError<TokenStorage>> {
+        let tolerance = self.expiration_immenance_tolerance;
+        let (oauth2_client, http_client) = (&self.oauth2_client, &self.http_client);
+        let update = self.storage.update(move |data| {
+            let refresh =
+                rejected.as_ref() == Some(&data.access_token) || expires_soon(&data, tolerance);
+            async move {
+                if !refresh {
+                    return Ok(None);
+                }
+                Self::exchange(oauth2_client, http_client, data.refresh_token)
+                    .await
+                    .map(Some)
+            }
+        });
+        // Boxed to tell the future is `Send`, which the compiler cannot
+        // prove through the borrows of the nested futures.
+        let update: std::pin::Pin<Box<dyn Future<Output = _> + Send + '_>> = Box::pin(update);
+        let data = update.await.map_err(|error| match error {
+            oauth2_token_storage_core::UpdateError::Load(error) => {
+                GetTokenError::StorageLoad(error)
+            }
+            oauth2_token_storage_core::UpdateError::Update(error) => error,
+            oauth2_token_storage_core::UpdateError::Store(error) => {
+                GetTokenError::StorageStore(error)
+            }
+        })?;
+
         Ok(data.access_token)
     }
+
+    /// Exchange the refresh token for new tokens.
+    async fn exchange(
+        oauth2_client: &oauth2::basic::BasicClient<
+            HasAuthUrl,
+            HasDeviceAuthUrl,
+            HasIntrospectionUrl,
+            HasRevocationUrl,
+            oauth2::EndpointSet,
+        >,
+        http_client: &reqwest::Client,
+        refresh_token: String,
+    ) -> Result<oauth2_token_storage_core::Data, GetTokenError<TokenStorage>> {
+        let res = oauth2_client
+            .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token))
+            .request_async(http_client)
+            .await
+            .map_err(GetTokenError::ExchangeRefreshToken)?;
+
+        let Some(refresh_token) = res.refresh_token() else {
+            return Err(GetTokenError::NoRefreshTokenInResponse);
+        };
+
+        Ok(oauth2_token_storage_core::Data {
+            access_token: res.access_token().secret().clone(),
+            expires_at: res
+                .expires_in()
+                .map(|expires_in| std::time::SystemTime::now() + expires_in),
+            refresh_token: refresh_token.secret().clone(),
+        })
+    }
+}
+
+/// Whether the access token expires within the tolerance.
+fn expires_soon(data: &oauth2_token_storage_core::Data, tolerance: std::time::Duration) -> bool {
+    data.expires_at
+        .is_some_and(|expires_at| std::time::SystemTime::now() + tolerance > expires_at)
+}
+
+/// An error that can occur while logging out.
+#[derive(Debug, thiserror::Error)]
+pub enum LogoutError<TokenStorage: oauth2_token_storage_core::TokenStorage> {
+    /// Loading token from storage failed.
+    #[error("unable to load token from storage: {0}")]
+    StorageLoad(oauth2_token_storage_core::LoadError<TokenStorage::LoadError>),
+
+    /// The revocation request could not be built.
+    #[error("unable to prepare token revocation: {0}")]
+    RevocationConfiguration(oauth2::ConfigurationError),
+
+    /// Revoking the token failed.
+    #[error("unable to revoke token: {0}")]
+    Revoke(
+        oauth2::RequestTokenError<
+            oauth2::HttpClientError<reqwest::Error>,
+            oauth2::basic::BasicRevocationErrorResponse,
+        >,
+    ),
+
+    /// Clearing the storage failed.
+    #[error("unable to clear token storage: {0}")]
+    StorageClear(TokenStorage::ClearError),
+}
+
+impl<
+    TokenStorage: oauth2_token_storage_core::TokenStorage,
+    HasAuthUrl,
+    HasDeviceAuthUrl,
+    HasIntrospectionUrl,
+> Manager<TokenStorage, HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, oauth2::EndpointMaybeSet>
+where
+    HasAuthUrl: oauth2::EndpointState,
+    HasDeviceAuthUrl: oauth2::EndpointState,
+    HasIntrospectionUrl: oauth2::EndpointState,
+{
+    /// Revoke the stored refresh token and clear the storage.
+    ///
+    /// The revocation is skipped when the client has no revocation URL or
+    /// there is no stored token. Returns whether the token was revoked.
+    pub async fn logout(&self) -> Result<bool, LogoutError<TokenStorage>> {
+        let data = match self.storage.load().await {
+            Ok(data) => Some(data),
+            Err(oauth2_token_storage_core::LoadError::NoData(_)) => None,
+            Err(err) => return Err(LogoutError::StorageLoad(err)),
+        };
+
+        let revoked = match data {
+            Some(data) if self.oauth2_client.revocation_url().is_some() => {
+                // Revoking the refresh token invalidates the access tokens
+                // issued with it too (RFC 7009, section 2.1).
+                let token = oauth2::StandardRevocableToken::RefreshToken(
+                    oauth2::RefreshToken::new(data.refresh_token),
+                );
+                self.oauth2_client
+                    .revoke_token(token)
+                    .map_err(LogoutError::RevocationConfiguration)?
+                    .request_async(&self.http_client)
+                    .await
+                    .map_err(LogoutError::Revoke)?;
+                true
+            }
+            _ => false,
+        };
+
+        self.storage
+            .clear()
+            .await
+            .map_err(LogoutError::StorageClear)?;
+
+        Ok(revoked)
+    }
 }
 
 /// Manage a new session with a given storage.
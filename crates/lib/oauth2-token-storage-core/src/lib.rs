@@ -1,7 +1,7 @@
 //! OAuth2 token storage interface.
 
 /// The owned token storage data item.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Data {
     /// The access token.
@@ -61,6 +61,22 @@ pub enum LoadError<Error> {
     Internal(Error),
 }
 
+/// An error that can occur while updating the stored data.
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError<LoadErr, StoreErr, Error> {
+    /// Locking or loading the data failed.
+    #[error(transparent)]
+    Load(LoadError<LoadErr>),
+
+    /// The update failed, leaving the data as it was.
+    #[error(transparent)]
+    Update(Error),
+
+    /// Storing the updated data failed.
+    #[error(transparent)]
+    Store(StoreErr),
+}
+
 /// Abstract token storage interface.
 pub trait TokenStorage: Send + Sync {
     /// The error type for store operation.
@@ -83,6 +99,22 @@ pub trait TokenStorage: Send + Sync {
         &'a self,
     ) -> impl std::future::Future<Output = Result<Data, LoadError<Self::LoadError>>> + Send + 'a;
 
+    /// Load the stored data and store the update made of it, if any.
+    ///
+    /// The storages that can be shared between processes hold a lock from
+    /// the load until the store, so that the update is made from the data
+    /// as last stored. Returns the data as stored after the update.
+    fn update<'a, Error, Fut>(
+        &'a self,
+        update: impl FnOnce(Data) -> Fut + Send + 'a,
+    ) -> impl std::future::Future<
+        Output = Result<Data, UpdateError<Self::LoadError, Self::StoreError, Error>>,
+    > + Send
+    + 'a
+    where
+        Error: 'a,
+        Fut: std::future::Future<Output = Result<Option<Data>, Error>> + Send + 'a;
+
     /// Clear stored data.
     fn clear<'a>(
         &'a self,
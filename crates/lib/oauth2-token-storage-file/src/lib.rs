@@ -0,0 +1,131 @@
+//! Encrypted file-based token storage implementation.
+//!
+//! The data is stored as JSON in an [`encrypted_file::EncryptedFile`].
+
+use std::path::Path;
+use std::sync::Arc;
+
+use oauth2_token_storage_core::{Data, DataRef, LoadError, TokenStorage, UpdateError};
+
+pub use encrypted_file::KeySource;
+
+/// Encrypted file-based token storage.
+#[derive(Debug, Clone)]
+pub struct FileTokenStorage {
+    /// The token file.
+    file: Arc<encrypted_file::EncryptedFile>,
+}
+
+impl FileTokenStorage {
+    /// Create a token storage at the given path.
+    ///
+    /// The file is not touched until the first operation.
+    pub fn new(path: impl Into<std::path::PathBuf>, key_source: KeySource) -> Self {
+        Self {
+            file: Arc::new(encrypted_file::EncryptedFile::new(path, key_source)),
+        }
+    }
+
+    /// The path of the token file.
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+}
+
+/// Errors from storage operations.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Accessing the token file failed.
+    #[error("token file: {0}")]
+    File(#[source] encrypted_file::Error),
+
+    /// The token file does not exist.
+    #[error("token file does not exist")]
+    Missing,
+
+    /// JSON serialization failed.
+    #[error("JSON serialization failed: {0}")]
+    Json(#[source] serde_json::Error),
+}
+
+impl TokenStorage for FileTokenStorage {
+    type StoreError = Error;
+    type LoadError = Error;
+    type ClearError = Error;
+
+    async fn store<'a>(&'a self, data: DataRef<'a>) -> Result<(), Self::StoreError> {
+        let json = serde_json::to_vec(&data).map_err(Error::Json)?;
+        let file = Arc::clone(&self.file);
+        tokio::task::spawn_blocking(move || file.write(&json).map_err(Error::File))
+            .await
+            .unwrap()
+    }
+
+    async fn load(&self) -> Result<Data, oauth2_token_storage_core::LoadError<Self::LoadError>> {
+        let file = Arc::clone(&self.file);
+        let json = tokio::task::spawn_blocking(move || file.read())
+            .await
+            .unwrap()
+            .map_err(Error::File)
+            .map_err(oauth2_token_storage_core::LoadError::Internal)?
+            .ok_or(oauth2_token_storage_core::LoadError::NoData(Error::Missing))?;
+
+        serde_json::from_slice(&json)
+            .map_err(Error::Json)
+            .map_err(oauth2_token_storage_core::LoadError::Internal)
+    }
+
+    /// The file stays locked from the load until the store, so concurrent
+    /// processes update it one after the other.
+    async fn update<'a, E, Fut>(
+        &'a self,
+        update: impl FnOnce(Data) -> Fut + Send + 'a,
+    ) -> Result<Data, UpdateError<Self::LoadError, Self::StoreError, E>>
+    where
+        E: 'a,
+        Fut: std::future::Future<Output = Result<Option<Data>, E>> + Send + 'a,
+    {
+        let file = Arc::clone(&self.file);
+        let (guard, json) = tokio::task::spawn_blocking(move || {
+            let guard = file.lock_owned()?;
+            let json = guard.read()?;
+            Ok((guard, json))
+        })
+        .await
+        .unwrap()
+        .map_err(Error::File)
+        .map_err(LoadError::Internal)
+        .map_err(UpdateError::Load)?;
+        let json = json
+            .ok_or(LoadError::NoData(Error::Missing))
+            .map_err(UpdateError::Load)?;
+        let data: Data = serde_json::from_slice(&json)
+            .map_err(Error::Json)
+            .map_err(LoadError::Internal)
+            .map_err(UpdateError::Load)?;
+
+        let Some(updated) = update(data.clone()).await.map_err(UpdateError::Update)? else {
+            return Ok(data);
+        };
+
+        let json = serde_json::to_vec(&updated.as_ref())
+            .map_err(Error::Json)
+            .map_err(UpdateError::Store)?;
+        tokio::task::spawn_blocking(move || guard.write(&json))
+            .await
+            .unwrap()
+            .map_err(Error::File)
+            .map_err(UpdateError::Store)?;
+        Ok(updated)
+    }
+
+    async fn clear(&self) -> Result<(), Self::ClearError> {
+        let file = Arc::clone(&self.file);
+        tokio::task::spawn_blocking(move || file.remove().map_err(Error::File))
+            .await
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests;
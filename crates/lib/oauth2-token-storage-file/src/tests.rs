@@ -0,0 +1,125 @@
+use oauth2_token_storage_core::LoadError;
+
+use super::*;
+
+/// Sample token data.
+fn sample() -> Data {
+    Data {
+        access_token: "access".to_owned(),
+        expires_at: Some(std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1)),
+        refresh_token: "refresh".to_owned(),
+    }
+}
+
+/// Create a key file with the given contents in the directory.
+fn key_file(dir: &tempfile::TempDir, contents: &[u8]) -> KeySource {
+    let path = dir.path().join("key");
+    std::fs::write(&path, contents).unwrap();
+    KeySource::KeyFile(path)
+}
+
+#[tokio::test]
+async fn roundtrip() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileTokenStorage::new(dir.path().join("token"), key_file(&dir, &[b's'; 32]));
+
+    storage.store(sample().as_ref()).await.unwrap();
+    let data = storage.load().await.unwrap();
+
+    assert_eq!(data.access_token, "access");
+    assert_eq!(data.refresh_token, "refresh");
+    assert_eq!(data.expires_at, sample().expires_at);
+}
+
+#[tokio::test]
+async fn wrong_key_fails_to_decrypt() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("token");
+
+    FileTokenStorage::new(&path, key_file(&dir, &[b's'; 32]))
+        .store(sample().as_ref())
+        .await
+        .unwrap();
+    let result = FileTokenStorage::new(&path, key_file(&dir, &[b'o'; 32]))
+        .load()
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(LoadError::Internal(Error::File(
+            encrypted_file::Error::Decrypt
+        )))
+    ));
+}
+
+#[tokio::test]
+async fn missing_file_is_no_data() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileTokenStorage::new(dir.path().join("token"), key_file(&dir, &[b's'; 32]));
+
+    let result = storage.load().await;
+
+    assert!(matches!(result, Err(LoadError::NoData(Error::Missing))));
+}
+
+#[tokio::test]
+async fn clear_removes_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileTokenStorage::new(dir.path().join("token"), key_file(&dir, &[b's'; 32]));
+
+    storage.store(sample().as_ref()).await.unwrap();
+    storage.clear().await.unwrap();
+    storage.clear().await.unwrap();
+
+    assert!(!storage.path().exists());
+    assert!(matches!(storage.load().await, Err(LoadError::NoData(_))));
+}
+
+#[tokio::test]
+async fn update_keeps_the_data_unless_changed() {
+    let dir = tempfile::tempdir().unwrap();
+    let storage = FileTokenStorage::new(dir.path().join("token"), key_file(&dir, &[b's'; 32]));
+    storage.store(sample().as_ref()).await.unwrap();
+
+    let data = storage.update(async |_| Ok::<_, ()>(None)).await.unwrap();
+
+    assert_eq!(data.access_token, "access");
+    assert_eq!(storage.load().await.unwrap().access_token, "access");
+}
+
+#[tokio::test]
+async fn concurrent_updates_see_each_other() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("token");
+    let first = FileTokenStorage::new(&path, key_file(&dir, &[b's'; 32]));
+    let second = FileTokenStorage::new(&path, key_file(&dir, &[b's'; 32]));
+    first.store(sample().as_ref()).await.unwrap();
+
+    let (locked, wait_locked) = tokio::sync::oneshot::channel();
+    let first = tokio::spawn(async move {
+        first
+            .update(async |data| {
+                locked.send(()).unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                Ok::<_, ()>(Some(Data {
+                    access_token: "refreshed".to_owned(),
+                    ..data
+                }))
+            })
+            .await
+            .unwrap()
+    });
+    wait_locked.await.unwrap();
+
+    // The second update waits for the first one to store its data.
+    let data = second
+        .update(async |data| {
+            assert_eq!(data.access_token, "refreshed");
+            Ok::<_, ()>(None)
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(data.access_token, "refreshed");
+    assert_eq!(first.await.unwrap().access_token, "refreshed");
+}
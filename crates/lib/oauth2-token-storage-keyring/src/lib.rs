@@ -2,9 +2,10 @@
 
 use std::sync::Arc;
 
-use oauth2_token_storage_core::{Data, DataRef, TokenStorage};
+use oauth2_token_storage_core::{Data, DataRef, TokenStorage, UpdateError};
 
 /// Keyring-based token storage.
+#[derive(Debug)]
 pub struct KeyringTokenStorage {
     /// The entry to store the token at.
     pub entry: Arc<keyring_core::Entry>,
@@ -78,6 +79,25 @@ impl TokenStorage for KeyringTokenStorage {
         .unwrap()
     }
 
+    /// The keyring entry is not locked, so a concurrent update may be lost.
+    async fn update<'a, E, Fut>(
+        &'a self,
+        update: impl FnOnce(Data) -> Fut + Send + 'a,
+    ) -> Result<Data, UpdateError<Self::LoadError, Self::StoreError, E>>
+    where
+        E: 'a,
+        Fut: std::future::Future<Output = Result<Option<Data>, E>> + Send + 'a,
+    {
+        let data = self.load().await.map_err(UpdateError::Load)?;
+        let Some(updated) = update(data.clone()).await.map_err(UpdateError::Update)? else {
+            return Ok(data);
+        };
+        self.store(updated.as_ref())
+            .await
+            .map_err(UpdateError::Store)?;
+        Ok(updated)
+    }
+
     async fn clear(&self) -> Result<(), Self::ClearError> {
         let entry = Arc::clone(&self.entry);
         tokio::task::spawn_blocking(move || {
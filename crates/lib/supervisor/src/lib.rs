@@ -37,6 +37,14 @@ pub enum SupervisorEvent<T, E> {
         next_retry_in: Duration,
     },
 
+    /// The work returned an error that retrying will not fix.
+    ///
+    /// It won't be restarted until resumed.
+    NeedsAttention {
+        /// The error that was returned by the work future.
+        error: E,
+    },
+
     /// The work panicked.
     ///
     /// It will be restarted.
@@ -51,7 +59,7 @@ pub enum SupervisorEvent<T, E> {
 
 /// Parameters for `run`. Generic over the work and notifier closure types
 /// and their returned futures. Runs a single async work item and reports events.
-pub struct Params<Work, Notifier, Sleep> {
+pub struct Params<Work, Notifier, Sleep, NeedsAttention, Resume> {
     /// The work to run.
     pub work: Work,
 
@@ -63,11 +71,29 @@ pub struct Params<Work, Notifier, Sleep> {
 
     /// The exponential backoff configuration for the retries.
     pub retries_backoff: exp_backoff::State,
+
+    /// Tells whether the error needs the user to act before retrying.
+    pub needs_attention: NeedsAttention,
+
+    /// Waits for the user to act on an error that needs attention.
+    pub resume: Resume,
 }
 
 /// Run once: notify start, run work, notify result.
-pub async fn run<Work, WorkFut, Notifier, NotifierFut, Sleep, SleepFut, Value, Error>(
-    mut params: Params<Work, Notifier, Sleep>,
+pub async fn run<
+    Work,
+    WorkFut,
+    Notifier,
+    NotifierFut,
+    Sleep,
+    SleepFut,
+    NeedsAttention,
+    Resume,
+    ResumeFut,
+    Value,
+    Error,
+>(
+    mut params: Params<Work, Notifier, Sleep, NeedsAttention, Resume>,
 ) where
     Work: FnMut() -> WorkFut,
     WorkFut: Future<Output = Result<Value, Error>> + UnwindSafe,
@@ -75,7 +101,12 @@ pub async fn run<Work, WorkFut, Notifier, NotifierFut, Sleep, SleepFut, Value, E
     NotifierFut: Future<Output = ()>,
     Sleep: FnMut(Duration) -> SleepFut,
     SleepFut: Future<Output = ()>,
+    NeedsAttention: FnMut(&Error) -> bool,
+    Resume: FnMut() -> ResumeFut,
+    ResumeFut: Future<Output = ()>,
 {
+    let initial_backoff = params.retries_backoff.clone();
+
     loop {
         (params.notifier)(SupervisorEvent::Started).await;
 
@@ -88,6 +119,12 @@ pub async fn run<Work, WorkFut, Notifier, NotifierFut, Sleep, SleepFut, Value, E
                 (params.notifier)(SupervisorEvent::Done { value }).await;
                 return;
             }
+            Ok(Err(error)) if (params.needs_attention)(&error) => {
+                (params.notifier)(SupervisorEvent::NeedsAttention { error }).await;
+                (params.resume)().await;
+                params.retries_backoff = initial_backoff.clone();
+                continue;
+            }
             Ok(Err(error)) => {
                 let delay = params.retries_backoff.advance();
                 (params.notifier)(SupervisorEvent::Error {
@@ -15,6 +15,9 @@ pub struct EntryState {
 
     /// Whether the mailbox is active or not.
     pub active: bool,
+
+    /// Whether the mailbox monitor is stopped until the user acts.
+    pub needs_attention: bool,
 }
 
 /// Render the main UI frame.
@@ -31,7 +34,7 @@ where
             .constraints([Constraint::Length(1), Constraint::Min(1)])
             .split(frame.area());
 
-        let header = Paragraph::new("Mail Notifier — press q to quit")
+        let header = Paragraph::new("Mail Notifier — press r to retry, q to quit")
             .style(Style::default().fg(Color::Yellow));
         frame.render_widget(header, chunks[0]);
 
@@ -41,11 +44,19 @@ where
             entries
                 .iter()
                 .map(|entry| {
-                    ListItem::new(format!("{} — {} new", entry.name, entry.unread)).style({
+                    let text = if entry.needs_attention {
+                        format!("{} — needs attention", entry.name)
+                    } else {
+                        format!("{} — {} new", entry.name, entry.unread)
+                    };
+                    ListItem::new(text).style({
                         let mut s = Style::new();
                         if !entry.active {
                             s = s.italic();
                         }
+                        if entry.needs_attention {
+                            s = s.fg(Color::Red);
+                        }
                         s
                     })
                 })
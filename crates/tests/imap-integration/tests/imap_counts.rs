@@ -4,6 +4,7 @@ use std::error::Error;
 
 const IMAP_USER: &str = "test";
 const IMAP_PASSWORD: &str = "secret";
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn imap_counts_roundtrip() -> Result<(), Box<dyn Error + Send + Sync>> {
@@ -27,7 +28,7 @@ async fn imap_counts_roundtrip() -> Result<(), Box<dyn Error + Send + Sync>> {
     .await?;
 
     let mailbox = imap_utf7::ImapUtf7Str::new("INBOX")?;
-    let before = imap_checker::fetch_counts(&mut session, mailbox).await?;
+    let before = imap_checker::fetch_counts(&mut session, mailbox, TIMEOUT).await?;
 
     session
         .append(
@@ -39,7 +40,7 @@ async fn imap_counts_roundtrip() -> Result<(), Box<dyn Error + Send + Sync>> {
         .await?;
     session.noop().await?;
 
-    let after = imap_checker::fetch_counts(&mut session, mailbox).await?;
+    let after = imap_checker::fetch_counts(&mut session, mailbox, TIMEOUT).await?;
 
     assert_eq!(after.total, before.total + 1);
     assert!(after.unread >= before.unread);